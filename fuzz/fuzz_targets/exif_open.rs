@@ -0,0 +1,21 @@
+#![no_main]
+#![allow(dead_code)]
+
+// cargo +nightly fuzz run exif_open
+// Az EXIF olvasó modulok közvetlenül a fő crate forrásából; a kiinduló korpusz a corpus/exif_open
+#[path = "../../src/exif_my.rs"]
+mod exif_my;
+#[path = "../../src/makernote.rs"]
+mod makernote;
+#[path = "../../src/ifd.rs"]
+mod ifd;
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = exif_my::ExifBlock::default().open(data, data.len());
+    // a megadott hossz eltérhet a szelet hosszától
+    if let Some((&len, rest)) = data.split_first() {
+        let _ = exif_my::ExifBlock::default().open(rest, len as usize * 4);
+    }
+});
@@ -0,0 +1,190 @@
+use crate::colors::ColorSettings;
+use crate::ImageViewer;
+
+// Alfa csatorna: megjelenítési módok (csak a képernyőn) és szerkesztések (mentéskor is).
+// A szerkesztések a varázspálca után, a feldolgozott képen futnak, ebben a sorrendben:
+// invertálás, küszöb, a rejtett színek kitöltése, végül az alfa eltávolítása a matt színre simítva.
+
+#[derive(Clone, Copy, PartialEq, Default)]
+pub enum AlphaView {
+    #[default] Normal, // a háttér mintán
+    Alpha,             // az alfa szürkeárnyalatban
+    Straight,          // a színek az alfa nélkül, a rejtett színekkel együtt
+    Premultiplied,     // a színek az alfával szorozva, feketén
+    Matte,             // a matt színen, ahogy alfa nélküli formátumba mentve látszik
+}
+
+impl AlphaView {
+    pub const ALL: [(AlphaView, &'static str, &'static str); 5] = [
+        (AlphaView::Normal, "Normal", "Composite over the background pattern"),
+        (AlphaView::Alpha, "Alpha", "Alpha channel as grayscale"),
+        (AlphaView::Straight, "Straight", "Color channels ignoring alpha, shows hidden colors"),
+        (AlphaView::Premultiplied, "Premultiplied", "Color channels multiplied by alpha"),
+        (AlphaView::Matte, "Matte", "Composite over the matte color, as saved without alpha"),
+    ];
+}
+
+impl ColorSettings {
+    pub fn has_alpha_edits(&self) -> bool {
+        self.alpha_invert || self.alpha_threshold >= 0.001 || self.alpha_fill || self.remove_alpha
+    }
+}
+
+fn blend(c: u8, matte: u8, a: u8) -> u8 {
+    ((c as u32 * a as u32 + matte as u32 * (255 - a as u32) + 127) / 255) as u8
+}
+
+// Az átlátszó pixelek színét a legközelebbi látható szomszédokból tölti ki rétegenként,
+// így átméretezéskor és a szűrők után nem szivárog be sötét vagy tarka szegély
+fn fill_transparent(img: &mut image::RgbaImage) {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width as i32, height as i32);
+    let mut filled: Vec<bool> = img.pixels().map(|p| p[3] > 0).collect();
+    if !filled.contains(&true) {
+        return;
+    }
+    let mut queued = filled.clone();
+    let neighbours = |idx: usize| {
+        let (x, y) = ((idx % width as usize) as i32, (idx / width as usize) as i32);
+        (-1..=1).flat_map(move |dy| (-1..=1).map(move |dx| (x + dx, y + dy)))
+            .filter(move |&(nx, ny)| nx >= 0 && ny >= 0 && nx < w && ny < h && (nx, ny) != (x, y))
+            .map(move |(nx, ny)| ny as usize * width as usize + nx as usize)
+    };
+    let mut frontier: Vec<usize> = (0..filled.len()).filter(|&i| filled[i]).collect();
+    let raw: &mut [u8] = img.as_mut();
+    while !frontier.is_empty() {
+        let mut next = Vec::new();
+        for &idx in &frontier {
+            for n in neighbours(idx) {
+                if !queued[n] {
+                    queued[n] = true;
+                    next.push(n);
+                }
+            }
+        }
+        let colors: Vec<[u8; 3]> = next.iter().map(|&idx| {
+            let mut sum = [0u32; 3];
+            let mut count = 0;
+            for n in neighbours(idx).filter(|&n| filled[n]) {
+                for i in 0..3 {
+                    sum[i] += raw[n * 4 + i] as u32;
+                }
+                count += 1;
+            }
+            sum.map(|s| (s / count.max(1)) as u8)
+        }).collect();
+        for (&idx, color) in next.iter().zip(colors) {
+            raw[idx * 4..idx * 4 + 3].copy_from_slice(&color);
+            filled[idx] = true;
+        }
+        frontier = next;
+    }
+}
+
+pub fn apply_alpha_edits(img: &mut image::RgbaImage, colset: &ColorSettings) {
+    if !colset.has_alpha_edits() {
+        return;
+    }
+    let threshold = colset.alpha_threshold * 255.0;
+    for pixel in img.pixels_mut() {
+        if colset.alpha_invert {
+            pixel[3] = 255 - pixel[3];
+        }
+        if threshold >= 0.001 * 255.0 {
+            pixel[3] = if pixel[3] as f32 >= threshold { 255 } else { 0 };
+        }
+    }
+    if colset.alpha_fill {
+        fill_transparent(img);
+    }
+    if colset.remove_alpha {
+        flatten(img, colset.matte_color);
+    }
+}
+
+pub fn flatten(img: &mut image::RgbaImage, matte: [u8; 3]) {
+    for pixel in img.pixels_mut() {
+        for i in 0..3 {
+            pixel[i] = blend(pixel[i], matte[i], pixel[3]);
+        }
+        pixel[3] = 255;
+    }
+}
+
+// Alfa nélküli formátumokba (JPEG, PPM) mentés előtt, különben a rejtett színek látszanának
+pub fn flatten_for_save(img: &mut image::DynamicImage, matte: [u8; 3]) {
+    if img.color().has_alpha() {
+        let mut rgba = img.to_rgba8();
+        flatten(&mut rgba, matte);
+        *img = image::DynamicImage::ImageRgba8(rgba);
+    }
+}
+
+// Csak a megjelenítéshez, a mentést nem érinti
+pub fn apply_alpha_view(img: &mut image::RgbaImage, view: AlphaView, matte: [u8; 3]) {
+    match view {
+        AlphaView::Normal => {}
+        AlphaView::Matte => flatten(img, matte),
+        _ => {
+            for pixel in img.pixels_mut() {
+                let a = pixel[3];
+                match view {
+                    AlphaView::Alpha => {
+                        pixel[0] = a;
+                        pixel[1] = a;
+                        pixel[2] = a;
+                    }
+                    AlphaView::Premultiplied => {
+                        for i in 0..3 {
+                            pixel[i] = blend(pixel[i], 0, a);
+                        }
+                    }
+                    _ => {}
+                }
+                pixel[3] = 255;
+            }
+        }
+    }
+}
+
+impl ImageViewer {
+    pub fn alpha_panel(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        ui.horizontal(|ui| {
+            ui.label("View:");
+            for (view, name, hint) in AlphaView::ALL {
+                if ui.selectable_label(self.alpha_view == view, name).on_hover_text(hint).clicked() {
+                    self.alpha_view = view;
+                    *changed = true;
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Matte Color");
+            let mut color = egui::Color32::from_rgb(self.color_settings.matte_color[0], self.color_settings.matte_color[1], self.color_settings.matte_color[2]);
+            if ui.color_edit_button_srgba(&mut color).on_hover_text("Background for the matte view, Remove alpha and saving to formats without alpha").changed() {
+                self.color_settings.matte_color = [color.r(), color.g(), color.b()];
+                *changed = true;
+            }
+        });
+        let cs = &mut self.color_settings;
+        ui.horizontal(|ui| {
+            *changed |= ui.checkbox(&mut cs.alpha_invert, "Invert alpha").changed();
+            *changed |= ui.checkbox(&mut cs.alpha_fill, "Fill hidden colors")
+                .on_hover_text("Replace the color of fully transparent pixels with the nearest visible color, removes halos")
+                .changed();
+            *changed |= ui.checkbox(&mut cs.remove_alpha, "Remove alpha")
+                .on_hover_text("Flatten onto the matte color")
+                .changed();
+        });
+        ui.horizontal(|ui| {
+            // a CPU-n számoljuk, ezért csak elengedéskor
+            let res = ui.add(egui::Slider::new(&mut cs.alpha_threshold, 0.0..=1.0).text("Alpha threshold"))
+                .on_hover_text("Pixels below become fully transparent, the rest opaque. 0: off");
+            *changed |= res.drag_stopped() || (res.changed() && !ui.input(|i| i.pointer.any_down()));
+            if ui.button("⟲").on_hover_text("Reset Alpha threshold").clicked() {
+                cs.alpha_threshold = 0.0;
+                *changed = true;
+            }
+        });
+    }
+}
@@ -2,6 +2,10 @@ use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::f32::consts::PI;
 use std::sync::atomic::{AtomicU32, Ordering};
+use crate::curves::ToneCurve;
+use crate::levels::Levels;
+use crate::hsl::{HslBand, HSL_BANDS};
+use crate::lens::LensCorrection;
 
 const TWO_PI: f32 = PI * 2.0;
 
@@ -44,7 +48,7 @@ fn r(th: f32) -> f32 {
     (ra * rb) / (rb * th.cos()).hypot(ra * th.sin())
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(default)]
 pub struct ColorSettings {
     //pub is_default: bool,
@@ -59,12 +63,37 @@ pub struct ColorSettings {
     pub invert: bool,
     pub sharpen_amount: f32, // -1.0 .. 5.0 // realy image setting
     pub sharpen_radius: f32, // 0.2 .. 3.0 // realy image setting
+    pub sharpen_threshold: f32, // 0.0 .. 64.0 szint, ennél kisebb különbséget nem élesít
+    pub sharpen_luma: bool,     // csak a világosság élesítése, nincs színes szegély
     pub rotate: Rotate, // realy image setting
     pub oklab: bool,
     pub transparent_color: [u8; 4],
     pub transparency_tolerance: f32, // 0.0 - 1.0
     pub use_transparency: bool,
     pub rough_transparency: bool,
+    pub transparency_flood: bool,        // varázspálca: csak a kiindulóponttal összefüggő terület
+    #[serde(skip)]
+    pub flood_seed: Option<[f32; 2]>,    // a kiindulópont a kép méretéhez képest (0.0 .. 1.0), csak az adott képhez
+    #[serde(skip)]
+    pub flood_mask: Option<std::sync::Arc<crate::magic_wand::FloodMask>>, // az előnézeten számolt maszk, mentéskor ezt nagyítjuk
+    pub transparency_feather: f32,       // 0.0 .. 5.0 (%), a maszk szélének elmosása
+    pub alpha_invert: bool,
+    pub alpha_threshold: f32,            // 0.0 .. 1.0, 0: nincs küszöb
+    pub alpha_fill: bool,                // az átlátszó pixelek színének kitöltése (szegély ellen)
+    pub remove_alpha: bool,              // a matt színre simítva
+    pub matte_color: [u8; 3],            // alfa nélküli formátumokba mentéskor is ez a háttér
+    pub curves: [ToneCurve; 4], // 0: RGB (közös), 1..3: R, G, B
+    pub levels: [Levels; 4],    // mint a görbéknél
+    pub temperature: f32,       // 2000 .. 12000 K, 6500 semleges
+    pub tint: f32,              // -1.0 (zöld) .. 1.0 (bíbor)
+    pub hsl: [HslBand; HSL_BANDS], // vörösök, narancsok, sárgák, zöldek, türkizek, kékek, lilák, bíborok
+    pub vibrance: f32,   // -1.0 .. 1.0 // térbeli korrekciók, nem a LUT része
+    pub clarity: f32,    // -1.0 .. 1.0
+    pub shadows: f32,    // -1.0 .. 1.0
+    pub highlights: f32, // -1.0 .. 1.0
+    pub denoise_luma: f32,   // 0.0 .. 1.0 // élesítés előtt
+    pub denoise_chroma: f32, // 0.0 .. 1.0
+    pub lens: LensCorrection, // torzítás, színi hiba, vignetta; minden más szűrő előtt
 }
 impl Default for ColorSettings {
     fn default() -> Self {
@@ -80,12 +109,35 @@ impl Default for ColorSettings {
             invert: false,
             sharpen_amount: 0.0, // -1.0 .. 5.0
             sharpen_radius: 0.2, // 0.2 .. 3.0
+            sharpen_threshold: 0.0,
+            sharpen_luma: false,
             rotate: Rotate::Rotate0,
             oklab: true,
             transparent_color: [255, 255, 255, 0],
             transparency_tolerance: 0.0,
             use_transparency: false,
             rough_transparency: false,
+            transparency_flood: false,
+            flood_seed: None,
+            flood_mask: None,
+            transparency_feather: 0.0,
+            alpha_invert: false,
+            alpha_threshold: 0.0,
+            alpha_fill: false,
+            remove_alpha: false,
+            matte_color: [255, 255, 255],
+            curves: Default::default(),
+            levels: Default::default(),
+            temperature: crate::white_balance::NEUTRAL_TEMPERATURE,
+            tint: 0.0,
+            hsl: Default::default(),
+            vibrance: 0.0,
+            clarity: 0.0,
+            shadows: 0.0,
+            highlights: 0.0,
+            denoise_luma: 0.0,
+            denoise_chroma: 0.0,
+            lens: LensCorrection::default(),
         }
     }
 }
@@ -99,13 +151,54 @@ impl ColorSettings {
             self.hue_shift.abs() < 0.001 &&
             self.saturation.abs() < 0.001 &&
             self.show_r && self.show_g && self.show_b &&
-            !self.invert)
+            !self.invert &&
+            !self.has_white_balance() &&
+            !self.has_hsl() &&
+            !self.has_tone())
     }
+    pub fn has_curves(&self) -> bool {
+        !self.curves.iter().all(|c| c.is_identity())
+    }
+
+    pub fn has_levels(&self) -> bool {
+        !self.levels.iter().all(|l| l.is_identity())
+    }
+
+    // Szintek vagy görbék: mindkettő csatornánkénti 1D leképezés, a GPU egy táblázatként kapja meg
+    pub fn has_tone(&self) -> bool {
+        self.has_levels() || self.has_curves()
+    }
+
+    // Előbb a csatorna, utána a közös szintek, majd ugyanígy a görbék
+    pub fn apply_tone(&self, value: f32, channel: usize) -> f32 {
+        let value = self.levels[0].apply(self.levels[channel + 1].apply(value));
+        self.curves[0].eval(self.curves[channel + 1].eval(value))
+    }
+
     pub fn is_blured(&self) -> bool {
-        self.sharpen_amount.abs() >= 0.001 || self.use_transparency
+        self.sharpen_amount.abs() >= 0.001 || self.use_transparency || self.has_local_adjustments() || self.has_denoise() || self.has_lens_correction() || self.has_alpha_edits()
     }
 
     pub fn convert(&self, color: &mut [f32; 4] ) {
+        self.convert_before_tone(color);
+        if self.has_tone() {
+            for (c, v) in color.iter_mut().take(3).enumerate() {
+                *v = self.apply_tone(*v, c);
+            }
+        }
+        if !self.show_r { color[0] = 0.0 };
+        if !self.show_g { color[1] = 0.0 };
+        if !self.show_b { color[2] = 0.0 };
+    }
+
+    // A szintek és görbék előtti állapot: a pipetták és az automatikus szintek ezt mérik
+    pub fn convert_before_tone(&self, color: &mut [f32; 4]) {
+        if self.has_white_balance() {
+            let gains = self.white_balance_gains();
+            for (v, g) in color.iter_mut().zip(gains) {
+                *v = (*v * g).clamp(0.0, 1.0);
+            }
+        }
         if self.invert {
             *color = [1.0 - color[0], 1.0 - color[1], 1.0 - color[2], color[3]];
         }
@@ -118,15 +211,16 @@ impl ColorSettings {
             }
             *channel = channel.clamp(0.0, 1.0);
         }
-        if !self.show_r { color[0] = 0.0 };
-        if !self.show_g { color[1] = 0.0 };
-        if !self.show_b { color[2] = 0.0 };
     }
 
     pub fn apply_color_settings(&self, rgb: [f32; 4] ) -> [f32; 4] {
         
         let mut hsv = if self.oklab { Self::rgb_to_oklab(rgb) } else { Self::rgb_to_hsv(rgb) };
 
+        if self.has_hsl() {
+            self.apply_hsl(&mut hsv);
+        }
+
         let shift = self.hue_shift / 360.0;
         hsv[0] = (hsv[0] + shift).rem_euclid(1.0); // Biztonságos körbefordulás Rustban
 
@@ -243,16 +337,41 @@ impl ColorSettings {
 
 }
 
+// (2r+1)x(2r+1) Gauss-mag, sorfolytonosan; az élesítés és a térbeli korrekciók közös magja
+pub fn gaussian_weights(r: i32, sigma: f32) -> Vec<f32> {
+    let s2 = 2.0 * sigma * sigma;
+    let d = (2 * r + 1) as usize; // A teljes kernel átmérője
+    let mut weights = vec![0.0f32; d * d];
+    for dy in -r..=r {
+        for dx in -r..=r {
+            // Eltoljuk az indexet 0..2r tartományba a tároláshoz
+            let iy = (dy + r) as usize;
+            let ix = (dx + r) as usize;
+            weights[iy * d + ix] = (-((dx * dx + dy * dy) as f32) / s2).exp();
+        }
+    }
+    weights
+}
+
 #[derive(Clone)]
 pub struct Lut4ColorSettings {
     pub size : usize,
     pub data : Vec<u8>, // RGBA adatok
     pub sharpen_amount: f32, // -1.0 .. 5.0 // realy image setting
     pub sharpen_radius: f32, // 0.2 .. 3.0 // realy image setting
+    pub sharpen_threshold: f32,
+    pub sharpen_luma: bool,
     pub transparent_color: [u8; 4],
     pub transparency_tolerance: f32, // 0.0 - 1.0
     pub use_transparency: bool,
     pub rough_transparency: bool,
+    pub vibrance: f32,
+    pub clarity: f32,
+    pub shadows: f32,
+    pub highlights: f32,
+    pub denoise_luma: f32,
+    pub denoise_chroma: f32,
+    pub lens: LensCorrection,
 }
 
 impl Lut4ColorSettings {
@@ -279,10 +398,19 @@ impl Lut4ColorSettings {
             data:data,
             sharpen_amount:0.0,
             sharpen_radius:0.0,
+            sharpen_threshold: 0.0,
+            sharpen_luma: false,
             transparent_color: [255, 255, 255, 0],
             transparency_tolerance: 0.0, // 0.0 - 1.0
             use_transparency: false,
             rough_transparency: false,
+            vibrance: 0.0,
+            clarity: 0.0,
+            shadows: 0.0,
+            highlights: 0.0,
+            denoise_luma: 0.0,
+            denoise_chroma: 0.0,
+            lens: LensCorrection::default(),
             }
     }
     
@@ -311,9 +439,18 @@ impl Lut4ColorSettings {
         }
         self.sharpen_amount = colset.sharpen_amount;
         self.sharpen_radius = colset.sharpen_radius;
+        self.sharpen_threshold = colset.sharpen_threshold;
+        self.sharpen_luma = colset.sharpen_luma;
         self.transparent_color = colset.transparent_color;
         self.transparency_tolerance = colset.transparency_tolerance;
-        self.use_transparency = colset.use_transparency;
+        self.use_transparency = colset.use_transparency && !colset.transparency_flood; // varázspálcánál a maszk külön
+        self.vibrance = colset.vibrance;
+        self.clarity = colset.clarity;
+        self.shadows = colset.shadows;
+        self.highlights = colset.highlights;
+        self.denoise_luma = colset.denoise_luma;
+        self.denoise_chroma = colset.denoise_chroma;
+        self.lens = colset.lens;
     }
 
     pub fn color_to_alpha(&self,  pixel: & mut image::Rgba<u8> ) {
@@ -407,24 +544,7 @@ impl Lut4ColorSettings {
     
     fn calculate_weights(&self, r: i32)  -> Vec<f32> {
         let sigma = (self.sharpen_radius * 0.5f32).max(0.5f32);
-        let s2 = 2.0 * sigma * sigma;
-        let d = (2 * r + 1) as usize; // A teljes kernel átmérője
-        let mut weights = vec![0.0f32; d * d];
-        for dy in 0..=r {
-            for dx in 0..=r {
-                // Eltoljuk az indexet 0..2r tartományba a tároláshoz
-                let mut iy = (dy + r) as usize;
-                let mut ix = (dx + r) as usize;
-                let weight = (-((dx * dx + dy * dy) as f32) / s2).exp();
-                weights[iy * d + ix] = weight;
-                weights[ix * d + iy] = weight;
-                iy = (r - dy) as usize;
-                ix = (r - dx) as usize;
-                weights[iy * d + ix] = weight;
-                weights[ix * d + iy] = weight;
-            }
-        }
-        weights
+        gaussian_weights(r, sigma)
     }
     
     //////////////////////////////
@@ -460,10 +580,24 @@ impl Lut4ColorSettings {
 
         if total_w > 0.0 {
             let center = source_img.get_pixel(cx, cy);
+            let mut detail = [0.0f32; 3];
             for i in 0..3 {
-                let avg = sum[i] / total_w;
-                let detail = (center[i] as f32) - avg;
-                let val = (center[i] as f32 + detail * self.sharpen_amount).clamp(0.0, 255.0);
+                detail[i] = (center[i] as f32) - sum[i] / total_w;
+            }
+            if self.sharpen_luma {
+                // mindhárom csatorna ugyanannyit változik, így a színárnyalat marad
+                let d = detail[0] * 0.299 + detail[1] * 0.587 + detail[2] * 0.114;
+                detail = [d; 3];
+            }
+            for i in 0..3 {
+                let mut d = detail[i];
+                // életlen maszk küszöb: a kis különbség (zaj, JPEG blokkok) lágyan kimarad; elmosásnál nem hat
+                if self.sharpen_amount > 0.0 && self.sharpen_threshold > 0.0 {
+                    let t = self.sharpen_threshold;
+                    let x = ((d.abs() - t * 0.5) / (t * 0.5)).clamp(0.0, 1.0);
+                    d *= x * x * (3.0 - 2.0 * x);
+                }
+                let val = (center[i] as f32 + d * self.sharpen_amount).clamp(0.0, 255.0);
                 pix[i] = val as u8;
             }
         }
@@ -475,11 +609,24 @@ impl Lut4ColorSettings {
         hist.iter().for_each(|m| m.store(0, Ordering::Relaxed));
         let r = (self.sharpen_radius*3.0+1.0) as i32 + 1;
         
-        if r > 0 && self.sharpen_radius >= 0.2 && self.sharpen_amount != 0.0 {
-            let weights = self.calculate_weights(r);
+        if !self.lens.is_identity() {
+            crate::lens::lens_correct(img, &self.lens);
+        }
+        if self.denoise_luma > 0.0 || self.denoise_chroma > 0.0 {
+            crate::denoise::denoise_image(img, self.denoise_luma, self.denoise_chroma);
+        }
+        let sharpen = r > 0 && self.sharpen_radius >= 0.2 && self.sharpen_amount != 0.0;
+        let local = crate::local_adjust::LocalAdjust::new(self, img);
+        if sharpen || local.is_some() {
+            let weights = if sharpen { self.calculate_weights(r) } else { Vec::new() };
             let source_img = img.clone(); // Olvasható másolat a szomszédokhoz
             img.enumerate_pixels_mut().par_bridge().for_each(|(x, y, pixel)| {
-                self.blur_pixel(x, y, pixel, &source_img, &weights, r);
+                if sharpen {
+                    self.blur_pixel(x, y, pixel, &source_img, &weights, r);
+                }
+                if let Some(local) = &local {
+                    local.apply_pixel(x, y, pixel);
+                }
                 self.apply_lut_pixel(x, y, pixel);
                 self.update_histogram(pixel, hist);
             });
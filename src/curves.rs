@@ -0,0 +1,256 @@
+use serde::{Deserialize, Serialize};
+use crate::colors::ColorSettings;
+use crate::ImageViewer;
+
+// Tónusgörbék: monoton köbös (Fritsch–Carlson) spline a vezérlőpontokon át, így nincs túllövés.
+// A curves[0] a közös (RGB) görbe, 1..3 a R, G, B csatornáé; előbb a csatorna, utána a közös görbe hat.
+
+pub const MAX_CURVE_POINTS: usize = 16;
+pub const TONE_TABLE_SIZE: usize = 256;
+const CHANNEL_NAMES: [&str; 4] = ["RGB", "R", "G", "B"];
+
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct ToneCurve {
+    pub points: Vec<[f32; 2]>, // x szerint növekvő, 0.0 .. 1.0
+}
+
+impl Default for ToneCurve {
+    fn default() -> Self {
+        Self { points: vec![[0.0, 0.0], [1.0, 1.0]] }
+    }
+}
+
+impl ToneCurve {
+    pub fn is_identity(&self) -> bool {
+        self.points.iter().all(|p| (p[0] - p[1]).abs() < 0.001)
+    }
+
+    // Érintők a Fritsch–Carlson feltétellel
+    fn tangents(&self) -> [f32; MAX_CURVE_POINTS] {
+        let p = &self.points;
+        let n = p.len().min(MAX_CURVE_POINTS);
+        let mut d = [0.0f32; MAX_CURVE_POINTS];
+        let mut m = [0.0f32; MAX_CURVE_POINTS];
+        for k in 0..n - 1 {
+            let h = p[k + 1][0] - p[k][0];
+            d[k] = if h > 0.0 { (p[k + 1][1] - p[k][1]) / h } else { 0.0 };
+        }
+        m[0] = d[0];
+        m[n - 1] = d[n - 2];
+        for k in 1..n - 1 {
+            m[k] = if d[k - 1] * d[k] <= 0.0 { 0.0 } else { (d[k - 1] + d[k]) / 2.0 };
+        }
+        for k in 0..n - 1 {
+            if d[k] == 0.0 {
+                m[k] = 0.0;
+                m[k + 1] = 0.0;
+                continue;
+            }
+            let a = m[k] / d[k];
+            let b = m[k + 1] / d[k];
+            let s = a * a + b * b;
+            if s > 9.0 {
+                let t = 3.0 / s.sqrt();
+                m[k] = t * a * d[k];
+                m[k + 1] = t * b * d[k];
+            }
+        }
+        m
+    }
+
+    pub fn eval(&self, x: f32) -> f32 {
+        let p = &self.points;
+        let n = p.len().min(MAX_CURVE_POINTS);
+        if n < 2 {
+            return x;
+        }
+        if x <= p[0][0] {
+            return p[0][1];
+        }
+        if x >= p[n - 1][0] {
+            return p[n - 1][1];
+        }
+        let m = self.tangents();
+        let k = p[..n].partition_point(|q| q[0] <= x).clamp(1, n - 1) - 1;
+        let h = p[k + 1][0] - p[k][0];
+        if h <= 0.0 {
+            return p[k + 1][1];
+        }
+        let t = (x - p[k][0]) / h;
+        let (t2, t3) = (t * t, t * t * t);
+        let y = (2.0 * t3 - 3.0 * t2 + 1.0) * p[k][1]
+            + (t3 - 2.0 * t2 + t) * h * m[k]
+            + (-2.0 * t3 + 3.0 * t2) * p[k + 1][1]
+            + (t3 - t2) * h * m[k + 1];
+        y.clamp(0.0, 1.0)
+    }
+
+    // Új pont x szerint a helyére; a meglévő indexe, ha túl közel van egy másikhoz
+    pub fn insert(&mut self, x: f32, y: f32) -> Option<usize> {
+        if self.points.len() >= MAX_CURVE_POINTS {
+            return None;
+        }
+        let idx = self.points.partition_point(|q| q[0] < x);
+        if self.points.get(idx).is_some_and(|q| (q[0] - x).abs() < 0.01)
+            || (idx > 0 && (self.points[idx - 1][0] - x).abs() < 0.01) {
+            return None;
+        }
+        self.points.insert(idx, [x, y]);
+        Some(idx)
+    }
+
+    // A pont nem lépheti át a szomszédait
+    pub fn move_point(&mut self, idx: usize, x: f32, y: f32) {
+        let lo = if idx > 0 { self.points[idx - 1][0] + 0.01 } else { 0.0 };
+        let hi = if idx + 1 < self.points.len() { self.points[idx + 1][0] - 0.01 } else { 1.0 };
+        self.points[idx] = [x.clamp(lo, hi.max(lo)), y.clamp(0.0, 1.0)];
+    }
+}
+
+// 256 elemes táblázat a GPU-nak (szintek és görbék): (R, G, B) kimenet a bemeneti szint szerint
+pub fn tone_table(colset: &ColorSettings) -> Vec<[f32; 4]> {
+    (0..TONE_TABLE_SIZE)
+        .map(|i| {
+            let x = i as f32 / (TONE_TABLE_SIZE - 1) as f32;
+            let mut out = [0.0f32; 4];
+            for (c, v) in out.iter_mut().take(3).enumerate() {
+                *v = colset.apply_tone(x, c);
+            }
+            out
+        })
+        .collect()
+}
+
+impl ImageViewer {
+    pub fn curves_panel(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        ui.horizontal(|ui| {
+            ui.label("Channel:");
+            for (i, name) in CHANNEL_NAMES.iter().enumerate() {
+                if ui.selectable_label(self.curve_channel == i, *name).clicked() {
+                    self.curve_channel = i;
+                }
+            }
+            ui.add_space(20.0);
+            if ui.button("⟲").on_hover_text("Reset Curve").clicked() {
+                self.color_settings.curves[self.curve_channel] = ToneCurve::default();
+                *changed = true;
+            }
+            if ui.button("Reset All Curves").clicked() {
+                self.color_settings.curves = Default::default();
+                *changed = true;
+            }
+        });
+
+        let size = egui::vec2(ui.available_width().min(400.0), 200.0);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::click_and_drag());
+        let rect = response.rect;
+        let channel = self.curve_channel;
+        let to_screen = |x: f32, y: f32| egui::pos2(rect.left() + x * rect.width(), rect.bottom() - y * rect.height());
+        let from_screen = |p: egui::Pos2| {
+            (((p.x - rect.left()) / rect.width()).clamp(0.0, 1.0), ((rect.bottom() - p.y) / rect.height()).clamp(0.0, 1.0))
+        };
+
+        painter.rect_filled(rect, 2.0, egui::Color32::from_gray(30));
+
+        // A csatorna hisztogramja a háttérben
+        if self.hist.len() == 1024 {
+            let offset = match channel {
+                0 => 768,
+                c => (c - 1) * 256,
+            };
+            let bins = &self.hist[offset..offset + 256];
+            let max_val = bins.iter().copied().max().unwrap_or(0) as f32;
+            if max_val > 0.0 {
+                let color = match channel {
+                    1 => egui::Color32::from_rgb(110, 40, 40),
+                    2 => egui::Color32::from_rgb(40, 100, 40),
+                    3 => egui::Color32::from_rgb(40, 50, 120),
+                    _ => egui::Color32::from_gray(80),
+                };
+                let bin_w = rect.width() / 256.0;
+                for (i, v) in bins.iter().enumerate() {
+                    let h = (*v as f32 + 1.0).ln() / (max_val + 1.0).ln() * rect.height();
+                    let x = rect.left() + i as f32 * bin_w;
+                    painter.rect_filled(egui::Rect::from_min_max(egui::pos2(x, rect.bottom() - h), egui::pos2(x + bin_w, rect.bottom())), 0.0, color);
+                }
+            }
+        }
+
+        let grid = egui::Stroke::new(1.0, egui::Color32::from_gray(60));
+        for i in 1..4 {
+            let f = i as f32 / 4.0;
+            painter.line_segment([to_screen(f, 0.0), to_screen(f, 1.0)], grid);
+            painter.line_segment([to_screen(0.0, f), to_screen(1.0, f)], grid);
+        }
+        painter.line_segment([to_screen(0.0, 0.0), to_screen(1.0, 1.0)], egui::Stroke::new(1.0, egui::Color32::from_gray(90)));
+
+        // Pontok kezelése: kattintás új pont, húzás mozgatás, jobb gomb vagy dupla kattintás törlés
+        let hit = |curve: &ToneCurve, pos: egui::Pos2| {
+            curve.points.iter().position(|p| to_screen(p[0], p[1]).distance(pos) < 8.0)
+        };
+        let curve = &mut self.color_settings.curves[channel];
+        let mut edited = false;
+        if let Some(pos) = response.interact_pointer_pos() {
+            if response.drag_started() {
+                self.curve_drag = hit(curve, pos).or_else(|| {
+                    let (x, y) = from_screen(pos);
+                    curve.insert(x, y)
+                });
+            }
+            if response.dragged()
+                && let Some(idx) = self.curve_drag.filter(|i| *i < curve.points.len()) {
+                let (x, y) = from_screen(pos);
+                curve.move_point(idx, x, y);
+                edited = true;
+            }
+            if response.clicked() && hit(curve, pos).is_none() {
+                let (x, y) = from_screen(pos);
+                if curve.insert(x, y).is_some() {
+                    edited = true;
+                }
+            }
+            if (response.secondary_clicked() || response.double_clicked())
+                && let Some(idx) = hit(curve, pos)
+                && curve.points.len() > 2 {
+                curve.points.remove(idx);
+                edited = true;
+            }
+        }
+        let released = response.drag_stopped();
+        if released {
+            self.curve_drag = None;
+        }
+
+        let stroke_color = match channel {
+            1 => egui::Color32::from_rgb(255, 90, 90),
+            2 => egui::Color32::from_rgb(90, 220, 90),
+            3 => egui::Color32::from_rgb(100, 140, 255),
+            _ => egui::Color32::WHITE,
+        };
+        let line: Vec<egui::Pos2> = (0..=128)
+            .map(|i| {
+                let x = i as f32 / 128.0;
+                to_screen(x, curve.eval(x))
+            })
+            .collect();
+        painter.add(egui::Shape::line(line, egui::Stroke::new(2.0, stroke_color)));
+        for p in &curve.points {
+            painter.circle(to_screen(p[0], p[1]), 4.0, egui::Color32::from_gray(30), egui::Stroke::new(1.5, stroke_color));
+        }
+        if let Some(pos) = response.hover_pos() {
+            let (x, _) = from_screen(pos);
+            let text = format!("{} → {}", (x * 255.0).round(), (curve.eval(x) * 255.0).round());
+            painter.text(rect.left_top() + egui::vec2(4.0, 4.0), egui::Align2::LEFT_TOP, text, egui::FontId::monospace(11.0), egui::Color32::LIGHT_GRAY);
+        }
+
+        // CPU esetén csak elengedéskor számolunk újra, mint a csúszkáknál
+        if self.gpu_interface.is_none() {
+            if released || (edited && !response.dragged()) {
+                *changed = true;
+            }
+        } else if edited || released {
+            *changed = true;
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use rayon::prelude::*;
+use crate::colors::ColorSettings;
+use crate::ImageViewer;
+
+// Élmegőrző zajszűrés: kétoldali (bilateral) szűrő YCbCr térben, külön erősséggel a világosságra és a színre.
+// A színzaj foltosabb, ezért ott nagyobb a térbeli szigma, és a világosság élei is tartják.
+// Az élesítés előtt fut; a shaders.wgsl denoise belépési pontja ugyanezt számolja.
+
+pub const DENOISE_RADIUS: i32 = 4; // 9x9 ablak
+const LUMA_SIGMA: f32 = 1.5;       // térbeli szigma pixelben
+const CHROMA_SIGMA: f32 = 3.0;
+const LUMA_RANGE: f32 = 0.12;      // az erősség 1.0-nál ekkora világosság különbség még simul
+const CHROMA_RANGE: f32 = 0.15;
+const CHROMA_EDGE: f32 = 0.1;      // a színszűrés ennél nagyobb világosság különbségen nem terjed át
+
+fn to_ycc(rgb: [f32; 3]) -> [f32; 3] {
+    let y = 0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2];
+    [y, (rgb[2] - y) * 0.564, (rgb[0] - y) * 0.713]
+}
+
+fn from_ycc(ycc: [f32; 3]) -> [f32; 3] {
+    let r = ycc[0] + ycc[2] / 0.713;
+    let b = ycc[0] + ycc[1] / 0.564;
+    let g = (ycc[0] - 0.299 * r - 0.114 * b) / 0.587;
+    [r, g, b]
+}
+
+impl ColorSettings {
+    pub fn has_denoise(&self) -> bool {
+        self.denoise_luma >= 0.001 || self.denoise_chroma >= 0.001
+    }
+}
+
+// luma, chroma: 0.0 .. 1.0
+pub fn denoise_image(img: &mut image::RgbaImage, luma: f32, chroma: f32) {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width as i32, height as i32);
+    let source: Vec<[f32; 3]> = img.pixels()
+        .map(|p| to_ycc([p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0]))
+        .collect();
+    let luma_range = 2.0 * (luma * LUMA_RANGE).max(0.001).powi(2);
+    let chroma_range = 2.0 * (chroma * CHROMA_RANGE).max(0.001).powi(2);
+    let chroma_edge = 2.0 * CHROMA_EDGE * CHROMA_EDGE;
+    let r = DENOISE_RADIUS;
+
+    img.par_chunks_mut(width as usize * 4).enumerate().for_each(|(y, row)| {
+        let y = y as i32;
+        for x in 0..w {
+            let center = source[(y * w + x) as usize];
+            let mut sum_y = 0.0f32;
+            let mut total_y = 0.0f32;
+            let mut sum_c = [0.0f32; 2];
+            let mut total_c = 0.0f32;
+            for dy in -r..=r {
+                let py = (y + dy).clamp(0, h - 1);
+                for dx in -r..=r {
+                    let px = (x + dx).clamp(0, w - 1);
+                    let s = source[(py * w + px) as usize];
+                    let d2 = (dx * dx + dy * dy) as f32;
+                    let dl = (s[0] - center[0]).powi(2);
+                    if luma > 0.0 {
+                        let wy = (-d2 / (2.0 * LUMA_SIGMA * LUMA_SIGMA) - dl / luma_range).exp();
+                        sum_y += s[0] * wy;
+                        total_y += wy;
+                    }
+                    if chroma > 0.0 {
+                        let dc = (s[1] - center[1]).powi(2) + (s[2] - center[2]).powi(2);
+                        let wc = (-d2 / (2.0 * CHROMA_SIGMA * CHROMA_SIGMA) - dl / chroma_edge - dc / chroma_range).exp();
+                        sum_c[0] += s[1] * wc;
+                        sum_c[1] += s[2] * wc;
+                        total_c += wc;
+                    }
+                }
+            }
+            let mut out = center;
+            if total_y > 0.0 {
+                out[0] = sum_y / total_y;
+            }
+            if total_c > 0.0 {
+                out[1] = sum_c[0] / total_c;
+                out[2] = sum_c[1] / total_c;
+            }
+            let rgb = from_ycc(out);
+            let idx = x as usize * 4;
+            for i in 0..3 {
+                row[idx + i] = (rgb[i].clamp(0.0, 1.0) * 255.0 + 0.5) as u8;
+            }
+        }
+    });
+}
+
+impl ImageViewer {
+    pub fn denoise_panel(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        let gpu = self.gpu_interface.is_some();
+        let cs = &mut self.color_settings;
+        for (value, name, hint) in [
+            (&mut cs.denoise_luma, "Luminance", "Smooth brightness noise, keeps edges"),
+            (&mut cs.denoise_chroma, "Color", "Smooth color blotches"),
+        ] {
+            ui.horizontal(|ui| {
+                let res = ui.add(egui::Slider::new(value, 0.0..=1.0).text(name)).on_hover_text(hint);
+                if gpu {
+                    *changed |= res.changed();
+                } else {
+                    *changed |= res.drag_stopped() || (res.changed() && !ui.input(|i| i.pointer.any_down()));
+                }
+                if ui.button("⟲").on_hover_text(format!("Reset {}", name)).clicked() {
+                    *value = 0.0;
+                    *changed = true;
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,1056 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::exif_my::ExifBlock;
+use crate::ifd::{Ifd, IfdEntry, Reader, FMT_ASCII, FMT_BYTE, FMT_ULONG, FMT_URATIONAL};
+use crate::file_handlers::SaveFormat;
+use crate::ImageViewer;
+
+// EXIF szerkesztés: a TIFF struktúrát IFD-kre bontjuk, módosítjuk, majd újraépítjük
+// az offsetek újraszámolásával. Az ismeretlen tag-ek nyers bájtként, az eredeti bájtsorrendben maradnak.
+
+pub const TAG_EXIF_IFD: u16 = 0x8769;
+pub const TAG_GPS_IFD: u16 = 0x8825;
+pub const TAG_INTEROP_IFD: u16 = 0xA005;
+pub const TAG_THUMB_OFFSET: u16 = 0x0201;
+pub const TAG_THUMB_LENGTH: u16 = 0x0202;
+pub const TAG_MAKERNOTE: u16 = 0x927C;
+pub const TAG_IMAGE_DESCRIPTION: u16 = 0x010E;
+pub const TAG_ARTIST: u16 = 0x013B;
+pub const TAG_COPYRIGHT: u16 = 0x8298;
+pub const TAG_DATETIME_ORIGINAL: u16 = 0x9003;
+
+
+// TIFF fájlban ezek a tag-ek a képadathoz tartoznak, nem metaadatok
+const IMAGE_TAGS: [u16; 26] = [
+    0x00FE, 0x00FF, 0x0100, 0x0101, 0x0102, 0x0103, 0x0106, 0x0107, 0x010A, 0x0111,
+    0x0115, 0x0116, 0x0117, 0x0118, 0x0119, 0x011C, 0x013D, 0x0140, 0x0142, 0x0143,
+    0x0144, 0x0145, 0x014A, 0x0152, 0x0153, 0x8773,
+];
+
+fn is_image_tag(tag: u16) -> bool {
+    IMAGE_TAGS.contains(&tag)
+}
+
+// Mentéskor a metaadatok kezelése (megosztás előtt a helyadatot el kell távolítani)
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+pub enum MetadataMode {
+    KeepAll,
+    RemoveGps,   // GPS IFD és MakerNote nélkül
+    Minimal,     // csak tájolás, felbontás és színtér
+    StripAll,    // EXIF és ICC profil nélkül (a pixeleket sRGB-be alakítjuk)
+}
+
+impl MetadataMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MetadataMode::KeepAll => "Keep all",
+            MetadataMode::RemoveGps => "Remove location",
+            MetadataMode::Minimal => "Orientation, resolution, color only",
+            MetadataMode::StripAll => "Strip all",
+        }
+    }
+}
+
+// Minimal módban megmaradó tag-ek: Orientation, X/YResolution, ResolutionUnit; ColorSpace
+const MINIMAL_IFD0_TAGS: [u16; 4] = [0x0112, 0x011A, 0x011B, 0x0128];
+const MINIMAL_EXIF_TAGS: [u16; 1] = [0xA001];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum IfdKind {
+    Ifd0,
+    Exif,
+    Gps,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ExifTree {
+    pub motorola: bool,
+    pub ifd0: Ifd,
+    pub exif: Ifd,
+    pub gps: Ifd,
+    pub interop: Ifd,
+    pub ifd1: Ifd,          // a bélyegkép könyvtára
+    pub thumbnail: Vec<u8>, // JPEG bélyegkép
+}
+
+struct Writer {
+    out: Vec<u8>,
+    origin: usize, // a TIFF fejléc helye az out-ban, az offsetek ehhez képest értendők
+    motorola: bool,
+    fixed: usize,  // az out eleje eddig eredeti tartalom (TIFF fájl, előre elhelyezett MakerNote), nem írjuk felül
+}
+
+impl Writer {
+    fn pos(&self) -> u32 {
+        (self.out.len() - self.origin) as u32
+    }
+
+    fn u16(&mut self, v: u16) {
+        let bytes = if self.motorola { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.out.extend_from_slice(&bytes);
+    }
+
+    fn u32(&mut self, v: u32) {
+        let bytes = if self.motorola { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.out.extend_from_slice(&bytes);
+    }
+
+    fn patch_u32(&mut self, at: usize, v: u32) {
+        let bytes = if self.motorola { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.out[at..at + 4].copy_from_slice(&bytes);
+    }
+
+    fn patch_field(&mut self, fields: &[(u16, usize)], tag: u16, v: u32) {
+        if let Some((_, at)) = fields.iter().find(|(t, _)| *t == tag) {
+            self.patch_u32(*at, v);
+        }
+    }
+
+    fn align(&mut self) {
+        if self.pos() % 2 == 1 {
+            self.out.push(0);
+        }
+    }
+
+    // Egy IFD és az adatterülete; vissza: az IFD offsetje, az értékmezők helye és a next mező helye
+    fn ifd(&mut self, entries: &[IfdEntry], deferred: &mut Vec<(usize, IfdEntry)>) -> (u32, Vec<(u16, usize)>, usize) {
+        self.align();
+        let start = self.pos();
+        let mut sorted: Vec<&IfdEntry> = entries.iter().collect();
+        sorted.sort_by_key(|e| e.tag);
+        self.u16(sorted.len() as u16);
+        let mut fields = Vec::new();
+        let mut outside = Vec::new();
+        for entry in sorted {
+            self.u16(entry.tag);
+            self.u16(entry.format);
+            self.u32(entry.count);
+            let at = self.out.len();
+            fields.push((entry.tag, at));
+            if entry.data.len() <= 4 {
+                let mut value = entry.data.clone();
+                value.resize(4, 0);
+                self.out.extend_from_slice(&value);
+            } else {
+                self.out.extend_from_slice(&[0; 4]);
+                if entry.tag == TAG_MAKERNOTE {
+                    deferred.push((at, entry.clone()));
+                } else {
+                    outside.push((at, entry));
+                }
+            }
+        }
+        let next_at = self.out.len();
+        self.u32(0);
+        for (at, entry) in outside {
+            self.align();
+            let offset = self.pos();
+            self.patch_u32(at, offset);
+            self.out.extend_from_slice(&entry.data);
+        }
+        (start, fields, next_at)
+    }
+
+    // A MakerNote belső offsetjei gyakran a TIFF fejléchez képestiek (Canon, Panasonic, régi Olympus),
+    // ezért az eredeti helyén kell maradnia; vissza: hamis, ha ez nem sikerült és át kellett tenni
+    fn place_deferred(&mut self, deferred: Vec<(usize, IfdEntry)>) -> bool {
+        let mut kept = true;
+        for (at, entry) in deferred {
+            let start = self.origin + entry.offset as usize;
+            let end = start + entry.data.len();
+            if entry.offset != 0 && end <= self.fixed && self.out[start..end] == entry.data[..] {
+                // már ott van: TIFF fájlban az eredeti, vagy előre elhelyezve
+                self.patch_u32(at, entry.offset);
+                continue;
+            }
+            self.align();
+            let pos = self.pos();
+            if entry.offset >= pos && entry.offset - pos < 0x10000 && entry.offset % 2 == 0 {
+                let pad = (entry.offset - pos) as usize;
+                self.out.resize(self.out.len() + pad, 0);
+            }
+            let offset = self.pos();
+            kept &= entry.offset == 0 || offset == entry.offset;
+            self.patch_u32(at, offset);
+            self.out.extend_from_slice(&entry.data);
+        }
+        kept
+    }
+}
+
+impl ExifTree {
+    // "Exif\0\0" kezdetű blokk (JPEG APP1, WebP, BMP) feldolgozása
+    pub fn from_exif_block(block: &[u8]) -> Result<Self, String> {
+        let tiff = block.strip_prefix(b"Exif\0\0").unwrap_or(block);
+        Self::parse(tiff, true).map(|(tree, _)| tree)
+    }
+
+    // A with_ifd1 hamis TIFF fájlnál: ott a következő IFD a második oldal, nem bélyegkép
+    fn parse(tiff: &[u8], with_ifd1: bool) -> Result<(Self, u32), String> {
+        let motorola = match tiff.get(0..2) {
+            Some(b"MM") => true,
+            Some(b"II") => false,
+            _ => return Err("Hibás bájtsorrend jelölő".into()),
+        };
+        let r = Reader { buf: tiff, motorola };
+        if r.u16(2) != Some(42) {
+            return Err("Hibás TIFF fejléc".into());
+        }
+        let first = r.u32(4).ok_or("Hibás TIFF fejléc")? as usize;
+        let (mut ifd0, next) = r.ifd(first)?;
+
+        let mut exif = match r.pointer(&ifd0, TAG_EXIF_IFD) {
+            Some(pos) => r.ifd(pos)?.0,
+            None => Ifd::default(),
+        };
+        let interop = match r.pointer(&exif, TAG_INTEROP_IFD) {
+            Some(pos) => r.ifd(pos).map(|(ifd, _)| ifd).unwrap_or_default(),
+            None => Ifd::default(),
+        };
+        let gps = match r.pointer(&ifd0, TAG_GPS_IFD) {
+            Some(pos) => r.ifd(pos).map(|(ifd, _)| ifd).unwrap_or_default(),
+            None => Ifd::default(),
+        };
+        ifd0.remove(TAG_EXIF_IFD);
+        ifd0.remove(TAG_GPS_IFD);
+        exif.remove(TAG_INTEROP_IFD);
+
+        let mut ifd1 = Ifd::default();
+        let mut thumbnail = Vec::new();
+        if with_ifd1 && next != 0 {
+            ifd1 = r.ifd(next as usize).map(|(ifd, _)| ifd).unwrap_or_default();
+            if let (Some(offset), Some(len)) = (r.pointer(&ifd1, TAG_THUMB_OFFSET), r.pointer(&ifd1, TAG_THUMB_LENGTH)) {
+                thumbnail = tiff.get(offset..offset.saturating_add(len)).map(|t| t.to_vec()).unwrap_or_default();
+            }
+            ifd1.remove(TAG_THUMB_OFFSET);
+            ifd1.remove(TAG_THUMB_LENGTH);
+        }
+        Ok((Self { motorola, ifd0, exif, gps, interop, ifd1, thumbnail }, next))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ifd0.is_empty() && self.exif.is_empty() && self.gps.is_empty() && self.ifd1.is_empty()
+    }
+
+    pub fn ifd(&self, kind: IfdKind) -> &Ifd {
+        match kind {
+            IfdKind::Ifd0 => &self.ifd0,
+            IfdKind::Exif => &self.exif,
+            IfdKind::Gps => &self.gps,
+        }
+    }
+
+    pub fn ifd_mut(&mut self, kind: IfdKind) -> &mut Ifd {
+        match kind {
+            IfdKind::Ifd0 => &mut self.ifd0,
+            IfdKind::Exif => &mut self.exif,
+            IfdKind::Gps => &mut self.gps,
+        }
+    }
+
+    fn u32_bytes(&self, v: u32) -> [u8; 4] {
+        if self.motorola { v.to_be_bytes() } else { v.to_le_bytes() }
+    }
+
+    fn rational_bytes(&self, values: &[(u32, u32)]) -> Vec<u8> {
+        values.iter().flat_map(|(n, d)| {
+            let mut bytes = self.u32_bytes(*n).to_vec();
+            bytes.extend_from_slice(&self.u32_bytes(*d));
+            bytes
+        }).collect()
+    }
+
+    fn rationals(&self, entry: &IfdEntry) -> Vec<f64> {
+        let r = Reader { buf: &entry.data, motorola: self.motorola };
+        (0..entry.count as usize)
+            .filter_map(|i| {
+                let (n, d) = (r.u32(i * 8)?, r.u32(i * 8 + 4)?);
+                (d != 0).then(|| n as f64 / d as f64)
+            })
+            .collect()
+    }
+
+    pub fn get_ascii(&self, kind: IfdKind, tag: u16) -> Option<String> {
+        let entry = self.ifd(kind).get(tag).filter(|e| e.format == FMT_ASCII)?;
+        let text = String::from_utf8_lossy(&entry.data);
+        Some(text.trim_end_matches('\0').to_string())
+    }
+
+    // Üres szöveg esetén a tag-et töröljük
+    pub fn set_ascii(&mut self, kind: IfdKind, tag: u16, value: &str) {
+        if value.is_empty() {
+            self.ifd_mut(kind).remove(tag);
+            return;
+        }
+        let mut data = value.as_bytes().to_vec();
+        data.push(0);
+        let entry = IfdEntry { tag, format: FMT_ASCII, count: data.len() as u32, data, offset: 0 };
+        self.ifd_mut(kind).set(entry);
+    }
+
+    // Szélesség, hosszúság, magasság tizedes fokban / méterben
+    pub fn gps_position(&self) -> Option<(f64, f64, Option<f64>)> {
+        let dms = |tag: u16| {
+            let v = self.rationals(self.gps.get(tag)?);
+            (v.len() == 3).then(|| v[0] + v[1] / 60.0 + v[2] / 3600.0)
+        };
+        let south = self.get_ascii(IfdKind::Gps, 0x0001).is_some_and(|r| r.starts_with('S'));
+        let west = self.get_ascii(IfdKind::Gps, 0x0003).is_some_and(|r| r.starts_with('W'));
+        let lat = dms(0x0002)?;
+        let lon = dms(0x0004)?;
+        let below = self.gps.get(0x0005).is_some_and(|e| e.data.first() == Some(&1));
+        let alt = self.gps.get(0x0006)
+            .and_then(|e| self.rationals(e).first().copied())
+            .map(|a| if below { -a } else { a });
+        Some((if south { -lat } else { lat }, if west { -lon } else { lon }, alt))
+    }
+
+    pub fn set_gps_position(&mut self, lat: f64, lon: f64, alt: Option<f64>) {
+        // ezredmásodpercre kerekítve bontjuk, így a kerekítés átvihet a percbe és a fokba (nincs 60.000")
+        let dms = |v: f64| {
+            let ms = (v.abs() * 3_600_000.0).round() as u64;
+            [((ms / 3_600_000) as u32, 1), ((ms / 60_000 % 60) as u32, 1), ((ms % 60_000) as u32, 1000)]
+        };
+        let version = IfdEntry { tag: 0x0000, format: FMT_BYTE, count: 4, data: vec![2, 3, 0, 0], offset: 0 };
+        self.gps.set(version);
+        self.set_ascii(IfdKind::Gps, 0x0001, if lat < 0.0 { "S" } else { "N" });
+        self.gps.set(IfdEntry { tag: 0x0002, format: FMT_URATIONAL, count: 3, data: self.rational_bytes(&dms(lat)), offset: 0 });
+        self.set_ascii(IfdKind::Gps, 0x0003, if lon < 0.0 { "W" } else { "E" });
+        self.gps.set(IfdEntry { tag: 0x0004, format: FMT_URATIONAL, count: 3, data: self.rational_bytes(&dms(lon)), offset: 0 });
+        match alt {
+            Some(alt) => {
+                let below = if alt < 0.0 { 1 } else { 0 };
+                self.gps.set(IfdEntry { tag: 0x0005, format: FMT_BYTE, count: 1, data: vec![below], offset: 0 });
+                let value = self.rational_bytes(&[((alt.abs() * 100.0).round() as u32, 100)]);
+                self.gps.set(IfdEntry { tag: 0x0006, format: FMT_URATIONAL, count: 1, data: value, offset: 0 });
+            }
+            None => {
+                self.gps.remove(0x0005);
+                self.gps.remove(0x0006);
+            }
+        }
+    }
+
+    // Bájtsorrend csere (pl. más sorrendű TIFF fájlba íráskor); a MakerNote-hoz nem nyúlunk
+    pub fn convert_order(&mut self, motorola: bool) {
+        if self.motorola == motorola {
+            return;
+        }
+        self.motorola = motorola;
+        for ifd in [&mut self.ifd0, &mut self.exif, &mut self.gps, &mut self.interop, &mut self.ifd1] {
+            for entry in ifd.entries.iter_mut().filter(|e| e.tag != TAG_MAKERNOTE) {
+                let unit = match entry.format {
+                    3 | 8 => 2,
+                    4 | 5 | 9 | 10 | 11 | 13 => 4,
+                    12 => 8,
+                    _ => 1,
+                };
+                if unit > 1 {
+                    entry.data.chunks_exact_mut(unit).for_each(|c| c.reverse());
+                }
+            }
+        }
+    }
+
+    // IFD0 és az alá tartozó Exif/Interop/GPS könyvtárak; vissza: az IFD0 offsetje, a next mező helye,
+    // és hogy a MakerNote az eredeti helyén maradt-e
+    fn write_main(&self, w: &mut Writer, ifd0: &Ifd) -> (u32, usize, bool) {
+        let mut deferred = Vec::new();
+        let has_exif = !self.exif.is_empty() || !self.interop.is_empty();
+        let mut entries0 = ifd0.entries.clone();
+        if has_exif {
+            entries0.push(IfdEntry::pointer(TAG_EXIF_IFD));
+        }
+        if !self.gps.is_empty() {
+            entries0.push(IfdEntry::pointer(TAG_GPS_IFD));
+        }
+        let (ifd0_offset, fields0, next_at) = w.ifd(&entries0, &mut deferred);
+        if has_exif {
+            let mut entries = self.exif.entries.clone();
+            if !self.interop.is_empty() {
+                entries.push(IfdEntry::pointer(TAG_INTEROP_IFD));
+            }
+            let (offset, fields, _) = w.ifd(&entries, &mut deferred);
+            w.patch_field(&fields0, TAG_EXIF_IFD, offset);
+            if !self.interop.is_empty() {
+                let (offset, _, _) = w.ifd(&self.interop.entries, &mut deferred);
+                w.patch_field(&fields, TAG_INTEROP_IFD, offset);
+            }
+        }
+        if !self.gps.is_empty() {
+            let (offset, _, _) = w.ifd(&self.gps.entries, &mut deferred);
+            w.patch_field(&fields0, TAG_GPS_IFD, offset);
+        }
+        let kept = w.place_deferred(deferred);
+        (ifd0_offset, next_at, kept)
+    }
+
+    // Az új "Exif\0\0" kezdetű blokk, bélyegképpel együtt. Ha a megnőtt könyvtárak (pl. új GPS IFD)
+    // miatt a MakerNote nem férne az eredeti helyére, előbb azt tesszük a helyére, a könyvtárak utána jönnek.
+    pub fn to_exif_block(&self) -> Vec<u8> {
+        let (block, kept) = self.write_block(None);
+        if kept {
+            return block;
+        }
+        // nagyobb offset csak TIFF fájlból jöhet, azt a write_tiff_exif a fájlban hagyja
+        let maker = self.exif.get(TAG_MAKERNOTE)
+            .filter(|e| e.offset >= 8 && e.offset as usize + e.data.len() < 0x10000);
+        match maker {
+            Some(maker) => self.write_block(Some(maker)).0,
+            None => block,
+        }
+    }
+
+    // Írás előtt: ha a MakerNote mégsem maradhatott a helyén, inkább nem írunk (TIFF-nél a fájlban marad, ott nem kell)
+    pub fn check_makernote(&self, block: &[u8]) -> Result<(), String> {
+        let Some(original) = self.exif.get(TAG_MAKERNOTE).filter(|e| e.offset != 0) else {
+            return Ok(());
+        };
+        let written = Self::from_exif_block(block)?;
+        match written.exif.get(TAG_MAKERNOTE) {
+            Some(entry) if entry.offset == original.offset => Ok(()),
+            _ => Err("A MakerNote nem maradhat az eredeti helyén, a fájl nem módosult".into()),
+        }
+    }
+
+    fn write_block(&self, maker: Option<&IfdEntry>) -> (Vec<u8>, bool) {
+        let mut w = Writer { out: b"Exif\0\0".to_vec(), origin: 6, motorola: self.motorola, fixed: 0 };
+        w.out.extend_from_slice(if self.motorola { b"MM" } else { b"II" });
+        w.u16(42);
+        w.u32(8);
+        if let Some(maker) = maker {
+            w.out.resize(w.origin + maker.offset as usize, 0);
+            w.out.extend_from_slice(&maker.data);
+            w.fixed = w.out.len();
+        }
+        let (ifd0_offset, next_at, mut kept) = self.write_main(&mut w, &self.ifd0);
+        w.patch_u32(w.origin + 4, ifd0_offset);
+        if !self.ifd1.is_empty() || !self.thumbnail.is_empty() {
+            let mut entries = self.ifd1.entries.clone();
+            if !self.thumbnail.is_empty() {
+                entries.push(IfdEntry::pointer(TAG_THUMB_OFFSET));
+                let len = self.u32_bytes(self.thumbnail.len() as u32).to_vec();
+                entries.push(IfdEntry { tag: TAG_THUMB_LENGTH, format: FMT_ULONG, count: 1, data: len, offset: 0 });
+            }
+            let mut deferred = Vec::new();
+            let (offset, fields, _) = w.ifd(&entries, &mut deferred);
+            kept &= w.place_deferred(deferred);
+            w.patch_u32(next_at, offset);
+            if !self.thumbnail.is_empty() {
+                let pos = w.pos();
+                w.out.extend_from_slice(&self.thumbnail);
+                w.patch_field(&fields, TAG_THUMB_OFFSET, pos);
+            }
+        }
+        (w.out, kept)
+    }
+}
+
+impl ExifBlock {
+    pub fn tree(&self) -> Result<ExifTree, String> {
+        ExifTree::from_exif_block(&self.raw_exif)
+    }
+
+    pub fn from_tree(tree: &ExifTree) -> Result<ExifBlock, String> {
+        let data = tree.to_exif_block();
+        let len = data.len();
+        ExifBlock::default().open(&data, len).map_err(|e| e.to_string())
+    }
+
+    // A GPS IFD és a MakerNote (gyakran sorozatszám, belső helyadat) eltávolítása
+    pub fn without_gps(&self) -> Result<ExifBlock, String> {
+        let mut tree = self.tree()?;
+        tree.gps = Ifd::default();
+        tree.exif.remove(TAG_MAKERNOTE);
+        Self::from_tree(&tree)
+    }
+
+    // Csak a megjelenítéshez szükséges tag-ek, bélyegkép nélkül
+    pub fn minimal(&self) -> Result<ExifBlock, String> {
+        let tree = self.tree()?;
+        let keep = |ifd: &Ifd, tags: &[u16]| Ifd {
+            entries: ifd.entries.iter().filter(|e| tags.contains(&e.tag)).cloned().collect(),
+        };
+        let minimal = ExifTree {
+            motorola: tree.motorola,
+            ifd0: keep(&tree.ifd0, &MINIMAL_IFD0_TAGS),
+            exif: keep(&tree.exif, &MINIMAL_EXIF_TAGS),
+            interop: tree.interop.clone(), // R98/R03: sRGB vagy Adobe RGB
+            ..Default::default()
+        };
+        Self::from_tree(&minimal)
+    }
+}
+
+// TIFF fájl metaadatai (IFD0 a képadat tag-ek nélkül + Exif/GPS) önálló EXIF blokként
+pub fn exif_block_from_tiff(buffer: &[u8]) -> Option<Vec<u8>> {
+    let (mut tree, _) = ExifTree::parse(buffer, false).ok()?;
+    tree.ifd0.entries.retain(|e| !is_image_tag(e.tag));
+    if tree.is_empty() {
+        return None;
+    }
+    Some(tree.to_exif_block())
+}
+
+// A TIFF fájl képadatának helye (csíkok, csempék, JPEG bélyegkép); hiányzó hossznál None
+fn image_data_ranges(r: &Reader, ifd: &Ifd) -> Option<Vec<(usize, usize)>> {
+    let values = |entry: &IfdEntry| -> Vec<usize> {
+        let reader = Reader { buf: &entry.data, motorola: r.motorola };
+        (0..entry.count as usize)
+            .filter_map(|i| match entry.format {
+                3 => reader.u16(i * 2).map(|v| v as usize),
+                _ => reader.u32(i * 4).map(|v| v as usize),
+            })
+            .collect()
+    };
+    let mut ranges = Vec::new();
+    for (offsets, counts) in [(0x0111, 0x0117), (0x0144, 0x0145), (TAG_THUMB_OFFSET, TAG_THUMB_LENGTH)] {
+        let (offsets, counts) = match (ifd.get(offsets), ifd.get(counts)) {
+            (None, _) => continue,
+            (Some(offsets), Some(counts)) => (values(offsets), values(counts)),
+            _ => return None,
+        };
+        if offsets.len() != counts.len() {
+            return None;
+        }
+        ranges.extend(offsets.iter().zip(&counts).map(|(o, c)| (*o, o.saturating_add(*c))));
+    }
+    Some(ranges)
+}
+
+// Az előző szerkesztés által a fájl végére írt, most elavuló IFD0 és metaadat könyvtárak kezdete.
+// Csak akkor vágható le, ha utána semmi megmaradó nincs (képadat, a helyén hagyott MakerNote, további oldalak).
+fn stale_metadata_start(tiff: &[u8]) -> Option<usize> {
+    let r = Reader { buf: tiff, motorola: tiff.get(0..2)? == b"MM" };
+    let ifd_range = |pos: usize| Some((pos, pos + 2 + 12 * r.u16(pos)? as usize + 4));
+    let data_range = |e: &IfdEntry| (e.offset != 0).then(|| (e.offset as usize, e.offset as usize + e.data.len()));
+    let first = r.u32(4)? as usize;
+    let (ifd0, mut next) = r.ifd(first).ok()?;
+    if ifd0.get(0x014A).is_some() {
+        return None; // SubIFDs: a hivatkozott könyvtárakat nem követjük
+    }
+    // az IFD0 minden külső adatát újraírjuk, csak a képadat marad
+    let mut stale = vec![ifd_range(first)?];
+    stale.extend(ifd0.entries.iter().filter_map(data_range));
+    let mut kept = image_data_ranges(&r, &ifd0)?;
+    let mut subs = vec![r.pointer(&ifd0, TAG_EXIF_IFD), r.pointer(&ifd0, TAG_GPS_IFD)];
+    while let Some(pos) = subs.pop() {
+        let Some(pos) = pos else {
+            continue;
+        };
+        let (ifd, _) = r.ifd(pos).ok()?;
+        stale.push(ifd_range(pos)?);
+        for entry in &ifd.entries {
+            match entry.tag {
+                TAG_MAKERNOTE => kept.extend(data_range(entry)),
+                TAG_INTEROP_IFD => subs.push(r.pointer(&ifd, TAG_INTEROP_IFD)),
+                _ => stale.extend(data_range(entry)),
+            }
+        }
+    }
+    // további oldalak: a könyvtáruk, az adataik és a képadatuk mind megmarad
+    let mut pages = 0;
+    while next != 0 {
+        pages += 1;
+        let (ifd, following) = r.ifd(next as usize).ok()?;
+        if pages > 10000 || [0x014A, TAG_EXIF_IFD, TAG_GPS_IFD].iter().any(|tag| ifd.get(*tag).is_some()) {
+            return None;
+        }
+        kept.push(ifd_range(next as usize)?);
+        kept.extend(ifd.entries.iter().filter_map(data_range));
+        kept.extend(image_data_ranges(&r, &ifd)?);
+        next = following;
+    }
+    let start = stale.iter().map(|(start, _)| *start).min()?;
+    (start >= 8 && start < tiff.len() && kept.iter().all(|(_, end)| *end <= start)).then_some(start)
+}
+
+// TIFF: az új IFD0 és a metaadat könyvtárak a fájl végére kerülnek, a képadat offsetjei érvényesek maradnak.
+// Az előző szerkesztéskor a végére írt könyvtárakat levágjuk, így a fájl nem nő minden mentéskor.
+fn write_tiff_exif(mut buffer: Vec<u8>, tree: &ExifTree) -> Result<Vec<u8>, String> {
+    let (file_tree, next) = ExifTree::parse(&buffer, false)?;
+    if let Some(start) = stale_metadata_start(&buffer) {
+        buffer.truncate(start);
+    }
+    let mut tree = tree.clone();
+    tree.convert_order(file_tree.motorola);
+    // a változatlan MakerNote a fájlban marad, oda mutatunk
+    if let (Some(ours), Some(theirs)) = (tree.exif.get(TAG_MAKERNOTE), file_tree.exif.get(TAG_MAKERNOTE))
+        && ours.data == theirs.data {
+        let offset = theirs.offset;
+        tree.exif.entries.iter_mut().filter(|e| e.tag == TAG_MAKERNOTE).for_each(|e| e.offset = offset);
+    }
+    let mut ifd0 = file_tree.ifd0;
+    ifd0.entries.retain(|e| is_image_tag(e.tag));
+    ifd0.entries.extend(tree.ifd0.entries.iter().filter(|e| !is_image_tag(e.tag)).cloned());
+    let fixed = buffer.len();
+    let mut w = Writer { out: buffer, origin: 0, motorola: file_tree.motorola, fixed };
+    let (offset, next_at, _) = tree.write_main(&mut w, &ifd0);
+    w.patch_u32(next_at, next);
+    w.patch_u32(4, offset);
+    Ok(w.out)
+}
+
+// A fájlba ágyazott EXIF blokk ("Exif\0\0" előtaggal) a formátum szerint, kép dekódolása nélkül
+pub fn exif_block_from_file(buffer: &[u8], format: SaveFormat) -> Option<Vec<u8>> {
+    let data = match format {
+        SaveFormat::Jpeg => {
+            let jpeg = img_parts::jpeg::Jpeg::from_bytes(buffer.to_vec().into()).ok()?;
+            jpeg.segments().iter()
+                .filter(|s| s.marker() == 0xE1)
+                .map(|s| s.contents().to_vec())
+                .find(|c| c.starts_with(b"Exif\0\0"))?
+        }
+        SaveFormat::Png => {
+            let png = img_parts::png::Png::from_bytes(buffer.to_vec().into()).ok()?;
+            png.chunk_by_type(*b"eXIf")?.contents().to_vec()
+        }
+        SaveFormat::Webp => {
+            use img_parts::ImageEXIF;
+            let webp = img_parts::webp::WebP::from_bytes(buffer.to_vec().into()).ok()?;
+            webp.exif()?.to_vec()
+        }
+        SaveFormat::Tif => return exif_block_from_tiff(buffer),
+        SaveFormat::Bmp => {
+            let offset = u32::from_le_bytes(buffer.get(10..14)?.try_into().ok()?) as usize;
+            let potential_exif = buffer.get(54..offset)?;
+            let pos = potential_exif.windows(4).position(|w| w == b"Exif" || w == b"II*\0" || w == b"MM\0*")?;
+            potential_exif[pos..].to_vec()
+        }
+        _ => return None,
+    };
+    Some(with_exif_header(data))
+}
+
+fn with_exif_header(data: Vec<u8>) -> Vec<u8> {
+    if data.starts_with(b"Exif\0\0") {
+        return data;
+    }
+    let mut block = b"Exif\0\0".to_vec();
+    block.extend_from_slice(&data);
+    block
+}
+
+// Sok fájlhoz (térkép jelölők): JPEG, PNG és WebP esetén csak a darabok fejlécét és az EXIF darabot olvassuk,
+// a képadatot átugorjuk. A többi formátumnál az egész fájl kell.
+pub fn exif_block_from_path(path: &Path, format: SaveFormat) -> Option<Vec<u8>> {
+    let mut file = std::fs::File::open(path).ok()?;
+    let data = match format {
+        SaveFormat::Jpeg => jpeg_exif_segment(&mut file)?,
+        SaveFormat::Png => png_exif_chunk(&mut file)?,
+        SaveFormat::Webp => webp_exif_chunk(&mut file)?,
+        _ => return exif_block_from_file(&std::fs::read(path).ok()?, format),
+    };
+    Some(with_exif_header(data))
+}
+
+// take: sérült hossznál sem foglalunk a fájlnál nagyobb puffert
+fn read_chunk(file: &mut std::fs::File, len: usize) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    file.take(len as u64).read_to_end(&mut data).ok()?;
+    (data.len() == len).then_some(data)
+}
+
+fn jpeg_exif_segment(file: &mut std::fs::File) -> Option<Vec<u8>> {
+    let mut head = [0u8; 4];
+    file.read_exact(&mut head[..2]).ok()?;
+    if head[..2] != [0xFF, 0xD8] {
+        return None;
+    }
+    loop {
+        file.read_exact(&mut head).ok()?;
+        // SOS után már a képadat jön
+        if head[0] != 0xFF || head[1] == 0xDA || head[1] == 0xD9 {
+            return None;
+        }
+        let len = (u16::from_be_bytes([head[2], head[3]]) as usize).checked_sub(2)?;
+        if head[1] == 0xE1 {
+            let data = read_chunk(file, len)?;
+            if data.starts_with(b"Exif\0\0") {
+                return Some(data);
+            }
+        } else {
+            file.seek(SeekFrom::Current(len as i64)).ok()?;
+        }
+    }
+}
+
+fn png_exif_chunk(file: &mut std::fs::File) -> Option<Vec<u8>> {
+    let mut head = [0u8; 8];
+    file.read_exact(&mut head).ok()?;
+    if head != *b"\x89PNG\r\n\x1a\n" {
+        return None;
+    }
+    loop {
+        file.read_exact(&mut head).ok()?;
+        let len = u32::from_be_bytes([head[0], head[1], head[2], head[3]]) as usize;
+        match &head[4..] {
+            b"eXIf" => return read_chunk(file, len),
+            b"IEND" => return None,
+            _ => { file.seek(SeekFrom::Current(len as i64 + 4)).ok()?; } // + CRC
+        }
+    }
+}
+
+fn webp_exif_chunk(file: &mut std::fs::File) -> Option<Vec<u8>> {
+    let mut head = [0u8; 12];
+    file.read_exact(&mut head).ok()?;
+    if &head[..4] != b"RIFF" || &head[8..] != b"WEBP" {
+        return None;
+    }
+    let mut chunk = [0u8; 8];
+    loop {
+        file.read_exact(&mut chunk).ok()?;
+        let len = u32::from_le_bytes([chunk[4], chunk[5], chunk[6], chunk[7]]) as usize;
+        if &chunk[..4] == b"EXIF" {
+            return read_chunk(file, len);
+        }
+        file.seek(SeekFrom::Current((len + (len & 1)) as i64)).ok()?; // páros hosszra kiegészítve
+    }
+}
+
+pub fn can_write_exif(format: SaveFormat) -> bool {
+    matches!(format, SaveFormat::Jpeg | SaveFormat::Png | SaveFormat::Webp | SaveFormat::Tif | SaveFormat::Bmp)
+}
+
+// Az EXIF blokk cseréje a fájlban a pixelek újrakódolása nélkül; előbb ideiglenes fájlba írunk
+pub fn write_exif_to_file(path: &PathBuf, format: SaveFormat, block: &[u8]) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let mut out = Vec::new();
+    match format {
+        SaveFormat::Jpeg => {
+            if block.len() > 65533 {
+                return Err("Az EXIF blokk túl nagy a JPEG APP1 szegmenshez".into());
+            }
+            let mut jpeg = img_parts::jpeg::Jpeg::from_bytes(data.into()).map_err(|e| e.to_string())?;
+            jpeg.segments_mut().retain(|s| !(s.marker() == 0xE1 && s.contents().starts_with(b"Exif\0\0")));
+            let pos = jpeg.segments().iter().position(|s| s.marker() == 0xE0).map_or(0, |p| p + 1);
+            let segment = img_parts::jpeg::JpegSegment::new_with_contents(0xE1, img_parts::Bytes::copy_from_slice(block));
+            jpeg.segments_mut().insert(pos, segment);
+            jpeg.encoder().write_to(&mut out).map_err(|e| e.to_string())?;
+        }
+        SaveFormat::Png => {
+            let mut png = img_parts::png::Png::from_bytes(data.into()).map_err(|e| e.to_string())?;
+            png.remove_chunks_by_type(*b"eXIf");
+            let tiff = block.strip_prefix(b"Exif\0\0").unwrap_or(block);
+            let chunk = img_parts::png::PngChunk::new(*b"eXIf", img_parts::Bytes::copy_from_slice(tiff));
+            let pos = png.chunks().iter().position(|c| c.kind() == *b"IDAT").unwrap_or(png.chunks().len() - 1);
+            png.chunks_mut().insert(pos, chunk);
+            png.encoder().write_to(&mut out).map_err(|e| e.to_string())?;
+        }
+        SaveFormat::Webp => {
+            use img_parts::ImageEXIF;
+            let mut webp = img_parts::webp::WebP::from_bytes(data.into()).map_err(|e| e.to_string())?;
+            // a WebP EXIF chunk a TIFF fejléccel kezdődik, "Exif\0\0" nélkül
+            let tiff = block.strip_prefix(b"Exif\0\0").unwrap_or(block);
+            webp.set_exif(Some(img_parts::Bytes::copy_from_slice(tiff)));
+            webp.encoder().write_to(&mut out).map_err(|e| e.to_string())?;
+        }
+        SaveFormat::Tif => {
+            let tree = ExifTree::from_exif_block(block)?;
+            out = write_tiff_exif(data, &tree)?;
+        }
+        SaveFormat::Bmp => {
+            // Az EXIF a pixeladat előtt áll (ahogy mentéskor beszúrjuk); a régit lecseréljük
+            if data.len() < 54 {
+                return Err("Hibás BMP fejléc".into());
+            }
+            let offset = u32::from_le_bytes(data[10..14].try_into().unwrap()) as usize;
+            if offset < 54 || offset > data.len() {
+                return Err("Hibás BMP pixel offset".into());
+            }
+            let start = data[54..offset].windows(6).position(|w| w == b"Exif\0\0").map_or(offset, |p| 54 + p);
+            out.extend_from_slice(&data[..start]);
+            out.extend_from_slice(block);
+            out.extend_from_slice(&data[offset..]);
+            let new_offset = (start + block.len()) as u32;
+            let new_size = out.len() as u32;
+            out[2..6].copy_from_slice(&new_size.to_le_bytes());
+            out[10..14].copy_from_slice(&new_offset.to_le_bytes());
+        }
+        _ => return Err(format!("{:?} fájlba nem írható EXIF", format)),
+    }
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let tmp = path.with_file_name(format!(".{}.tmp", name));
+    std::fs::write(&tmp, out).map_err(|e| e.to_string())?;
+    std::fs::rename(&tmp, path).map_err(|e| {
+        let _ = std::fs::remove_file(&tmp);
+        e.to_string()
+    })
+}
+
+// A szerkesztő ablak mezői szövegként
+#[derive(Clone, Default)]
+pub struct ExifEditor {
+    pub artist: String,
+    pub copyright: String,
+    pub description: String,
+    pub date_original: String,
+    pub latitude: String,
+    pub longitude: String,
+    pub altitude: String,
+    pub error: Option<String>,
+}
+
+impl ExifEditor {
+    pub fn from_tree(tree: &ExifTree) -> Self {
+        let gps = tree.gps_position();
+        Self {
+            artist: tree.get_ascii(IfdKind::Ifd0, TAG_ARTIST).unwrap_or_default(),
+            copyright: tree.get_ascii(IfdKind::Ifd0, TAG_COPYRIGHT).unwrap_or_default(),
+            description: tree.get_ascii(IfdKind::Ifd0, TAG_IMAGE_DESCRIPTION).unwrap_or_default(),
+            date_original: tree.get_ascii(IfdKind::Exif, TAG_DATETIME_ORIGINAL).unwrap_or_default(),
+            latitude: gps.map(|g| format!("{:.6}", g.0)).unwrap_or_default(),
+            longitude: gps.map(|g| format!("{:.6}", g.1)).unwrap_or_default(),
+            altitude: gps.and_then(|g| g.2).map(|a| format!("{:.1}", a)).unwrap_or_default(),
+            error: None,
+        }
+    }
+
+    pub fn apply(&self, tree: &mut ExifTree) -> Result<(), String> {
+        let date = self.date_original.trim();
+        if !date.is_empty() && chrono::NaiveDateTime::parse_from_str(date, "%Y:%m:%d %H:%M:%S").is_err() {
+            return Err("Date format: YYYY:MM:DD HH:MM:SS".into());
+        }
+        let (lat, lon, alt) = (self.latitude.trim(), self.longitude.trim(), self.altitude.trim());
+        let gps = if lat.is_empty() && lon.is_empty() {
+            None
+        } else {
+            let lat: f64 = lat.parse().ok().filter(|v: &f64| v.abs() <= 90.0).ok_or("Latitude: -90 .. 90")?;
+            let lon: f64 = lon.parse().ok().filter(|v: &f64| v.abs() <= 180.0).ok_or("Longitude: -180 .. 180")?;
+            let alt: Option<f64> = if alt.is_empty() { None } else { Some(alt.parse().map_err(|_| "Altitude: meters")?) };
+            Some((lat, lon, alt))
+        };
+        tree.set_ascii(IfdKind::Ifd0, TAG_ARTIST, self.artist.trim());
+        tree.set_ascii(IfdKind::Ifd0, TAG_COPYRIGHT, self.copyright.trim());
+        tree.set_ascii(IfdKind::Ifd0, TAG_IMAGE_DESCRIPTION, self.description.trim());
+        tree.set_ascii(IfdKind::Exif, TAG_DATETIME_ORIGINAL, date);
+        match gps {
+            Some((lat, lon, alt)) => tree.set_gps_position(lat, lon, alt),
+            None => tree.gps = Ifd::default(),
+        }
+        Ok(())
+    }
+}
+
+impl ImageViewer {
+
+    // A mentendő EXIF blokk a választott mód szerint; hibánál inkább semmit sem írunk
+    pub fn exif_for_save(&self, mode: MetadataMode) -> Option<ExifBlock> {
+        let exif = self.exif.as_ref()?;
+        let result = match mode {
+            MetadataMode::KeepAll => Ok(exif.clone()),
+            MetadataMode::RemoveGps => exif.without_gps(),
+            MetadataMode::Minimal => exif.minimal(),
+            MetadataMode::StripAll => return None,
+        };
+        result.map_err(|e| println!("EXIF szűrési hiba: {}", e)).ok()
+    }
+
+    pub fn open_exif_editor(&mut self) {
+        let tree = self.exif.as_ref().and_then(|e| e.tree().ok()).unwrap_or_default();
+        self.exif_editor = Some(ExifEditor::from_tree(&tree));
+    }
+
+    // A szerkesztett mezők az EXIF blokkba; write esetén a fájlba is
+    pub fn apply_exif_editor(&mut self, write: bool) -> Result<(), String> {
+        let Some(editor) = self.exif_editor.clone() else {
+            return Ok(());
+        };
+        let mut tree = match &self.exif {
+            Some(exif) => exif.tree()?,
+            None => ExifTree::default(),
+        };
+        editor.apply(&mut tree)?;
+        let block = ExifBlock::from_tree(&tree)?;
+        if write {
+            let path = self.image_full_path.clone().ok_or("Nincs megnyitott fájl")?;
+            if self.image_format != SaveFormat::Tif {
+                tree.check_makernote(&block.raw_exif)?;
+            }
+            write_exif_to_file(&path, self.image_format, &block.raw_exif)?;
+            self.file_meta = std::fs::metadata(&path).ok();
+        }
+        self.exif = Some(block);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENS: &[u8] = b"EF50mm f/1.8\0";
+
+    // Kézzel összerakott Canon blokk: IFD0 (Make), Exif IFD, majd közvetlenül utána a MakerNote,
+    // amelynek a LensModel offsetje a TIFF fejléchez képest értendő, mint a valódi Canon fájlokban
+    fn canon_block() -> (Vec<u8>, u32) {
+        let mut t = b"II*\0".to_vec();
+        t.extend_from_slice(&8u32.to_le_bytes());
+        let entry = |t: &mut Vec<u8>, tag: u16, format: u16, count: u32, value: u32| {
+            t.extend_from_slice(&tag.to_le_bytes());
+            t.extend_from_slice(&format.to_le_bytes());
+            t.extend_from_slice(&count.to_le_bytes());
+            t.extend_from_slice(&value.to_le_bytes());
+        };
+        // IFD0: 8 .. 38, "Canon\0" 38 .. 44
+        t.extend_from_slice(&2u16.to_le_bytes());
+        entry(&mut t, 0x010F, 2, 6, 38);
+        entry(&mut t, TAG_EXIF_IFD, 4, 1, 44);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        t.extend_from_slice(b"Canon\0");
+        // Exif IFD: 44 .. 62, a MakerNote 62-től
+        let maker_offset = 62u32;
+        let maker_len = 2 + 12 + 4 + LENS.len() as u32;
+        t.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut t, TAG_MAKERNOTE, 7, maker_len, maker_offset);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        t.extend_from_slice(&1u16.to_le_bytes());
+        entry(&mut t, 0x0095, 2, LENS.len() as u32, maker_offset + 18);
+        t.extend_from_slice(&0u32.to_le_bytes());
+        t.extend_from_slice(LENS);
+        let mut block = b"Exif\0\0".to_vec();
+        block.extend_from_slice(&t);
+        (block, maker_offset)
+    }
+
+    fn tiny_jpeg() -> Vec<u8> {
+        let mut buffer = Vec::new();
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::new(8, 8));
+        img.write_with_encoder(image::codecs::jpeg::JpegEncoder::new(&mut buffer)).unwrap();
+        buffer
+    }
+
+    fn lens_model(block: &[u8]) -> Option<String> {
+        let exif = ExifBlock::default().open(block, block.len()).ok()?;
+        exif.get_field("Canon.LensModel")
+    }
+
+    // A fejléc olvasás ugyanazt a blokkot adja, mint az egész fájl feldolgozása
+    #[test]
+    fn exif_from_header_only() {
+        let (block, _) = canon_block();
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::new(8, 8));
+        for (format, ext) in [(SaveFormat::Jpeg, "jpg"), (SaveFormat::Png, "png"), (SaveFormat::Webp, "webp")] {
+            let path = std::env::temp_dir().join(format!("iview_header_{}.{}", std::process::id(), ext));
+            img.save(&path).unwrap();
+            assert_eq!(exif_block_from_path(&path, format), None);
+            write_exif_to_file(&path, format, &block).unwrap();
+            let full = exif_block_from_file(&std::fs::read(&path).unwrap(), format);
+            let header = exif_block_from_path(&path, format);
+            let _ = std::fs::remove_file(&path);
+            assert!(full.is_some());
+            assert_eq!(header, full, "{}", ext);
+        }
+    }
+
+    // Geotagelés: a GPS IFD miatt a könyvtárak nőnek, a MakerNote-nak mégis a helyén kell maradnia
+    #[test]
+    fn geotag_keeps_makernote() {
+        let (block, maker_offset) = canon_block();
+        assert_eq!(lens_model(&block).as_deref(), Some("\"EF50mm f/1.8\""));
+
+        let path = std::env::temp_dir().join(format!("iview_makernote_{}.jpg", std::process::id()));
+        std::fs::write(&path, tiny_jpeg()).unwrap();
+        write_exif_to_file(&path, SaveFormat::Jpeg, &block).unwrap();
+
+        let buffer = std::fs::read(&path).unwrap();
+        let mut tree = ExifTree::from_exif_block(&exif_block_from_file(&buffer, SaveFormat::Jpeg).unwrap()).unwrap();
+        tree.set_gps_position(47.5, -19.25, Some(120.0));
+        let new_block = tree.to_exif_block();
+        tree.check_makernote(&new_block).unwrap();
+        write_exif_to_file(&path, SaveFormat::Jpeg, &new_block).unwrap();
+
+        let written = exif_block_from_file(&std::fs::read(&path).unwrap(), SaveFormat::Jpeg).unwrap();
+        let _ = std::fs::remove_file(&path);
+        let tree = ExifTree::from_exif_block(&written).unwrap();
+        assert_eq!(tree.exif.get(TAG_MAKERNOTE).map(|e| e.offset), Some(maker_offset));
+        assert_eq!(lens_model(&written).as_deref(), Some("\"EF50mm f/1.8\""));
+        let (lat, lon, alt) = tree.gps_position().unwrap();
+        assert!((lat - 47.5).abs() < 1e-6 && (lon + 19.25).abs() < 1e-6);
+        assert_eq!(alt, Some(120.0));
+    }
+    // Programból összerakott fa minden könyvtárral és JPEG bélyegképpel
+    fn sample_tree(motorola: bool) -> ExifTree {
+        let mut tree = ExifTree { motorola, ..Default::default() };
+        tree.set_ascii(IfdKind::Ifd0, 0x010F, "Canon");
+        tree.ifd0.set(IfdEntry { tag: 0x011A, format: FMT_URATIONAL, count: 1, data: tree.rational_bytes(&[(300, 1)]), offset: 0 });
+        tree.set_ascii(IfdKind::Exif, 0x9003, "2024:05:01 12:30:00");
+        tree.set_gps_position(47.497912, -19.040235, Some(-12.5));
+        tree.ifd1.set(IfdEntry { tag: 0x0103, format: 3, count: 1, data: if motorola { vec![0, 6] } else { vec![6, 0] }, offset: 0 });
+        tree.thumbnail = tiny_jpeg();
+        tree
+    }
+
+    // Az offseteket és a bélyegkép helyét a szerializáló számolja, a többi bejegyzésnek változatlannak kell lennie
+    fn values(ifd: &Ifd) -> Vec<(u16, u16, u32, Vec<u8>)> {
+        let mut values: Vec<_> = ifd.entries.iter()
+            .filter(|e| ![TAG_EXIF_IFD, TAG_GPS_IFD, TAG_INTEROP_IFD, TAG_THUMB_OFFSET, TAG_THUMB_LENGTH].contains(&e.tag))
+            .map(|e| (e.tag, e.format, e.count, e.data.clone()))
+            .collect();
+        values.sort();
+        values
+    }
+
+    #[test]
+    fn block_round_trip() {
+        for motorola in [false, true] {
+            let tree = sample_tree(motorola);
+            let block = tree.to_exif_block();
+            assert_eq!(&block[6..8], if motorola { b"MM" } else { b"II" });
+            let parsed = ExifTree::from_exif_block(&block).unwrap();
+            assert_eq!(parsed.motorola, motorola);
+            for kind in [IfdKind::Ifd0, IfdKind::Exif, IfdKind::Gps] {
+                assert_eq!(values(parsed.ifd(kind)), values(tree.ifd(kind)), "{:?} {}", kind, motorola);
+            }
+            assert_eq!(values(&parsed.ifd1), values(&tree.ifd1));
+            assert_eq!(parsed.thumbnail, tree.thumbnail);
+            assert_eq!(parsed.get_ascii(IfdKind::Ifd0, 0x010F).as_deref(), Some("Canon"));
+            let (lat, lon, alt) = parsed.gps_position().unwrap();
+            assert!((lat - 47.497912).abs() < 1e-6 && (lon + 19.040235).abs() < 1e-6);
+            assert_eq!(alt, Some(-12.5));
+            // újra szerializálva bájtra ugyanaz
+            assert_eq!(parsed.to_exif_block(), block);
+            assert!(ExifBlock::default().open(&block, block.len()).is_ok());
+        }
+    }
+
+    // A kerekítés a másodpercből a percbe és a fokba visz át, 60" nem keletkezhet
+    #[test]
+    fn gps_seconds_carry() {
+        let mut tree = ExifTree::default();
+        tree.set_gps_position(47.9999999, -18.99999999, None);
+        let r = Reader { buf: &tree.gps.get(0x0002).unwrap().data, motorola: false };
+        assert_eq!([r.u32(0), r.u32(8), r.u32(16)], [Some(48), Some(0), Some(0)]);
+        let (lat, lon, _) = tree.gps_position().unwrap();
+        assert_eq!((lat, lon), (48.0, -19.0));
+        tree.set_gps_position(12.0 + 59.0 / 60.0 + 59.9996 / 3600.0, 0.0, None);
+        let r = Reader { buf: &tree.gps.get(0x0002).unwrap().data, motorola: false };
+        assert_eq!([r.u32(0), r.u32(8), r.u32(16)], [Some(13), Some(0), Some(0)]);
+    }
+
+    // Ismételt TIFF szerkesztésnél az előző metaadat helyére írunk, a fájl nem nő
+    #[test]
+    fn tiff_edit_does_not_grow() {
+        let path = std::env::temp_dir().join(format!("iview_grow_{}.tif", std::process::id()));
+        let img = image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(16, 16, |x, y| image::Rgb([x as u8 * 16, y as u8 * 16, 7])));
+        img.save(&path).unwrap();
+        let mut sizes = Vec::new();
+        for round in 0..3 {
+            let mut tree = sample_tree(round == 1);
+            tree.set_ascii(IfdKind::Ifd0, 0x013B, "Artist");
+            write_exif_to_file(&path, SaveFormat::Tif, &tree.to_exif_block()).unwrap();
+            sizes.push(std::fs::metadata(&path).unwrap().len());
+        }
+        let buffer = std::fs::read(&path).unwrap();
+        let decoded = image::load_from_memory(&buffer).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(sizes[0], sizes[2]);
+        assert_eq!(decoded.to_rgb8(), img.to_rgb8());
+        let tree = ExifTree::from_exif_block(&exif_block_from_tiff(&buffer).unwrap()).unwrap();
+        assert_eq!(tree.get_ascii(IfdKind::Ifd0, 0x013B).as_deref(), Some("Artist"));
+        assert!(tree.gps_position().is_some());
+    }
+}
@@ -515,7 +515,57 @@ impl FMT {
     }*/
 }
 
-const BYTESPERFORMAT: [usize; 13] = [0,1,1,2,4,8,1,1,2,4,8,4,8];
+pub const BYTESPERFORMAT: [usize; 13] = [0,1,1,2,4,8,1,1,2,4,8,4,8];
+
+// Az UTF-8 (exif 3.0) kódja 129, nem fér a táblába
+fn bytes_per_format(format: &FMT) -> usize {
+    match format {
+        FMT::UTF_8 => 1,
+        _ => BYTESPERFORMAT.get(format.clone() as usize).copied().unwrap_or(0),
+    }
+}
+
+// Az EXIF blokk feldolgozásának hibái (a jhead JWRN_EXIF_* üzenetei alapján, lásd a fájl végén)
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExifError {
+    NoHeader,                                 // JWRN_EXIF_9
+    InvalidAlignment,                         // JWRN_EXIF_10
+    InvalidStart,                             // JWRN_EXIF_11
+    SuspiciousFirstOffset(usize),             // JWRN_EXIF_12
+    NestingExceeded,                          // JWRN_EXIF_1
+    IllegalDirectorySize(usize),              // JWRN_EXIF_2
+    IllegalFormat { format: u16, tag: u16 },  // JWRN_EXIF_3
+    IllegalValuePointer { tag: u16 },         // JWRN_EXIF_4
+    IllegalDirectoryLink(usize),              // JWRN_EXIF_7
+    BadComponentCount { count: usize, tag: u16 },
+    CyclicDirectory(usize),                   // egy IFD önmagára vagy egy korábbira mutat
+    Truncated(usize),                         // olvasás a blokk végén túl
+}
+
+impl std::fmt::Display for ExifError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExifError::NoHeader => write!(f, "No exif header"),
+            ExifError::InvalidAlignment => write!(f, "Corrupt exif header: Invalid Exif alignment marker"),
+            ExifError::InvalidStart => write!(f, "Corrupt exif header: Invalid Exif start (1)"),
+            ExifError::SuspiciousFirstOffset(offset) =>
+                write!(f, "Corrupt exif header: Suspicious offset of first IFD value ({})", offset),
+            ExifError::NestingExceeded => write!(f, "Corrupt exif header: Maximum directory nesting exceeded"),
+            ExifError::IllegalDirectorySize(pos) => write!(f, "Corrupt exif header: Illegally sized directory at {}", pos),
+            ExifError::IllegalFormat { format, tag } =>
+                write!(f, "Corrupt exif header: Illegal number format {} for tag {:04x}", format, tag),
+            ExifError::IllegalValuePointer { tag } => write!(f, "Corrupt exif header: Illegal value pointer for tag {:04x}", tag),
+            ExifError::IllegalDirectoryLink(offset) =>
+                write!(f, "Corrupt exif header: Illegal exif or interop offset directory link ({})", offset),
+            ExifError::BadComponentCount { count, tag } =>
+                write!(f, "Corrupt exif header: bad component number {} for tag {:04x}", count, tag),
+            ExifError::CyclicDirectory(pos) => write!(f, "Corrupt exif header: Directory at {} is linked twice", pos),
+            ExifError::Truncated(pos) => write!(f, "Corrupt exif header: Data ends before offset {}", pos),
+        }
+    }
+}
+
+impl std::error::Error for ExifError {}
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[allow(non_camel_case_types)]
@@ -534,6 +584,8 @@ pub struct ExifBlock {
     pub make : String,
     pub thumbnailsize: usize,
     pub thumbnailoffset: usize,
+    #[serde(skip)]
+    visited_dirs: Vec<usize>, // a már feldolgozott IFD-k, a körkörös hivatkozás ellen
 }
 
 
@@ -554,6 +606,7 @@ impl Default for ExifBlock {
             make : "".into(),
             thumbnailsize: 0,
             thumbnailoffset: 0,
+            visited_dirs: Vec::new(),
         };
         tmp.init_exif_tags();
         tmp.init_gps_tags();
@@ -668,6 +721,32 @@ impl ExifBlock {
         return None;
     }
 
+    // Előjeles tizedes fok: S (Dél) és W (Nyugat) esetén negatív
+    pub fn gps_position(&self) -> Option<(f64, f64)> {
+        let mut lat = self.get_num_field("GPSLatitude")? as f64;
+        let mut lon = self.get_num_field("GPSLongitude")? as f64;
+        if self.get_field("GPSLatitudeRef").is_some_and(|r| r.contains('S')) {
+            lat = -lat;
+        }
+        if self.get_field("GPSLongitudeRef").is_some_and(|r| r.contains('W')) {
+            lon = -lon;
+        }
+        Some((lat, lon))
+    }
+
+    // Magasság méterben; GPSAltitudeRef 1 esetén tengerszint alatt
+    pub fn gps_altitude(&self) -> Option<f64> {
+        let alt = self.get_num_field("GPSAltitude")? as f64;
+        Some(if self.get_num_field("GPSAltitudeRef") == Some(1.0) { -alt } else { alt })
+    }
+
+    // Felvételi irány fokban; igaz, ha mágneses északhoz mért (M), különben földrajzi (T)
+    pub fn gps_direction(&self) -> Option<(f64, bool)> {
+        let direction = self.get_num_field("GPSImgDirection")? as f64;
+        let magnetic = self.get_field("GPSImgDirectionRef").is_some_and(|r| r.contains('M'));
+        Some((direction, magnetic))
+    }
+
     pub fn get_exif_tag(&self, id : u16) -> ExifTag {
         if let Some(tag) = self.exif_tags.iter().find(|t| t.id == id) {
             tag.clone()
@@ -686,52 +765,58 @@ impl ExifBlock {
         }
     }
     
-    fn read_buff_u16(&self, buff :&[u8], pos: usize) -> u16 {
-        let bytes = buff[pos..pos + 2].try_into().unwrap();
-        if self.motorola_order { u16::from_be_bytes(bytes) }
-        else { u16::from_le_bytes(bytes) }
+    fn read_bytes<const N: usize>(&self, pos: usize) -> Result<[u8; N], ExifError> {
+        pos.checked_add(N)
+            .and_then(|end| self.raw_exif.get(pos..end))
+            .and_then(|b| b.try_into().ok())
+            .ok_or(ExifError::Truncated(pos))
     }
-    
-    fn read_u16(&self, pos: usize) -> u16 {
-        let bytes = self.raw_exif[pos..pos + 2].try_into().unwrap();
-        if self.motorola_order { u16::from_be_bytes(bytes) }
-        else { u16::from_le_bytes(bytes) }
+
+    fn read_slice(&self, pos: usize, len: usize) -> Result<&[u8], ExifError> {
+        pos.checked_add(len)
+            .and_then(|end| self.raw_exif.get(pos..end))
+            .ok_or(ExifError::Truncated(pos))
     }
-    
-    fn read_u32(&self, pos: usize) -> u32 {
-        let bytes = self.raw_exif[pos..pos + 4].try_into().unwrap();
-        if self.motorola_order { u32::from_be_bytes(bytes) }
-        else { u32::from_le_bytes(bytes) }
+
+    fn read_u8(&self, pos: usize) -> Result<u8, ExifError> {
+        self.raw_exif.get(pos).copied().ok_or(ExifError::Truncated(pos))
     }
-    
-    fn read_i32(&self, pos: usize) -> i32 {
-        let bytes = self.raw_exif[pos..pos + 4].try_into().unwrap();
-        if self.motorola_order { i32::from_be_bytes(bytes) }
-        else { i32::from_le_bytes(bytes) }
+
+    fn read_u16(&self, pos: usize) -> Result<u16, ExifError> {
+        let bytes = self.read_bytes(pos)?;
+        Ok(if self.motorola_order { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
     }
-    
-    fn read_f32(&self, pos: usize) -> f32 {
-        let bytes = self.raw_exif[pos..pos + 4].try_into().unwrap();
-        if self.motorola_order { f32::from_be_bytes(bytes) }
-        else { f32::from_le_bytes(bytes) }
+
+    fn read_u32(&self, pos: usize) -> Result<u32, ExifError> {
+        let bytes = self.read_bytes(pos)?;
+        Ok(if self.motorola_order { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
     }
-    
-    fn read_f64(&self, pos: usize) -> f64 {
-        let bytes = self.raw_exif[pos..pos + 4].try_into().unwrap();
-        if self.motorola_order { f64::from_be_bytes(bytes) }
-        else { f64::from_le_bytes(bytes) }
+
+    fn read_i32(&self, pos: usize) -> Result<i32, ExifError> {
+        let bytes = self.read_bytes(pos)?;
+        Ok(if self.motorola_order { i32::from_be_bytes(bytes) } else { i32::from_le_bytes(bytes) })
+    }
+
+    fn read_f32(&self, pos: usize) -> Result<f32, ExifError> {
+        let bytes = self.read_bytes(pos)?;
+        Ok(if self.motorola_order { f32::from_be_bytes(bytes) } else { f32::from_le_bytes(bytes) })
+    }
+
+    fn read_f64(&self, pos: usize) -> Result<f64, ExifError> {
+        let bytes = self.read_bytes(pos)?;
+        Ok(if self.motorola_order { f64::from_be_bytes(bytes) } else { f64::from_le_bytes(bytes) })
     }
 
-    fn convert_format_usize(&self, valueptr: usize, format:& FMT) -> usize {
-       match format {
-            FMT::BYTE   => (self.raw_exif[valueptr] as u8) as usize,
-            FMT::SBYTE  => (self.raw_exif[valueptr] as i8) as usize,
-            FMT::USHORT => (self.read_u16(valueptr)) as usize,
-            FMT::SSHORT => (self.read_u16(valueptr) as i16) as usize,
-            FMT::ULONG  => (self.read_u32(valueptr)) as usize,
-            FMT::SLONG  => (self.read_i32(valueptr)) as usize,
+    fn convert_format_usize(&self, valueptr: usize, format:& FMT) -> Result<usize, ExifError> {
+       Ok(match format {
+            FMT::BYTE   => self.read_u8(valueptr)? as usize,
+            FMT::SBYTE  => (self.read_u8(valueptr)? as i8) as usize,
+            FMT::USHORT => (self.read_u16(valueptr)?) as usize,
+            FMT::SSHORT => (self.read_u16(valueptr)? as i16) as usize,
+            FMT::ULONG  => (self.read_u32(valueptr)?) as usize,
+            FMT::SLONG  => (self.read_i32(valueptr)?) as usize,
             _ => (0) as usize, 
-        }
+        })
     }
 
     pub fn patch_thumbnail(&mut self, new_thumb: &[u8]) {
@@ -743,92 +828,95 @@ impl ExifBlock {
         }
     }
 
-    pub fn patch_exifdata(&mut self, xres: f32, yres: f32, w: u32, h: u32) {
-        if let Some(entry) = self.find_tag("XResolution",0,true) {
-            let (nxf,nx) = if ((xres+0.5) as u32) as f32 == xres { (1.0,1u32) } else { (100000.0,100000u32) };
-            let dx = (xres * nxf + 0.5) as u32;
-            let mut bytes = if self.motorola_order { dx.to_be_bytes() } else { dx.to_le_bytes() };
-            self.raw_exif[entry.offset..entry.offset+4].copy_from_slice(&bytes);
-            bytes = if self.motorola_order { nx.to_be_bytes() } else { nx.to_le_bytes() };
-            self.raw_exif[entry.offset+4..entry.offset+8].copy_from_slice(&bytes);
-        }
+    // A tag értékének helye, ha a formátuma és a darabszáma az elvárt; egy gyártott fájlban bármi lehet
+    fn patch_target(&self, name: &str, formats: &[&str], min_count: usize) -> Option<(usize, String)> {
+        let entry = self.find_tag(name, 0, true)?;
+        let typ = entry.value.get("type")?.as_str()?.to_string();
+        let count = entry.value.get("count")?.as_u64()? as usize;
+        (formats.contains(&typ.as_str()) && count >= min_count).then_some((entry.offset, typ))
+    }
 
-        if let Some(entry) = self.find_tag("YResolution",0,true) {
-            let (nyf,ny) = if ((yres+0.5) as u32) as f32 == yres { (1.0,1u32) } else { (100000.0,100000u32) };
-            let dy = (yres * nyf + 0.5) as u32;
-            let mut bytes = if self.motorola_order { dy.to_be_bytes() } else { dy.to_le_bytes() };
-            self.raw_exif[entry.offset..entry.offset+4].copy_from_slice(&bytes);
-            bytes = if self.motorola_order { ny.to_be_bytes() } else { ny.to_le_bytes() };
-            self.raw_exif[entry.offset+4..entry.offset+8].copy_from_slice(&bytes);
+    // Csak a blokkon belül írunk; ha nem fér el, a tag marad a régi
+    fn patch_bytes(&mut self, offset: usize, bytes: &[u8]) {
+        if let Some(target) = offset.checked_add(bytes.len()).and_then(|end| self.raw_exif.get_mut(offset..end)) {
+            target.copy_from_slice(bytes);
         }
+    }
+
+    fn patch_u16(&mut self, offset: usize, v: u16) {
+        let bytes = if self.motorola_order { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.patch_bytes(offset, &bytes);
+    }
+
+    fn patch_u32(&mut self, offset: usize, v: u32) {
+        let bytes = if self.motorola_order { v.to_be_bytes() } else { v.to_le_bytes() };
+        self.patch_bytes(offset, &bytes);
+    }
 
-        if let Some(entry) = self.find_tag("Orientation",0,true) {
-            let ori = 1u16;
-            let bytes = if self.motorola_order { ori.to_be_bytes() } else { ori.to_le_bytes() };
-            self.raw_exif[entry.offset..entry.offset+2].copy_from_slice(&bytes);
+    // SHORT vagy LONG méret; SHORT-ba csak akkor, ha belefér
+    fn patch_dimension(&mut self, names: [&str; 2], v: u32) {
+        let target = names.iter().find_map(|name| self.patch_target(name, &["USHORT", "ULONG"], 1));
+        match target {
+            Some((offset, typ)) if typ == "ULONG" => self.patch_u32(offset, v),
+            Some((offset, _)) if v <= u16::MAX as u32 => self.patch_u16(offset, v as u16),
+            _ => {}
         }
+    }
 
-        if let Some(entry) = self.find_tag("DateTime",0,true) {
-            let current_date = chrono::Local::now().format("%Y:%m:%d %H:%M:%S").to_string();
-            let bytes = current_date.as_bytes();
-            if bytes.len() <= 20 {
-                self.raw_exif[entry.offset..entry.offset + bytes.len()].copy_from_slice(bytes);
+    pub fn patch_exifdata(&mut self, xres: f32, yres: f32, w: u32, h: u32) {
+        for (name, res) in [("XResolution", xres), ("YResolution", yres)] {
+            if let Some((offset, _)) = self.patch_target(name, &["URATIONAL"], 1) {
+                let (nf, n) = if ((res+0.5) as u32) as f32 == res { (1.0,1u32) } else { (100000.0,100000u32) };
+                let d = (res * nf + 0.5) as u32;
+                self.patch_u32(offset, d);
+                self.patch_u32(offset + 4, n);
             }
         }
 
-        if let Some(entry) = self.find_tag("PixelXDimension",0,true) {
-            let bytes = if self.motorola_order { w.to_be_bytes() } else { w.to_le_bytes() };
-            self.raw_exif[entry.offset..entry.offset+4].copy_from_slice(&bytes);
-        }
-        else if let Some(entry) = self.find_tag("ImageWidth",0,true) {
-            let bytes = if self.motorola_order { w.to_be_bytes() } else { w.to_le_bytes() };
-            self.raw_exif[entry.offset..entry.offset+4].copy_from_slice(&bytes);
+        if let Some((offset, _)) = self.patch_target("DateTime", &["STRING"], 19) {
+            let current_date = chrono::Local::now().format("%Y:%m:%d %H:%M:%S").to_string();
+            self.patch_bytes(offset, current_date.as_bytes());
         }
 
-        if let Some(entry) = self.find_tag("PixelYDimension",0,true) {
-            let bytes = if self.motorola_order { h.to_be_bytes() } else { h.to_le_bytes() };
-            self.raw_exif[entry.offset..entry.offset+4].copy_from_slice(&bytes);
-        }
-        else if let Some(entry) = self.find_tag("ImageLength",0,true) {
-            let bytes = if self.motorola_order { h.to_be_bytes() } else { h.to_le_bytes() };
-            self.raw_exif[entry.offset..entry.offset+4].copy_from_slice(&bytes);
-        }
+        self.patch_dimension(["PixelXDimension", "ImageWidth"], w);
+        self.patch_dimension(["PixelYDimension", "ImageLength"], h);
 
-        if let Some(entry) = self.find_tag("Orientation",0,true) {
-            let ori = 1u16;
-            let bytes = if self.motorola_order { ori.to_be_bytes() } else { ori.to_le_bytes() };
-            self.raw_exif[entry.offset..entry.offset+2].copy_from_slice(&bytes);
+        if let Some((offset, _)) = self.patch_target("Orientation", &["USHORT"], 1) {
+            self.patch_u16(offset, 1);
         }
     }
 
-    pub fn open(&mut self, exifsection: &[u8],  length: usize) -> Result<ExifBlock, String> {
+    pub fn open(&mut self, exifsection: &[u8],  length: usize) -> Result<ExifBlock, ExifError> {
+        // a megadott hossz nem lehet nagyobb a tényleges adatnál
+        let length = length.min(exifsection.len());
+        let exifsection = &exifsection[..length];
         let exifheader: [u8; 6] = [b'E',b'x',b'i',b'f',0,0];
-        if exifsection[0..6] != exifheader {
-            return Err("No exif header".into());
+        if exifsection.get(0..6) != Some(&exifheader[..]) {
+            return Err(ExifError::NoHeader);
         }
         let motorola: [u8; 2] = [b'M',b'M'];
         let intel: [u8; 2] = [b'I',b'I'];
-        if exifsection[6..8] == motorola {
-            self.motorola_order = true;
-        } else if exifsection[6..8] == intel {
-            self.motorola_order = false;
-        }else{
-            return Err("Corrupt exif header: Invalid Exif alignment marker".into());
+        match exifsection.get(6..8) {
+            Some(marker) if marker == motorola => self.motorola_order = true,
+            Some(marker) if marker == intel => self.motorola_order = false,
+            _ => return Err(ExifError::InvalidAlignment),
         }
 
-        if self.read_buff_u16(&exifsection,8) != 0x2a {
-            return Err("Corrupt exif header: Invalid Exif start (1)".into())
+        self.raw_exif = exifsection.to_vec();
+        if self.read_u16(8)? != 0x2a {
+            return Err(ExifError::InvalidStart)
         }
 
-        let firstoffset = self.read_buff_u16(&exifsection,10) as usize;
+        // az első IFD offsetje 32 bites (MM sorrendnél a 16 bites olvasás 0-t adna)
+        let firstoffset = self.read_u32(10)? as usize;
         if firstoffset < 8 || firstoffset > 32000 {
-            return Err("Corrupt exif header: Suspicious offset of first IFD value".into());
+            return Err(ExifError::SuspiciousFirstOffset(firstoffset));
         }
 
-        self.raw_exif = exifsection.to_vec();
         self.raw_exif_length = length;
         self.lastexifrefd = 0;
         self.dirwiththumbnailptrs = 0;
+        self.visited_dirs.clear();
         
         // First directory starts 16 bytes in.  All offset are relative to 8 bytes in.
         self.nesting_level+=1;
@@ -836,8 +924,7 @@ impl ExifBlock {
         self.nesting_level-=1;
         
         if self.thumbnailsize != 0 && self.thumbnailoffset != 0 {
-            if self.thumbnailsize + self.thumbnailoffset <= length {
-                let raw_bytes = &self.raw_exif[self.thumbnailoffset..self.thumbnailoffset + self.thumbnailsize];
+            if let Ok(raw_bytes) = self.read_slice(self.thumbnailoffset, self.thumbnailsize) {
                 let value = json!(general_purpose::STANDARD.encode(raw_bytes));
                 json.insert("Thumbnail".to_string(), value);
             }
@@ -855,13 +942,34 @@ impl ExifBlock {
         start + 2 + 12 * entry
     }
 
+    // Az érték helye: 4 bájtig az entry-ben, fölötte az entry offsetet tartalmaz
+    fn value_pointer(&self, direntry: usize, bytecount: usize, offsetbase: usize, exiflength: usize, tag: u16) -> Result<usize, ExifError> {
+        if bytecount <= 4 {
+            return Ok(direntry+8);
+        }
+        let offsetval = self.read_u32(direntry+8)? as usize;
+        if offsetval.checked_add(bytecount).is_none_or(|end| end > exiflength) {
+            return Err(ExifError::IllegalValuePointer { tag });
+        }
+        Ok(offsetbase+offsetval)
+    }
+
+    fn enter_dir(&mut self, dirstart: usize) -> Result<(), ExifError> {
+        if self.visited_dirs.contains(&dirstart) {
+            return Err(ExifError::CyclicDirectory(dirstart));
+        }
+        self.visited_dirs.push(dirstart);
+        Ok(())
+    }
+
 
-    fn process_exif_dir(&mut self, dirstart: usize, offsetbase: usize, exiflength: usize) ->  Result<Map<String, Value>, String> {
+    fn process_exif_dir(&mut self, dirstart: usize, offsetbase: usize, exiflength: usize) ->  Result<Map<String, Value>, ExifError> {
 
-        let numdirentries = self.read_u16(dirstart) as usize;
         if self.nesting_level > 4 {
-            return Err("Corrupt exif header: Maximum directory nesting exceeded".into());
+            return Err(ExifError::NestingExceeded);
         }
+        self.enter_dir(dirstart)?;
+        let numdirentries = self.read_u16(dirstart)? as usize;
 
         let dirend = Self::dir_entry_addr(dirstart, numdirentries);
         if dirend+4 > offsetbase+exiflength {
@@ -871,7 +979,7 @@ impl ExifBlock {
             }else{
                 // note: files that had thumbnails trimmed with jhead 1.3 or earlier
                 // might trigger this.
-                return Err("Corrupt exif header: Illegally sized directory".into());
+                return Err(ExifError::IllegalDirectorySize(dirstart));
             }
         }
         if dirend > self.lastexifrefd { self.lastexifrefd = dirend; }
@@ -882,29 +990,22 @@ impl ExifBlock {
             let idx = de_idx as usize;
             let direntry = Self::dir_entry_addr(dirstart, idx);
             
-            let tag = self.get_exif_tag(self.read_u16(direntry));
-            let format = FMT::from(self.read_u16(direntry+2));
+            let tag_id = self.read_u16(direntry)?;
+            let tag = self.get_exif_tag(tag_id);
+            let format_id = self.read_u16(direntry+2)?;
+            let format = FMT::from(format_id);
             if format == FMT::NUM_FORMATS {
-                return Err(format!("Corrupt exif header: Illegal number format {:?} for tag {:?}", format, tag.name));
+                return Err(ExifError::IllegalFormat { format: format_id, tag: tag_id });
             }
-            let components = self.read_u32(direntry+4) as usize;
-            let bytecount = components * BYTESPERFORMAT[format.clone() as usize];
+            let components = self.read_u32(direntry+4)? as usize;
+            let bytecount = components.checked_mul(bytes_per_format(&format))
+                .ok_or(ExifError::BadComponentCount { count: components, tag: tag_id })?;
             
             let mut json_tag: Map<String, Value> = Map::new();
             json_tag.insert("type".to_string(),json!(format));
             json_tag.insert("count".to_string(),json!(components));
             
-            let valueptr = if bytecount > 4 {
-                // if its bigger than 4 bytes, the dir entry contains an offset.
-                let offsetval = self.read_u32(direntry+8) as usize;
-                if offsetval+bytecount > exiflength {
-                    return Err(format!("Corrupt exif header: Illegal value pointer for tag {:?}",tag.name));
-                }
-                offsetbase+offsetval
-            }else{
-                // 4 bytes or less and value is in the dir entry itself
-                direntry+8
-            };
+            let valueptr = self.value_pointer(direntry, bytecount, offsetbase, exiflength, tag_id)?;
 
             if self.lastexifrefd < valueptr+bytecount {
                 // keep track of last byte in the exif header that was actually referenced.
@@ -914,9 +1015,9 @@ impl ExifBlock {
 
             match tag.enu {
                 ExifTagId::GPSInfo => {
-                        let subdirstart = offsetbase + self.read_u32(valueptr) as usize;
+                        let subdirstart = offsetbase + self.read_u32(valueptr)? as usize;
                         if subdirstart < offsetbase || subdirstart > offsetbase+exiflength {
-                            return Err("Corrupt exif header: Illegal exif or interop ofset directory link".into());
+                            return Err(ExifError::IllegalDirectoryLink(subdirstart));
                         }else{
                             self.nesting_level+=1;
                             let json = self.process_gps_info(subdirstart, offsetbase, exiflength)?;
@@ -926,9 +1027,9 @@ impl ExifBlock {
                         continue;
                     },
                 ExifTagId::EXIF_OFFSET | ExifTagId::INTEROP_OFFSET => {
-                        let subdirstart = offsetbase + self.read_u32(valueptr) as usize;
+                        let subdirstart = offsetbase + self.read_u32(valueptr)? as usize;
                         if subdirstart < offsetbase || subdirstart > offsetbase+exiflength {
-                            return Err("Corrupt exif header: Illegal exif or interop offset directory link".into());
+                            return Err(ExifError::IllegalDirectoryLink(subdirstart));
                         }else{
                             self.nesting_level+=1;
                             let json = self.process_exif_dir(subdirstart, offsetbase, exiflength)?;
@@ -938,17 +1039,17 @@ impl ExifBlock {
                         continue;
                     },
                 ExifTagId::ThumbnailOffset => {
-                        self.thumbnailoffset = self.convert_format_usize(valueptr, &format);
+                        self.thumbnailoffset = self.convert_format_usize(valueptr, &format)?;
                         self.dirwiththumbnailptrs = dirstart;
                     },
                 ExifTagId::ThumbnailLength => {
-                        self.thumbnailsize = self.convert_format_usize(valueptr, &format);
+                        self.thumbnailsize = self.convert_format_usize(valueptr, &format)?;
                     },
                  _ => {},
                 }
 
             let (value, insert_to_flat) = self.get_entry_value(format,valueptr,components,bytecount,
-                    tag.enu == ExifTagId::Make,   tag.enu == ExifTagId::MakerNote && self.make == "Canon");
+                    tag.enu == ExifTagId::Make,   tag.enu == ExifTagId::MakerNote && self.make == "Canon")?;
 
             let mut copy_json_tag = json_tag.clone();
             json_tag.insert("val".into(), value);
@@ -963,7 +1064,11 @@ impl ExifBlock {
                 let jsontag_value = serde_json::json!(copy_json_tag);
                 self.entry_data_vector.push( ExifTagEntry{ name:tag.name.clone(), value:jsontag_value, offset:valueptr} );
             }
+            let is_note = tag.enu == ExifTagId::MakerNote;
             result.insert(tag.name, jsontag_value);
+            if is_note {
+                self.process_maker_note(valueptr, bytecount);
+            }
 
         }
 
@@ -971,18 +1076,15 @@ impl ExifBlock {
         // there's also a potential link to another directory at the end of each
         // directory.  this has got to be the result of a comitee!
         if Self::dir_entry_addr(dirstart, numdirentries) + 4 <= offsetbase+exiflength {
-             let offset = self.read_u32(dirstart+2+12*numdirentries) as usize;
+             let offset = self.read_u32(dirstart+2+12*numdirentries)? as usize;
              if offset != 0 {
                 let subdirstart = offsetbase + offset;
-                if subdirstart > offsetbase+exiflength {
-                } else {
-                   if subdirstart <= offsetbase+exiflength {
-                      //inf->exiftext("%*ccontinued ",level*4,' ');
-                      self.nesting_level+=1;
-                      let json = self.process_exif_dir(subdirstart, offsetbase, exiflength)?;
-                      self.nesting_level-=1;
-                      result.insert("ExtraExifDir".to_string(), json!(json));
-                   }
+                if subdirstart <= offsetbase+exiflength {
+                   //inf->exiftext("%*ccontinued ",level*4,' ');
+                   self.nesting_level+=1;
+                   let json = self.process_exif_dir(subdirstart, offsetbase, exiflength)?;
+                   self.nesting_level-=1;
+                   result.insert("ExtraExifDir".to_string(), json!(json));
                 }
              }
         } else {
@@ -992,15 +1094,15 @@ impl ExifBlock {
         Ok(result)
     }
 
-
     //fn PrintFormatNumber(&mut self,valueptr: usize, format: FMT, bytecount: i32) {}
-    fn process_gps_info(&mut self, dirstart: usize, offsetbase: usize, exiflength: usize) ->  Result<Map<String, Value>, String>  {
-        let numdirentries = self.read_u16(dirstart) as usize;
+    fn process_gps_info(&mut self, dirstart: usize, offsetbase: usize, exiflength: usize) ->  Result<Map<String, Value>, ExifError>  {
+        self.enter_dir(dirstart)?;
+        let numdirentries = self.read_u16(dirstart)? as usize;
         let dirend = Self::dir_entry_addr(dirstart, numdirentries);
         if dirend > (offsetbase+exiflength) {
             // Note: Files that had thumbnails trimmed with jhead 1.3 or earlier
             // might trigger this.
-            return Err("Corrupt exif header: Illegally sized directory".into());
+            return Err(ExifError::IllegalDirectorySize(dirstart));
         }
 
         let mut result = Map::new();
@@ -1008,32 +1110,24 @@ impl ExifBlock {
         for de in 0..numdirentries {
             let idx = de as usize;
             let direntry = Self::dir_entry_addr(dirstart, idx);
-            let tag = self.get_gps_tag(self.read_u16(direntry));
-            let format = FMT::from(self.read_u16(direntry+2));
+            let tag_id = self.read_u16(direntry)?;
+            let tag = self.get_gps_tag(tag_id);
+            let format_id = self.read_u16(direntry+2)?;
+            let format = FMT::from(format_id);
             if format == FMT::NUM_FORMATS {
-                return Err(format!("Corrupt exif header: Illegal number format {:?} for tag {:?}", format, tag.name));
+                return Err(ExifError::IllegalFormat { format: format_id, tag: tag_id });
             }
-            let components = self.read_u32(direntry+4) as usize;
+            let components = self.read_u32(direntry+4)? as usize;
             if components < 1 || components > 32768 {
-                return Err(format!("Corrupt exif header: bad component number"));
+                return Err(ExifError::BadComponentCount { count: components, tag: tag_id });
             }
-            let bytecount = components * BYTESPERFORMAT[format.clone() as usize];
+            let bytecount = components * bytes_per_format(&format);
 
             let mut json_tag: Map<String, Value> = Map::new();
             json_tag.insert("type".to_string(),json!(format));
             json_tag.insert("count".to_string(),json!(components));
             
-            let valueptr = if bytecount > 4 {
-                // if its bigger than 4 bytes, the dir entry contains an offset.
-                let offsetval = self.read_u32(direntry+8) as usize;
-                if offsetval+bytecount > exiflength {
-                    return Err(format!("Corrupt exif header: Illegal value pointer for tag {:?}",tag.name));
-                }
-                offsetbase+offsetval
-            }else{
-                // 4 bytes or less and value is in the dir entry itself
-                direntry+8
-            };
+            let valueptr = self.value_pointer(direntry, bytecount, offsetbase, exiflength, tag_id)?;
 
             if self.lastexifrefd < valueptr+bytecount {
                 // keep track of last byte in the exif header that was actually referenced.
@@ -1041,7 +1135,7 @@ impl ExifBlock {
                 self.lastexifrefd = valueptr+bytecount;
             }
 
-            let (value, insert_to_flat) = self.get_entry_value(format,valueptr,components,bytecount, false, false);
+            let (value, insert_to_flat) = self.get_entry_value(format,valueptr,components,bytecount, false, false)?;
 
             let mut copy_json_tag = json_tag.clone();
             json_tag.insert("val".into(), value);
@@ -1065,109 +1159,111 @@ impl ExifBlock {
 
 
     fn get_entry_value(&mut self, format: FMT, mut valueptr: usize, components: usize,
-            bytecount: usize, is_make: bool, _is_note: bool) -> ( Value, bool) {
+            bytecount: usize, is_make: bool, _is_note: bool) -> Result<(Value, bool), ExifError> {
         match format {
             FMT::UNDEFINED | FMT::STRING | FMT::UTF_8 => {
-                let raw_bytes = &self.raw_exif[valueptr..valueptr + bytecount];
+                let raw_bytes = self.read_slice(valueptr, bytecount)?;
                 let clean_bytes = raw_bytes.split(|&b| b == 0).next().unwrap_or(&[]);
                 let text = String::from_utf8_lossy(clean_bytes).to_string();
                 if is_make {
                     self.make = text.clone();
                 }
-                return ( json!(text), true);
+                return Ok(( json!(text), true));
             },
             FMT::BYTE   => {
                 //if is_note {
                 //    json!(process_maker_note(valueptr, bytecount, offsetbase, exiflength))
                 //}
-                let raw_bytes = &self.raw_exif[valueptr..valueptr + bytecount];
-                if bytecount<=120 { return (json!(raw_bytes),true); }
-                else { return (json!(general_purpose::STANDARD.encode(raw_bytes)), false); }
+                let raw_bytes = self.read_slice(valueptr, bytecount)?;
+                if bytecount<=120 { return Ok((json!(raw_bytes),true)); }
+                else { return Ok((json!(general_purpose::STANDARD.encode(raw_bytes)), false)); }
             },
-            FMT::SBYTE  =>
+            FMT::SBYTE  => {
+                let raw_bytes = self.read_slice(valueptr, bytecount)?;
                 if bytecount<=120 {
-                    let signed_bytes: Vec<i8> = self.raw_exif[valueptr..valueptr + bytecount]
-                        .iter().map(|&b| b as i8).collect();
-                    return (json!(signed_bytes),true);
+                    let signed_bytes: Vec<i8> = raw_bytes.iter().map(|&b| b as i8).collect();
+                    return Ok((json!(signed_bytes),true));
                 }
                 else {
-                    let raw_bytes = &self.raw_exif[valueptr..valueptr + bytecount];
-                    return (json!(general_purpose::STANDARD.encode(raw_bytes)), false);
-                },
-            FMT::USHORT => if components == 1 { return (json!(self.read_u16(valueptr)),true); },
-            FMT::SSHORT => if components == 1 { return (json!(self.read_u16(valueptr) as i16),true); },
-            FMT::ULONG  => if components == 1 { return (json!(self.read_u32(valueptr)),true); },
-            FMT::SLONG  => if components == 1 { return (json!(self.read_i32(valueptr)),true); },
+                    return Ok((json!(general_purpose::STANDARD.encode(raw_bytes)), false));
+                }
+            },
+            FMT::USHORT => if components == 1 { return Ok((json!(self.read_u16(valueptr)?),true)); },
+            FMT::SSHORT => if components == 1 { return Ok((json!(self.read_u16(valueptr)? as i16),true)); },
+            FMT::ULONG  => if components == 1 { return Ok((json!(self.read_u32(valueptr)?),true)); },
+            FMT::SLONG  => if components == 1 { return Ok((json!(self.read_i32(valueptr)?),true)); },
             FMT::URATIONAL | FMT::SRATIONAL => if components == 1 {
-                let num = self.read_u32(valueptr);
-                let den = self.read_u32(valueptr + 4);
-                return (json!([num, den]),true);
+                let num = self.read_u32(valueptr)?;
+                let den = self.read_u32(valueptr + 4)?;
+                return Ok((json!([num, den]),true));
             },
-            FMT::SINGLE => if components == 1 { return (json!(self.read_f32(valueptr)),true); },
-            FMT::DOUBLE => if components == 1 { return (json!(self.read_f64(valueptr)),true); },
-            _ => return (json!(null),false), // Ismeretlen formátum esetén
+            FMT::SINGLE => if components == 1 { return Ok((json!(self.read_f32(valueptr)?),true)); },
+            FMT::DOUBLE => if components == 1 { return Ok((json!(self.read_f64(valueptr)?),true)); },
+            _ => return Ok((json!(null),false)), // Ismeretlen formátum esetén
         }
+        // a darabszámot a value_pointer már a blokk méretéhez ellenőrizte
+        self.read_slice(valueptr, bytecount)?;
         
         match format {
             FMT::USHORT => {
                 let mut values = Vec::new();        
                 for _i in 0..components {
-                    values.push(self.read_u16(valueptr));
+                    values.push(self.read_u16(valueptr)?);
                     valueptr += 2;
                 } 
-                return (json!(values), true);
+                return Ok((json!(values), true));
             },
             FMT::SSHORT => {
                 let mut values = Vec::new();        
                 for _i in 0..components {
-                    values.push(self.read_u16(valueptr) as i16);
+                    values.push(self.read_u16(valueptr)? as i16);
                     valueptr+= 2;
                 } 
-                return (json!(values), true);
+                return Ok((json!(values), true));
             },
             FMT::ULONG  => {
                 let mut values = Vec::new();        
                 for _i in 0..components {
-                    values.push(self.read_u32(valueptr));
+                    values.push(self.read_u32(valueptr)?);
                     valueptr += 4;
                 } 
-                return (json!(values), true);
+                return Ok((json!(values), true));
             },
             FMT::SLONG  => {
                 let mut values = Vec::new();        
                 for _i in 0..components {
-                    values.push(self.read_i32(valueptr));
+                    values.push(self.read_i32(valueptr)?);
                     valueptr += 4;
                 } 
-                return (json!(values), true);
+                return Ok((json!(values), true));
             },
             FMT::URATIONAL | FMT::SRATIONAL => {
                 let mut values = Vec::new();        
                 for _i in 0..components {
-                    let num = self.read_u32(valueptr);
-                    let den = self.read_u32(valueptr + 4);
+                    let num = self.read_u32(valueptr)?;
+                    let den = self.read_u32(valueptr + 4)?;
                     values.push([num, den]);
                     valueptr += 8;
                 } 
-                return (json!(values), true);
+                return Ok((json!(values), true));
             },
             FMT::SINGLE => {
                 let mut values = Vec::new();        
                 for _i in 0..components {
-                    values.push(self.read_f32(valueptr));
+                    values.push(self.read_f32(valueptr)?);
                     valueptr += 4;
                 } 
-                return (json!(values), true);
+                return Ok((json!(values), true));
             },
             FMT::DOUBLE => {
                 let mut values = Vec::new();        
                 for _i in 0..components {
-                    values.push(self.read_f64(valueptr));
+                    values.push(self.read_f64(valueptr)?);
                     valueptr += 8;
                 } 
-                return (json!(values), true);
+                return Ok((json!(values), true));
             },
-            _ => return (json!(null),false),
+            _ => return Ok((json!(null),false)),
         }
     }
 
@@ -1225,4 +1321,104 @@ JMESSAGE(JWRN_EXIF_9, "Incorrect exif header")
 JMESSAGE(JWRN_EXIF_10, "Corrupt exif header: Invalid Exif alignment marker")
 JMESSAGE(JWRN_EXIF_11, "Corrupt exif header: Invalid Exif start (1)")
 JMESSAGE(JWRN_EXIF_12, "Corrupt exif header: Suspicious offset of first IFD value")
-*/
\ No newline at end of file
+*/
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Ugyanez a könyvtár a fuzz célpont kiinduló korpusza is
+    const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/fuzz/corpus/exif_open");
+
+    fn open(data: &[u8]) -> Result<ExifBlock, ExifError> {
+        ExifBlock::default().open(data, data.len())
+    }
+
+    fn corpus(name: &str) -> Vec<u8> {
+        std::fs::read(format!("{}/{}", CORPUS, name)).unwrap()
+    }
+
+    #[test]
+    fn corpus_results() {
+        let expected: [(&str, Option<ExifError>); 14] = [
+            ("valid_ii.bin", None),
+            ("valid_mm.bin", None),
+            ("utf8_format.bin", None),
+            ("makernote_garbage.bin", None),
+            ("empty.bin", Some(ExifError::NoHeader)),
+            ("truncated_header.bin", Some(ExifError::Truncated(8))),
+            ("truncated_ifd.bin", Some(ExifError::IllegalDirectorySize(14))),
+            ("truncated_value.bin", Some(ExifError::IllegalValuePointer { tag: 0x010F })),
+            ("cyclic_next_ifd.bin", Some(ExifError::CyclicDirectory(14))),
+            ("cyclic_exif_pointer.bin", Some(ExifError::CyclicDirectory(14))),
+            ("oversized_count.bin", Some(ExifError::IllegalValuePointer { tag: 0x0110 })),
+            ("oversized_gps_count.bin", Some(ExifError::BadComponentCount { count: 0x10000, tag: 0x0001 })),
+            ("bad_format.bin", Some(ExifError::IllegalFormat { format: 0xFFFF, tag: 0x0112 })),
+            ("deep_nesting.bin", Some(ExifError::NestingExceeded)),
+        ];
+        for (name, error) in expected {
+            assert_eq!(open(&corpus(name)).err(), error, "{}", name);
+        }
+    }
+
+    #[test]
+    fn valid_fields() {
+        for name in ["valid_ii.bin", "valid_mm.bin"] {
+            let exif = open(&corpus(name)).unwrap();
+            assert_eq!(exif.make, "Canon", "{}", name);
+            assert_eq!(exif.get_num_field("Orientation"), Some(6.0), "{}", name);
+            assert_eq!(exif.get_num_field("ExposureTime"), Some(1.0 / 250.0), "{}", name);
+            assert_eq!(exif.get_num_field("GPSLatitude"), Some(47.5), "{}", name);
+        }
+    }
+
+    // Minden csonkítás és minden egyes bájt elrontása: hiba lehet, pánik nem
+    #[test]
+    fn no_panic_on_damage() {
+        for name in ["valid_ii.bin", "valid_mm.bin", "makernote_garbage.bin"] {
+            let data = corpus(name);
+            for len in 0..data.len() {
+                let _ = ExifBlock::default().open(&data, len);
+            }
+            for pos in 0..data.len() {
+                for value in [0x00, 0x01, 0x7F, 0x80, 0xFF] {
+                    let mut damaged = data.clone();
+                    damaged[pos] = value;
+                    let _ = open(&damaged);
+                }
+            }
+        }
+    }
+
+    // IFD0 egyetlen XResolution tag-gel, next mező nélkül: az entry a blokk legvégén van, az adat (ha van) a 22. bájttól
+    fn resolution_block(format: u16, count: u32, value: [u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut block = b"Exif\0\0II*\0\x08\0\0\0\x01\0".to_vec();
+        block.extend_from_slice(&0x011Au16.to_le_bytes());
+        block.extend_from_slice(&format.to_le_bytes());
+        block.extend_from_slice(&count.to_le_bytes());
+        block.extend_from_slice(&value);
+        block.extend_from_slice(data);
+        block
+    }
+
+    #[test]
+    fn patch_checks_format() {
+        // SHORT, az entry-ben: a 8 bájtos RATIONAL írás túlfutna a blokk végén
+        let mut exif = open(&resolution_block(3, 1, [72, 0, 0, 0], &[])).unwrap();
+        let before = exif.raw_exif.clone();
+        exif.patch_exifdata(300.0, 300.0, 100, 100);
+        assert_eq!(exif.raw_exif, before);
+
+        let mut data = 72u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&1u32.to_le_bytes());
+        let mut exif = open(&resolution_block(5, 1, 22u32.to_le_bytes(), &data)).unwrap();
+        exif.patch_exifdata(300.0, 300.0, 100, 100);
+        let reopened = open(&exif.raw_exif.clone()).unwrap();
+        assert_eq!(reopened.get_num_field("XResolution"), Some(300.0));
+    }
+
+    #[test]
+    fn length_larger_than_data() {
+        let data = corpus("valid_ii.bin");
+        assert!(ExifBlock::default().open(&data, data.len() + 100).is_ok());
+    }
+}
@@ -14,7 +14,9 @@ use crate::exif_my::*;
 use crate::colors::*;
 use crate::image_processing::*;
 use crate::ImageViewer;
-use crate::gpu_colors;                             
+use crate::gpu_colors;
+use crate::icc::IccSave;
+use crate::exif_edit::MetadataMode;                             
 
 #[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Default)]
 pub enum SortDir {
@@ -32,6 +34,38 @@ pub enum SaveFormat {
     Png,
     Bmp,
     Tif,
+    Ico,
+    Pnm,
+    Tga,
+    Qoi,
+    Dds, // csak olvasás
+    Svg, // csak olvasás, raszterizálva menthető
+    Pdf, // csak olvasás, oldalanként raszterizálva menthető
+}
+
+pub const SUPPORTED_EXTENSIONS: [&str; 21] = ["bmp", "jpg", "jpeg", "png", "tif", "tiff", "gif", "webp", "ico", "cur",
+    "ppm", "pgm", "pbm", "pam", "pnm", "tga", "qoi", "dds", "svg", "svgz", "pdf"];
+
+// kisbetűs kiterjesztésből
+pub fn format_from_extension(ext: &str) -> SaveFormat {
+    match ext {
+        "jpg" => SaveFormat::Jpeg,
+        "jpeg" => SaveFormat::Jpeg,
+        "webp" => SaveFormat::Webp,
+        "png" => SaveFormat::Png,
+        "tiff" => SaveFormat::Tif,
+        "tif" => SaveFormat::Tif,
+        "gif" => SaveFormat::Gif,
+        "ico" => SaveFormat::Ico,
+        "cur" => SaveFormat::Ico,
+        "ppm" | "pgm" | "pbm" | "pam" | "pnm" => SaveFormat::Pnm,
+        "tga" => SaveFormat::Tga,
+        "qoi" => SaveFormat::Qoi,
+        "dds" => SaveFormat::Dds,
+        "svg" | "svgz" => SaveFormat::Svg,
+        "pdf" => SaveFormat::Pdf,
+        _ => SaveFormat::Bmp,
+    }
 }
 
 pub struct SaveSettings {
@@ -40,9 +74,12 @@ pub struct SaveSettings {
     pub quality: u8,    // JPEG és WebP (1-100)
     pub lossless: bool, // WebP
     pub can_include_exif: bool,
-    pub include_exif: bool,
+    pub metadata: MetadataMode,
     pub save_all_frames: bool,
     pub is_animation: bool,
+    pub can_keep_profile: bool, // nem sRGB profil, és a formátum be tudja ágyazni
+    pub icc_mode: IccSave,
+    pub pdf_dpi: f32, // PDF oldal exportálási felbontása
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -64,6 +101,11 @@ pub struct AppSettings {
     pub show_rgb_histogram: bool,
     pub use_log_scale: bool,
     pub recent_files: Vec<PathBuf>,
+    pub color_management: bool,
+    pub display_profile: Option<PathBuf>,
+    pub metadata_mode: MetadataMode, // a mentési párbeszédben utoljára választott mód
+    pub map_tile_dir: Option<PathBuf>, // offline térkép csempék saját könyvtára
+    pub map_url: String, // a böngészős térkép URL sablonja, {lat} és {lon} helyettesítéssel
 }
 
 impl Default for AppSettings {
@@ -85,6 +127,11 @@ impl Default for AppSettings {
             show_rgb_histogram: true,
             use_log_scale: false,
             recent_files: Vec::new(),
+            metadata_mode: MetadataMode::KeepAll,
+            color_management: true,
+            display_profile: None,
+            map_tile_dir: None,
+            map_url: crate::map::MAP_PROVIDERS[0].1.to_string(),
         }
     }
 }
@@ -114,7 +161,7 @@ pub fn load_icon() -> egui::IconData {
     }
 }
 
-fn apply_modifies_to_frame(img: &mut image::DynamicImage, color_settings: &ColorSettings, magnify: f32, lut: &Option<Lut4ColorSettings>, gpu_interface: &Option<gpu_colors::GpuInterface> ) {
+pub fn apply_modifies_to_frame(img: &mut image::DynamicImage, color_settings: &ColorSettings, magnify: f32, lut: &Option<Lut4ColorSettings>, gpu_interface: &Option<gpu_colors::GpuInterface> ) {
     let new_width = (img.width() as f32 * magnify).round() as u32;
     let new_height = (img.height() as f32 * magnify).round() as u32;
     let mut processed_img = if (magnify - 1.0).abs() > 0.001 {
@@ -143,6 +190,8 @@ fn apply_modifies_to_frame(img: &mut image::DynamicImage, color_settings: &Color
             }
         }
     }
+    crate::magic_wand::apply_flood_mask(&mut rgba_image, color_settings, false);
+    crate::alpha::apply_alpha_edits(&mut rgba_image, color_settings);
     *img = image::DynamicImage::ImageRgba8(rgba_image);
 }
 
@@ -172,6 +221,19 @@ impl ImageViewer {
                 let decoder = image::codecs::webp::WebPDecoder::new(reader).unwrap();
                 decoder.into_frames().collect_frames()
             }
+            SaveFormat::Png => {
+                // APNG: a dispose és blend műveleteket a dekóder elvégzi, teljes képkockákat kapunk
+                let Ok(decoder) = image::codecs::png::PngDecoder::new(reader) else {
+                    return;
+                };
+                if !decoder.is_apng().unwrap_or(false) {
+                    return;
+                }
+                match decoder.apng() {
+                    Ok(apng) => apng.into_frames().collect_frames(),
+                    Err(_) => return,
+                }
+            }
             _ => return,
         };
 
@@ -196,8 +258,10 @@ impl ImageViewer {
                 if total > 1 {
                     self.total_frames = total;
                     self.anim_data = Some(AnimatedImage {
-                        anim_frames: images,
+                        anim_frames: images.into_iter().map(Some).collect(),
                         delays,
+                        is_pages: false,
+                        source: None,
                     });
                     self.last_frame_time = std::time::Instant::now();
                 }
@@ -207,7 +271,7 @@ impl ImageViewer {
 
     pub fn save_settings(&mut self) {
         let path = get_settings_path();
-        self.config.color_settings = self.color_settings;
+        self.config.color_settings = self.color_settings.clone();
         self.config.sort_dir = self.sort;
         self.config.last_image = self.image_full_path.clone();
         self.config.magnify = self.magnify;
@@ -221,6 +285,7 @@ impl ImageViewer {
         self.config.anim_autostart = self.anim_autostart;
         self.config.show_rgb_histogram = self.show_rgb_histogram;
         self.config.use_log_scale = self.use_log_scale;
+        self.config.color_management = self.color_management;
         if let Ok(json) = serde_json::to_string_pretty(&self.config) {
             let _ = std::fs::write(&path, json);
         }
@@ -242,6 +307,12 @@ impl ImageViewer {
                 self.bg_style = settings.bg_style;
                 self.config.recent_files = settings.recent_files;
                 self.recent_file_modified = true;
+                self.color_management = settings.color_management;
+                self.config.display_profile = settings.display_profile;
+                self.config.metadata_mode = settings.metadata_mode;
+                self.config.map_tile_dir = settings.map_tile_dir;
+                self.config.map_url = settings.map_url;
+                self.load_display_profile();
             }
         }
     }
@@ -251,6 +322,7 @@ impl ImageViewer {
             if !self.save_original {
                 self.image_modifies(&mut img);
             }
+            self.to_srgb(&mut img);
             let rgba = img.to_rgba8();
             let (w, h) = rgba.dimensions();
             let image_data = arboard::ImageData {
@@ -278,6 +350,7 @@ impl ImageViewer {
             if !self.save_original {
                 self.image_modifies(&mut img);
             }
+            self.to_srgb(&mut img);
             let rgba = img.to_rgba8().clone();
             if let Some(temp_path) = save_clipboard_image() {
                 self.image_full_path = Some(temp_path); // nem állunk rá a tmp könyvtárra
@@ -296,7 +369,18 @@ impl ImageViewer {
     }
 
     pub fn image_modifies(&self, img: &mut image::DynamicImage) {
-        apply_modifies_to_frame( img, &self.color_settings, self.magnify, &self.lut, &self.gpu_interface);
+        // vektoros kép: nagyítás helyett a kért méretben raszterizálunk
+        let rendered = match (&self.svg, &self.pdf) {
+            (Some(svg), _) => svg.render(self.magnify, 16384),
+            (None, Some(pdf)) => pdf.render(self.current_frame, self.magnify, 16384),
+            _ => None,
+        };
+        if let Some((rendered, _)) = rendered {
+            *img = rendered;
+            apply_modifies_to_frame( img, &self.color_settings, 1.0, &self.lut, &self.gpu_interface);
+        } else {
+            apply_modifies_to_frame( img, &self.color_settings, self.magnify, &self.lut, &self.gpu_interface);
+        }
     }
 
     pub fn make_image_list(&mut self) {
@@ -312,7 +396,6 @@ impl ImageViewer {
         if folder_canonicalized != self.image_folder {
             // Új image_folder mentése
             self.image_folder = folder_canonicalized.clone();
-            let supported_extensions = ["bmp", "jpg", "jpeg", "png", "tif", "gif", "webp"];
             // Lista ürítése és újratöltése
             self.list_of_images.clear();
             if let Some(p) = &self.image_folder {
@@ -322,7 +405,7 @@ impl ImageViewer {
 
                         if full_path.is_file() {
                             if let Some(ext) = full_path.extension().and_then(|s| s.to_str()) {
-                                if supported_extensions.contains(&ext.to_lowercase().as_str()) {
+                                if SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str()) {
                                     self.list_of_images.push(entry);
                                 }
                             }
@@ -401,6 +484,10 @@ impl ImageViewer {
                 .add_filter("Gif", &["gif"])
                 .add_filter("Webp", &["webp"])
                 .add_filter("Windows bitmap", &["bmp"])
+                .add_filter("Icon", &["ico"])
+                .add_filter("Portable anymap", &["ppm", "pgm", "pbm", "pam", "pnm"])
+                .add_filter("Targa", &["tga"])
+                .add_filter("QOI", &["qoi"])
                 .set_file_name(&default_save_name); // Alapértelmezett név
 
             if let Some(path) = def {
@@ -409,37 +496,51 @@ impl ImageViewer {
                 }
             }
 
-            if let Some(ut) = dialog.save_file() {
+            if let Some(mut ut) = dialog.save_file() {
                 let ext = ut
                     .extension()
                     .and_then(|s| s.to_str())
                     .unwrap_or("")
                     .to_lowercase();
                 let saveformat = match ext.as_str() {
-                    "jpg" => SaveFormat::Jpeg,
+                    "jpg" | "jpeg" => SaveFormat::Jpeg,
                     "webp" => SaveFormat::Webp,
                     "png" => SaveFormat::Png,
-                    "tif" => SaveFormat::Tif,
+                    "tif" | "tiff" => SaveFormat::Tif,
                     "gif" => SaveFormat::Gif,
                     "bmp" => SaveFormat::Bmp,
-                    &_ => SaveFormat::Png,
+                    "ico" => SaveFormat::Ico,
+                    "ppm" | "pgm" | "pbm" | "pam" | "pnm" => SaveFormat::Pnm,
+                    "tga" => SaveFormat::Tga,
+                    "qoi" => SaveFormat::Qoi,
+                    // ismeretlen vagy csak olvasható (dds, svg, pdf) kiterjesztés: Png-ként, .png néven mentjük
+                    &_ => {
+                        ut.set_extension("png");
+                        SaveFormat::Png
+                    }
                 };
                 let inex = self.exif.is_some();
                 let can = ( saveformat == SaveFormat::Jpeg || saveformat == SaveFormat::Webp
-                    || saveformat == SaveFormat::Bmp ) && inex;
-                 let anim = self.anim_data.is_some() && (saveformat == SaveFormat::Gif || saveformat == SaveFormat::Webp);
+                    || saveformat == SaveFormat::Bmp || saveformat == SaveFormat::Png ) && inex;
+                 let anim = self.anim_data.is_some() && (saveformat == SaveFormat::Gif || saveformat == SaveFormat::Webp
+                    || saveformat == SaveFormat::Png || saveformat == SaveFormat::Tif || saveformat == SaveFormat::Ico);
                                                                                                                         
+                let keep = self.icc_profile.as_ref().is_some_and(|p| !p.is_srgb())
+                    && crate::icc::can_embed_icc(saveformat);
                 let dial_need = saveformat == SaveFormat::Jpeg || saveformat == SaveFormat::Webp ||
-                    (saveformat == SaveFormat::Bmp && inex) || anim;
+                    can || anim || keep || self.pdf.is_some();
                 self.save_dialog = Some(SaveSettings {
                     full_path: ut,
                     saveformat,
                     quality: 85, // Alapértelmezett JPEG minőség
                     lossless: false,
                     can_include_exif: can,
-                    include_exif: inex,
+                    metadata: self.config.metadata_mode,
                     save_all_frames: false,
                     is_animation: anim,
+                    can_keep_profile: keep,
+                    icc_mode: if keep { IccSave::KeepProfile } else { IccSave::ConvertToSrgb },
+                    pdf_dpi: 150.0,
                 });
                 if !dial_need {
                     self.completing_save();
@@ -451,9 +552,20 @@ impl ImageViewer {
     pub fn completing_save(&mut self) {
         if let Some(save_data) = self.save_dialog.take() {
             self.add_to_recent(&save_data.full_path);
+            if save_data.save_all_frames {
+                self.load_all_pages();
+            }
             if let Some(mut img) = self.original_image.clone() {
                 let mut resolution = self.resolution.clone();
-                if !self.save_original {
+                if let Some(page) = self.render_pdf_page(self.current_frame, save_data.pdf_dpi) {
+                    // PDF: a választott felbontással rajzoljuk, és ez kerül a metaadatba is
+                    img = page;
+                    resolution = Some(Resolution { xres: save_data.pdf_dpi, yres: save_data.pdf_dpi, dpi: true });
+                    if !self.save_original {
+                        apply_modifies_to_frame(&mut img, &self.color_settings, 1.0, &self.lut, &self.gpu_interface);
+                    }
+                }
+                else if !self.save_original {
                     if let Some(mut resol) = resolution.clone() {
                         resol.xres *= self.magnify;
                         resol.yres *= self.magnify;
@@ -461,8 +573,23 @@ impl ImageViewer {
                     }                    
                     self.image_modifies(&mut img);
                 }
+                // profil megtartása, vagy a pixelek sRGB-be alakítása
+                // a StripAll csak ott számít, ahol a párbeszéd fel is ajánlotta (a metadata a legutóbbi választás)
+                let strip_all = save_data.can_include_exif && save_data.metadata == MetadataMode::StripAll;
+                let keep_profile = save_data.can_keep_profile && save_data.icc_mode == IccSave::KeepProfile && !strip_all;
+                if save_data.can_include_exif {
+                    self.config.metadata_mode = save_data.metadata;
+                }
+                let save_exif = self.exif_for_save(save_data.metadata);
+                let srgb_transform = if keep_profile { None } else {
+                    self.icc_profile.as_ref().filter(|p| !p.is_srgb()).and_then(|p| p.transform_to_srgb())
+                };
+                if let Some(transform) = &srgb_transform {
+                    crate::icc::convert_dynamic_image(transform.as_ref(), &mut img);
+                }
                 match save_data.saveformat {
                     SaveFormat::Jpeg => {
+                        crate::alpha::flatten_for_save(&mut img, self.color_settings.matte_color);
                         let mut buffer = Vec::new();
                         let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut buffer, save_data.quality);
                         img.write_with_encoder(encoder).expect("JPEG kódolási hiba");
@@ -492,7 +619,7 @@ impl ImageViewer {
                                     jpeg.segments_mut().insert(0, new_seg);
                                 }
                             }
-                            if let (true, Some(mut exif)) = (save_data.include_exif, self.exif.clone()) {
+                            if let Some(mut exif) = save_exif.clone() {
                                 let rot = exif.get_num_field("Orientation").unwrap_or(1.0);
                                 if !self.save_original || rot != 1.0 {
                                     if let Some(res) = resolution.clone() {
@@ -523,11 +650,15 @@ impl ImageViewer {
                                     
                                 let processed_images: Vec<image::DynamicImage> = anim.anim_frames
                                     .par_iter()
+                                    .flatten()
                                     .map(|frame| {
                                         let mut f = frame.clone();
                                         if !save_original {
                                             apply_modifies_to_frame( &mut f, &settings, magnify, &lut, &self.gpu_interface);
                                         }
+                                        if let Some(transform) = &srgb_transform {
+                                            crate::icc::convert_dynamic_image(transform.as_ref(), &mut f);
+                                        }
                                         f
                                     })
                                     .collect();
@@ -557,9 +688,9 @@ impl ImageViewer {
                                 let final_webp_data = encoder.finalize(timestamp)
                                     .expect("Hiba az animáció lezárásakor");
                                 let mut output_data = final_webp_data.to_vec();
-                                if save_data.include_exif && self.exif.is_some() {
+                                if save_exif.is_some() {
                                     if let Ok(mut webp_structure) = img_parts::webp::WebP::from_bytes(img_parts::Bytes::copy_from_slice(&output_data)) {
-                                         if let Some(exif_obj) = &self.exif {
+                                         if let Some(exif_obj) = &save_exif {
                                              webp_structure.set_exif(Some(img_parts::Bytes::from(exif_obj.raw_exif.clone())));
                                              let mut buf = Vec::new();
                                              webp_structure.encoder().write_to(&mut buf).ok();
@@ -580,7 +711,7 @@ impl ImageViewer {
                             };
                             let mut webp = img_parts::webp::WebP::from_bytes(img_parts::Bytes::copy_from_slice(&*memory))
                                 .expect("Hiba a WebP struktúra feldolgozásakor");
-                            if let (true, Some(mut exif)) = (save_data.include_exif, self.exif.clone()) {
+                            if let Some(mut exif) = save_exif.clone() {
                                 let rot = exif.get_num_field("Orientation").unwrap_or(1.0);
                                 if !self.save_original || rot != 1.0 {
                                     if let Some(res) = resolution.clone() {
@@ -597,6 +728,18 @@ impl ImageViewer {
                             }
                         }
                     }
+                    SaveFormat::Tif if save_data.save_all_frames && self.anim_data.is_some() => {
+                        let (x, y, unit) = if let Some(res) = resolution {
+                            ((res.xres * 1000.0) as u32, (res.yres * 1000.0) as u32, if res.dpi { 2u16 } else { 3u16 })
+                        } else {
+                            (72000, 72000, 2u16)
+                        };
+                        let pages = self.processed_frames(&srgb_transform, save_data.pdf_dpi);
+                        let icc = self.icc_profile.as_ref().filter(|_| keep_profile).map(|icc| icc.data.as_slice());
+                        if let Err(e) = crate::pages::save_tiff_pages(&save_data.full_path, &pages, (x, y, unit), icc) {
+                            println!("TIFF írási hiba: {}", e);
+                        }
+                    }
                     SaveFormat::Tif => {
                         let file = std::fs::File::create(&save_data.full_path).unwrap();
                         let rgb_data = img.to_rgba8(); 
@@ -615,9 +758,19 @@ impl ImageViewer {
                         col.encoder().write_tag(tiff::tags::Tag::ResolutionUnit, unit).unwrap();
                         col.encoder().write_tag(tiff::tags::Tag::Software, "IView 2026").unwrap();
                         col.encoder().write_tag(tiff::tags::Tag::DateTime, chrono::Local::now().format("%Y:%m:%d %H:%M:%S").to_string().as_str()).unwrap();
+                        if let (true, Some(icc)) = (keep_profile, &self.icc_profile) {
+                            col.encoder().write_tag(tiff::tags::Tag::IccProfile, icc.data.as_slice()).unwrap();
+                        }
 
                         col.write_data(rgb_data.as_raw()).expect("TIFF írási hiba");
                     }
+                    SaveFormat::Png if save_data.save_all_frames && self.anim_data.is_some() => {
+                        let frames = self.processed_frames(&srgb_transform, save_data.pdf_dpi);
+                        let delays = self.anim_data.as_ref().map(|a| a.delays.clone()).unwrap_or_default();
+                        if let Err(e) = crate::pages::save_apng(&save_data.full_path, &frames, &delays) {
+                            println!("APNG írási hiba: {}", e);
+                        }
+                    }
                     SaveFormat::Png => {
                         let mut buffer = Vec::new();
                         {
@@ -642,7 +795,7 @@ impl ImageViewer {
                             writer.write_image_data(img.as_bytes()).expect("PNG adatírási hiba");
                         }
 
-                        if let (true, Some(exif)) = (save_data.include_exif, self.exif.clone()) {
+                        if let Some(exif) = save_exif.clone() {
                             let clean_exif = exif.raw_exif[6..].to_vec();
                             let mut png_parts = img_parts::png::Png::from_bytes(buffer.into()).unwrap();
                             let exif_chunk = img_parts::png::PngChunk::new(*b"eXIf", img_parts::Bytes::copy_from_slice(&clean_exif));
@@ -655,6 +808,58 @@ impl ImageViewer {
                         }
                     }
                     
+                    SaveFormat::Ico => {
+                        let pages = if save_data.save_all_frames && self.anim_data.is_some() {
+                            self.processed_frames(&srgb_transform, save_data.pdf_dpi)
+                        } else {
+                            vec![img.clone()]
+                        };
+                        if let Err(e) = crate::pages::save_ico_pages(&save_data.full_path, &pages) {
+                            println!("ICO írási hiba: {}", e);
+                        }
+                    }
+                    SaveFormat::Pnm => {
+                        use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
+                        use image::ImageEncoder;
+                        let ext = save_data.full_path.extension().and_then(|s| s.to_str()).unwrap_or("").to_lowercase();
+                        if ext != "pam" {
+                            crate::alpha::flatten_for_save(&mut img, self.color_settings.matte_color);
+                        }
+                        let (w, h) = (img.width(), img.height());
+                        let result = std::fs::File::create(&save_data.full_path)
+                            .map_err(image::ImageError::IoError)
+                            .and_then(|file| {
+                                let writer = std::io::BufWriter::new(file);
+                                match ext.as_str() {
+                                    "pbm" => {
+                                        // a PnmEncoder a 0 mintát írja fekete bitként
+                                        let bits: Vec<u8> = img.to_luma8().pixels().map(|p| (p[0] >= 128) as u8).collect();
+                                        PnmEncoder::new(writer).with_subtype(PnmSubtype::Bitmap(SampleEncoding::Binary))
+                                            .write_image(&bits, w, h, image::ExtendedColorType::L8)
+                                    }
+                                    "pgm" => PnmEncoder::new(writer).with_subtype(PnmSubtype::Graymap(SampleEncoding::Binary))
+                                        .write_image(img.to_luma8().as_raw(), w, h, image::ExtendedColorType::L8),
+                                    "ppm" | "pnm" => PnmEncoder::new(writer).with_subtype(PnmSubtype::Pixmap(SampleEncoding::Binary))
+                                        .write_image(img.to_rgb8().as_raw(), w, h, image::ExtendedColorType::Rgb8),
+                                    _ => PnmEncoder::new(writer) // PAM, alfa csatornával
+                                        .write_image(img.to_rgba8().as_raw(), w, h, image::ExtendedColorType::Rgba8),
+                                }
+                            });
+                        if let Err(e) = result {
+                            println!("Hiba a mentéskor ({:?}): {}", save_data.saveformat, e);
+                        }
+                    }
+                    SaveFormat::Tga | SaveFormat::Qoi => {
+                        let format = if save_data.saveformat == SaveFormat::Tga { image::ImageFormat::Tga } else { image::ImageFormat::Qoi };
+                        let rgba = image::DynamicImage::ImageRgba8(img.to_rgba8());
+                        if let Err(e) = rgba.save_with_format(&save_data.full_path, format) {
+                            println!("Hiba a mentéskor ({:?}): {}", save_data.saveformat, e);
+                        }
+                    }
+                    // a mentés párbeszéd ezeket .png-re írja át, ide nem jutunk
+                    SaveFormat::Dds | SaveFormat::Svg | SaveFormat::Pdf => {
+                        println!("{:?} mentés nem támogatott", save_data.saveformat);
+                    }
                     SaveFormat::Bmp => {
                         let mut buffer = std::io::Cursor::new(Vec::new());
                         img.write_to(&mut buffer, image::ImageFormat::Bmp)
@@ -673,7 +878,7 @@ impl ImageViewer {
                                 bmp_data[42..46].copy_from_slice(&dpm_y_bytes);
                             }
                         }
-                        if let (true, Some(mut exif)) = (save_data.include_exif, self.exif.clone()) {
+                        if let Some(mut exif) = save_exif.clone() {
                             let rot = exif.get_num_field("Orientation").unwrap_or(1.0);
                             if !self.save_original || rot != 1.0 {
                                 if let Some(res) = resolution.clone() {
@@ -720,11 +925,15 @@ impl ImageViewer {
                                 };
                                 let processed_images: Vec<image::RgbaImage> = anim.anim_frames
                                     .par_iter()
+                                    .flatten()
                                     .map(|frame| {
                                         let mut f = frame.clone();
                                         if !save_original {
                                             apply_modifies_to_frame( &mut f, &settings, magnify, &lut_for_save,&self.gpu_interface);
                                         }
+                                        if let Some(transform) = &srgb_transform {
+                                            crate::icc::convert_dynamic_image(transform.as_ref(), &mut f);
+                                        }
                                         f.to_rgba8()
                                     })
                                     .collect();
@@ -755,7 +964,12 @@ impl ImageViewer {
                         }
                     }
                 }
-                
+                if let (true, Some(icc)) = (keep_profile, &self.icc_profile) {
+                    let result = crate::icc::embed_icc_profile(&save_data.full_path, save_data.saveformat, &icc.data);
+                    if let Err(e) = result {
+                        println!("Hiba az ICC profil beágyazásakor: {}", e);
+                    }
+                }
             }
         }
     }
@@ -767,17 +981,7 @@ impl ImageViewer {
             .and_then(|s| s.to_str())
             .unwrap_or("")
             .to_lowercase();
-        let image_format = match ext.as_str() {
-            "jpg" => SaveFormat::Jpeg,
-            "jpeg" => SaveFormat::Jpeg,
-            "webp" => SaveFormat::Webp,
-            "png" => SaveFormat::Png,
-            "tiff" => SaveFormat::Tif,
-            "tif" => SaveFormat::Tif,
-            "gif" => SaveFormat::Gif,
-            _ => SaveFormat::Bmp,
-        };
-        self.image_format = image_format;
+        self.image_format = format_from_extension(&ext);
         if make_list {
             self.add_to_recent(&path);
             self.make_image_list();
@@ -789,14 +993,22 @@ impl ImageViewer {
         let mut dialog = rfd::FileDialog::new()
             .add_filter(
                 "Images",
-                &["bmp", "jpg", "jpeg", "png", "tif", "tiff", "gif", "webp"],
+                &["bmp", "jpg", "jpeg", "png", "tif", "tiff", "gif", "webp", "ico", "cur",
+                  "ppm", "pgm", "pbm", "pam", "pnm", "tga", "qoi", "dds", "svg", "svgz", "pdf"],
             )
             .add_filter("Png", &["png"])
             .add_filter("Jpeg kép", &["jpg", "jpeg"])
             .add_filter("Webp", &["webp"])
             .add_filter("Tiff", &["tif", "tiff"])
             .add_filter("Gif", &["gif"])
-            .add_filter("Windows bitmap", &["bmp"]);
+            .add_filter("Windows bitmap", &["bmp"])
+            .add_filter("Icon", &["ico", "cur"])
+            .add_filter("Portable anymap", &["ppm", "pgm", "pbm", "pam", "pnm"])
+            .add_filter("Targa", &["tga"])
+            .add_filter("QOI", &["qoi"])
+            .add_filter("DirectDraw Surface", &["dds"])
+            .add_filter("SVG", &["svg", "svgz"])
+            .add_filter("PDF", &["pdf"]);
 
         if let Some(path) = def {
             if path.is_file() {
@@ -823,9 +1035,47 @@ impl ImageViewer {
         };
         self.resolution = None;
         self.anim_playing = false;
+        // a varázspálca pontja képhez tartozik, a "same correction" sem viheti tovább
+        if !reopen {
+            self.color_settings.flood_seed = None;
+            self.color_settings.flood_mask = None;
+        }
         //self.anim_timer.stop();
         ctx.send_viewport_cmd(egui::ViewportCommand::Title(format!("iView")));
-        if let Ok(mut img) = image::open(&filepath) {
+        self.svg = None;
+        self.pdf = None;
+        let opened = if self.image_format == SaveFormat::Pdf {
+            match crate::vector::PdfFile::open(&filepath) {
+                Ok(pdf) => {
+                    let rendered = pdf.render(0, 1.0, 16384).map(|(img, _)| img);
+                    self.pdf = Some(pdf);
+                    rendered
+                }
+                Err(e) => {
+                    println!("PDF hiba: {}", e);
+                    None
+                }
+            }
+        } else if self.image_format == SaveFormat::Svg {
+            match crate::vector::SvgDocument::open(&filepath) {
+                Ok(svg) => {
+                    let rendered = svg.render(1.0, 16384).map(|(img, _)| img);
+                    self.svg = Some(svg);
+                    rendered
+                }
+                Err(e) => {
+                    println!("SVG hiba: {}", e);
+                    None
+                }
+            }
+        } else if self.image_format == SaveFormat::Ico {
+            // a .cur kiterjesztést az image crate nem ismeri fel
+            std::fs::read(&filepath).ok()
+                .and_then(|buffer| image::load_from_memory_with_format(&buffer, image::ImageFormat::Ico).ok())
+        } else {
+            image::open(&filepath).ok()
+        };
+        if let Some(mut img) = opened {
             if self.image_format == SaveFormat::Tif {
                 if let Ok(file) = std::fs::File::open(&filepath) {
                     if let Ok(mut decoder) = tiff::decoder::Decoder::new(file) {
@@ -916,9 +1166,15 @@ impl ImageViewer {
             }
 
             self.exif = None;
+            self.exif_editor = None; // a szerkesztő mindig a megnyitott képhez tartozik
+            self.xmp = None;
+            self.icc_profile = None;
             if let Ok(mut f) = std::fs::File::open(&filepath) {
                 let mut buffer = Vec::new();
                 if f.read_to_end(&mut buffer).is_ok() {
+                    self.icc_profile = crate::icc::read_icc_profile(&buffer, self.image_format, &filepath)
+                        .and_then(|data| crate::icc::IccProfile::from_bytes(&data));
+                    self.xmp = crate::xmp::read_metadata(&buffer, self.image_format, &filepath);
                     if self.image_format == SaveFormat::Webp {
                         if let Ok(webp) = img_parts::webp::WebP::from_bytes(buffer.clone().into()) {
                             if let Some(exif_bytes) = webp.exif() {
@@ -1035,6 +1291,15 @@ impl ImageViewer {
                             }
                         }
                     }
+                    else if self.image_format == SaveFormat::Tif {
+                        if let Some(data) = crate::exif_edit::exif_block_from_tiff(&buffer) {
+                            let mut exifblock = ExifBlock::default();
+                            let len = data.len();
+                            if let Ok(result) = exifblock.open( &data, len) {
+                                self.exif = Some(result);
+                            }
+                        }
+                    }
                     else if self.image_format == SaveFormat::Png {
                         if let Ok(png) = img_parts::png::Png::from_bytes(buffer.clone().into()) {
                             if let Some(exif_chunk) = png.chunk_by_type(*b"eXIf") {
@@ -1072,6 +1337,7 @@ impl ImageViewer {
                 }
             }
 
+            self.update_icc_transform();
             self.original_image = Some(img);
             self.resized_image = None;
             self.resize = 1.0;
@@ -1082,8 +1348,9 @@ impl ImageViewer {
             self.current_frame = 0;
             self.is_animated = false;
 
-            // Csak GIF és WebP esetén próbáljuk meg az animációt betölteni
-            if self.image_format == SaveFormat::Gif || self.image_format == SaveFormat::Webp {
+            // Csak GIF, WebP és APNG esetén próbáljuk meg az animációt betölteni
+            if self.image_format == SaveFormat::Gif || self.image_format == SaveFormat::Webp
+                || self.image_format == SaveFormat::Png {
                 // Meghívjuk a segédfüggvényt (lásd lentebb)
                 self.load_animation(&filepath);
                 if self.anim_data.is_some() {
@@ -1092,6 +1359,13 @@ impl ImageViewer {
                     self.last_frame_time = std::time::Instant::now();
                 }
             }
+            // Többoldalas TIFF, ICO/CUR és PDF: lapozás, lejátszás nélkül
+            if matches!(self.image_format, SaveFormat::Tif | SaveFormat::Ico | SaveFormat::Pdf) {
+                self.load_pages(&filepath);
+                if let Some(first) = self.frame_image(0) {
+                    self.original_image = Some(first);
+                }
+            }
 
             if (self.refit_reopen || !reopen) && self.fit_open {
                 self.want_magnify = -1.0;
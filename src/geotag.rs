@@ -0,0 +1,441 @@
+use std::path::PathBuf;
+use chrono::{DateTime, NaiveDateTime};
+use rayon::prelude::*;
+
+use crate::exif_edit::{can_write_exif, exif_block_from_file, write_exif_to_file, ExifTree, IfdKind, TAG_DATETIME_ORIGINAL};
+use crate::exif_my::ExifBlock;
+use crate::file_handlers::{format_from_extension, SaveFormat};
+use crate::ImageViewer;
+
+// Geotag GPX nyomvonalból: a DateTimeOriginal (fényképezőgép órája, helyi idő) UTC-re váltva,
+// a két szomszédos nyomvonal pont között lineárisan interpolálva
+
+const TAG_DATETIME: u16 = 0x0132;
+const TAG_OFFSET_TIME_ORIGINAL: u16 = 0x9011;
+
+pub struct TrackPoint {
+    pub time: f64, // UTC, Unix másodperc
+    pub lat: f64,
+    pub lon: f64,
+    pub ele: Option<f64>,
+}
+
+#[derive(Default)]
+pub struct GpxTrack {
+    pub points: Vec<TrackPoint>,
+}
+
+impl GpxTrack {
+    // trkpt és rtept pontok; idő nélküli pontot nem tudunk párosítani
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let doc = roxmltree::Document::parse(text).map_err(|e| e.to_string())?;
+        let child_text = |node: roxmltree::Node, name: &str| {
+            node.children().find(|c| c.tag_name().name() == name).and_then(|c| c.text()).map(|t| t.trim().to_string())
+        };
+        let mut points: Vec<TrackPoint> = doc.descendants()
+            .filter(|n| matches!(n.tag_name().name(), "trkpt" | "rtept"))
+            .filter_map(|n| {
+                let lat = n.attribute("lat")?.trim().parse::<f64>().ok()?;
+                let lon = n.attribute("lon")?.trim().parse::<f64>().ok()?;
+                let time = DateTime::parse_from_rfc3339(&child_text(n, "time")?).ok()?;
+                let ele = child_text(n, "ele").and_then(|e| e.parse::<f64>().ok());
+                let time = time.timestamp() as f64 + time.timestamp_subsec_millis() as f64 / 1000.0;
+                Some(TrackPoint { time, lat, lon, ele })
+            })
+            .collect();
+        if points.is_empty() {
+            return Err("A GPX fájlban nincs időbélyeges pont".into());
+        }
+        points.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(Self { points })
+    }
+
+    // max_gap: ennél nagyobb szünetet (vagy a nyomvonalon kívüli időt) nem hidalunk át
+    pub fn position_at(&self, time: f64, max_gap: f64) -> Option<(f64, f64, Option<f64>)> {
+        let idx = self.points.partition_point(|p| p.time <= time);
+        let at = |p: &TrackPoint| (p.lat, p.lon, p.ele);
+        let (a, b) = match idx {
+            0 => {
+                let first = self.points.first()?;
+                return (first.time - time <= max_gap).then(|| at(first));
+            }
+            n if n == self.points.len() => {
+                let last = self.points.last()?;
+                return (time - last.time <= max_gap).then(|| at(last));
+            }
+            n => (&self.points[n - 1], &self.points[n]),
+        };
+        let span = b.time - a.time;
+        if span > max_gap {
+            // szakadás a nyomvonalban: csak a közeli pontot fogadjuk el
+            let (da, db) = (time - a.time, b.time - time);
+            return if da <= db && da <= max_gap {
+                Some(at(a))
+            } else if db <= max_gap {
+                Some(at(b))
+            } else {
+                None
+            };
+        }
+        let t = if span > 0.0 { (time - a.time) / span } else { 0.0 };
+        let mut dlon = b.lon - a.lon;
+        if dlon > 180.0 {
+            dlon -= 360.0;
+        } else if dlon < -180.0 {
+            dlon += 360.0;
+        }
+        let mut lon = a.lon + dlon * t;
+        if lon > 180.0 {
+            lon -= 360.0;
+        } else if lon < -180.0 {
+            lon += 360.0;
+        }
+        let ele = match (a.ele, b.ele) {
+            (Some(ea), Some(eb)) => Some(ea + (eb - ea) * t),
+            (ea, eb) => ea.or(eb),
+        };
+        Some((a.lat + (b.lat - a.lat) * t, lon, ele))
+    }
+}
+
+pub struct GeotagImage {
+    pub path: PathBuf,
+    pub name: String,
+    pub format: SaveFormat,
+    pub taken: Option<NaiveDateTime>,
+    pub exif_offset: Option<i64>, // OffsetTimeOriginal másodpercben
+    pub has_gps: bool,
+    pub position: Option<(f64, f64, Option<f64>)>,
+}
+
+impl GeotagImage {
+    fn read(path: PathBuf) -> Self {
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        let format = format_from_extension(&ext);
+        let tree = std::fs::read(&path).ok()
+            .and_then(|buffer| exif_block_from_file(&buffer, format))
+            .and_then(|block| ExifTree::from_exif_block(&block).ok());
+        let taken = tree.as_ref()
+            .and_then(|t| t.get_ascii(IfdKind::Exif, TAG_DATETIME_ORIGINAL).or_else(|| t.get_ascii(IfdKind::Ifd0, TAG_DATETIME)))
+            .and_then(|s| NaiveDateTime::parse_from_str(s.trim(), "%Y:%m:%d %H:%M:%S").ok());
+        let exif_offset = tree.as_ref()
+            .and_then(|t| t.get_ascii(IfdKind::Exif, TAG_OFFSET_TIME_ORIGINAL))
+            .and_then(|s| parse_offset(&s));
+        let has_gps = tree.as_ref().and_then(|t| t.gps_position()).is_some();
+        Self { path, name, format, taken, exif_offset, has_gps, position: None }
+    }
+}
+
+// "+02:00" alakú időzóna eltolás
+fn parse_offset(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let (h, m) = text[1..].split_once(':')?;
+    Some(sign * (h.parse::<i64>().ok()? * 3600 + m.parse::<i64>().ok()? * 60))
+}
+
+pub struct Geotagger {
+    pub gpx_name: String,
+    pub track: GpxTrack,
+    pub images: Vec<GeotagImage>,
+    pub timezone: f32,     // a fényképezőgép órájának időzónája, órában
+    pub clock_offset: i64, // ennyi másodperccel sietett a fényképezőgép órája a GPS-hez képest
+    pub max_gap: f64,
+    pub use_exif_offset: bool,
+    pub overwrite: bool,
+    pub write_altitude: bool,
+    pub message: Option<String>,
+    pub error: Option<String>, // GPX betöltési hiba, az ablakban jelenik meg
+}
+
+impl Geotagger {
+    pub fn new(gpx_name: String, track: GpxTrack, images: Vec<GeotagImage>) -> Self {
+        let local = chrono::Local::now().offset().local_minus_utc() as f32 / 3600.0;
+        let mut geotagger = Self {
+            gpx_name,
+            track,
+            images,
+            timezone: local,
+            clock_offset: 0,
+            max_gap: 300.0,
+            use_exif_offset: true,
+            overwrite: false,
+            write_altitude: true,
+            message: None,
+            error: None,
+        };
+        geotagger.match_positions();
+        geotagger
+    }
+
+    pub fn match_positions(&mut self) {
+        for image in &mut self.images {
+            image.position = image.taken.and_then(|taken| {
+                let offset = match image.exif_offset {
+                    Some(offset) if self.use_exif_offset => offset,
+                    _ => (self.timezone as f64 * 3600.0).round() as i64,
+                };
+                let utc = taken.and_utc().timestamp() - offset - self.clock_offset;
+                self.track.position_at(utc as f64, self.max_gap)
+            });
+        }
+    }
+
+    fn will_write(&self, image: &GeotagImage) -> bool {
+        image.position.is_some() && (self.overwrite || !image.has_gps) && can_write_exif(image.format)
+    }
+
+    pub fn status(&self, image: &GeotagImage) -> String {
+        match (image.taken, image.position) {
+            (None, _) => "no date".into(),
+            (Some(_), None) => "outside track".into(),
+            (Some(_), Some(_)) if !can_write_exif(image.format) => "format not writable".into(),
+            (Some(_), Some(_)) if image.has_gps && !self.overwrite => "has GPS, skipped".into(),
+            (Some(_), Some((lat, lon, _))) => format!("{:.6}, {:.6}", lat, lon),
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.images.iter().filter(|i| self.will_write(i)).count()
+    }
+}
+
+impl ImageViewer {
+    pub fn open_geotag(&mut self) {
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("iView 🛰 Geotag from GPX")
+            .add_filter("GPX", &["gpx"]);
+        if let Some(folder) = &self.image_folder {
+            dialog = dialog.set_directory(folder);
+        }
+        let Some(gpx_path) = dialog.pick_file() else {
+            return;
+        };
+        let gpx_name = gpx_path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        let track = match std::fs::read_to_string(&gpx_path).map_err(|e| e.to_string()).and_then(|t| GpxTrack::parse(&t)) {
+            Ok(track) => track,
+            Err(e) => {
+                println!("GPX betöltési hiba: {}", e);
+                // a már megnyitott nyomvonal marad, különben üres ablakban jelezzük a hibát
+                let geotagger = self.geotagger.get_or_insert_with(|| Geotagger::new(String::new(), GpxTrack::default(), Vec::new()));
+                geotagger.error = Some(format!("Cannot load {}: {}", gpx_name, e));
+                return;
+            }
+        };
+        self.make_image_list();
+        let paths: Vec<PathBuf> = self.list_of_images.iter().map(|e| e.path()).collect();
+        let images: Vec<GeotagImage> = paths.into_par_iter().map(GeotagImage::read).collect();
+        self.geotagger = Some(Geotagger::new(gpx_name, track, images));
+    }
+
+    // Csak a GPS IFD változik, a pixelekhez nem nyúlunk
+    fn write_geotags(&mut self) {
+        let Some(geotagger) = &mut self.geotagger else {
+            return;
+        };
+        let (mut written, mut failed) = (0, 0);
+        for i in 0..geotagger.images.len() {
+            if !geotagger.will_write(&geotagger.images[i]) {
+                continue;
+            }
+            let image = &geotagger.images[i];
+            let Some((lat, lon, alt)) = image.position else {
+                continue;
+            };
+            let alt = alt.filter(|_| geotagger.write_altitude);
+            let result = std::fs::read(&image.path).map_err(|e| e.to_string()).and_then(|buffer| {
+                let mut tree = match exif_block_from_file(&buffer, image.format) {
+                    Some(block) => ExifTree::from_exif_block(&block)?,
+                    None => ExifTree::default(),
+                };
+                tree.set_gps_position(lat, lon, alt);
+                let block = tree.to_exif_block();
+                if image.format != SaveFormat::Tif {
+                    tree.check_makernote(&block)?;
+                }
+                write_exif_to_file(&image.path, image.format, &block)?;
+                Ok(tree)
+            });
+            match result {
+                Ok(tree) => {
+                    written += 1;
+                    if self.image_full_path.as_ref() == Some(&image.path) {
+                        self.exif = ExifBlock::from_tree(&tree).ok();
+                        self.file_meta = std::fs::metadata(&image.path).ok();
+                    }
+                    geotagger.images[i].has_gps = true;
+                }
+                Err(e) => {
+                    failed += 1;
+                    println!("Geotag hiba {:?}: {}", image.path, e);
+                }
+            }
+        }
+        geotagger.message = Some(if failed == 0 {
+            format!("{} images geotagged", written)
+        } else {
+            format!("{} images geotagged, {} failed", written, failed)
+        });
+        if let Some(map) = &mut self.map_view {
+            map.pins_folder = None; // a mappa térkép újraolvassa a helyeket
+        }
+    }
+
+    pub fn draw_geotag(&mut self, ctx: &egui::Context) {
+        let Some(geotagger) = &mut self.geotagger else {
+            return;
+        };
+        let mut open = true;
+        let mut write = false;
+        let mut reload = false;
+        egui::Window::new("iView 🛰 Geotag from GPX")
+            .open(&mut open)
+            .show(ctx, |ui| {
+                if let Some(error) = &geotagger.error {
+                    ui.colored_label(egui::Color32::RED, error);
+                }
+                if geotagger.track.points.is_empty() {
+                    if ui.button("Other GPX ...").clicked() {
+                        reload = true;
+                    }
+                    return;
+                }
+                let first = geotagger.track.points.first().map(|p| p.time as i64).unwrap_or(0);
+                let last = geotagger.track.points.last().map(|p| p.time as i64).unwrap_or(0);
+                let fmt = |t: i64| DateTime::from_timestamp(t, 0).map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default();
+                ui.label(format!("{}: {} points, {} – {} UTC", geotagger.gpx_name, geotagger.track.points.len(), fmt(first), fmt(last)));
+                ui.separator();
+                let mut changed = false;
+                egui::Grid::new("geotag_grid")
+                    .num_columns(2)
+                    .spacing([20.0, 4.0])
+                    .show(ui, |ui| {
+                        ui.label("Camera time zone:");
+                        changed |= ui.add(egui::DragValue::new(&mut geotagger.timezone).speed(0.25).range(-12.0..=14.0).suffix(" h")).changed();
+                        ui.end_row();
+                        ui.label("Camera clock ahead:");
+                        changed |= ui.add(egui::DragValue::new(&mut geotagger.clock_offset).speed(1.0).suffix(" s")).changed();
+                        ui.end_row();
+                        ui.label("Max gap:");
+                        changed |= ui.add(egui::DragValue::new(&mut geotagger.max_gap).speed(10.0).range(0.0..=86400.0).suffix(" s")).changed();
+                        ui.end_row();
+                    });
+                changed |= ui.checkbox(&mut geotagger.use_exif_offset, "Use the time zone recorded by the camera").changed();
+                changed |= ui.checkbox(&mut geotagger.overwrite, "Overwrite existing GPS").changed();
+                ui.checkbox(&mut geotagger.write_altitude, "Write altitude");
+                if changed {
+                    geotagger.match_positions();
+                    geotagger.message = None;
+                }
+                ui.separator();
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    egui::Grid::new("geotag_images_grid")
+                        .num_columns(3)
+                        .striped(true)
+                        .spacing([12.0, 2.0])
+                        .show(ui, |ui| {
+                            for image in &geotagger.images {
+                                ui.label(&image.name);
+                                ui.label(image.taken.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default());
+                                ui.label(geotagger.status(image));
+                                ui.end_row();
+                            }
+                        });
+                });
+                ui.separator();
+                let count = geotagger.count();
+                ui.horizontal(|ui| {
+                    if ui.add_enabled(count > 0, egui::Button::new(format!("Write tags ({})", count))).clicked() {
+                        write = true;
+                    }
+                    if ui.button("Other GPX ...").clicked() {
+                        reload = true;
+                    }
+                });
+                if let Some(message) = &geotagger.message {
+                    ui.label(message);
+                }
+            });
+        if write {
+            self.write_geotags();
+        }
+        if reload {
+            self.open_geotag();
+        }
+        if !open {
+            self.geotagger = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(points: &[(f64, f64, f64, Option<f64>)]) -> GpxTrack {
+        GpxTrack { points: points.iter().map(|&(time, lat, lon, ele)| TrackPoint { time, lat, lon, ele }).collect() }
+    }
+
+    #[test]
+    fn interpolation() {
+        let t = track(&[(100.0, 47.0, 19.0, Some(100.0)), (200.0, 48.0, 20.0, Some(200.0)), (300.0, 48.0, 21.0, None)]);
+        assert_eq!(t.position_at(150.0, 300.0), Some((47.5, 19.5, Some(150.0))));
+        assert_eq!(t.position_at(200.0, 300.0), Some((48.0, 20.0, Some(200.0))));
+        // csak az egyik ponton van magasság
+        assert_eq!(t.position_at(275.0, 300.0), Some((48.0, 20.75, Some(200.0))));
+        // a nyomvonal előtt és után csak max_gap-en belül
+        assert_eq!(t.position_at(50.0, 60.0), Some((47.0, 19.0, Some(100.0))));
+        assert_eq!(t.position_at(30.0, 60.0), None);
+        assert_eq!(t.position_at(350.0, 60.0), Some((48.0, 21.0, None)));
+        assert_eq!(t.position_at(400.0, 60.0), None);
+    }
+
+    // Szakadásnál nem interpolálunk, csak a max_gap-en belüli közelebbi pontot adjuk
+    #[test]
+    fn gap_in_track() {
+        let t = track(&[(0.0, 10.0, 10.0, None), (1000.0, 20.0, 20.0, None)]);
+        assert_eq!(t.position_at(100.0, 300.0), Some((10.0, 10.0, None)));
+        assert_eq!(t.position_at(900.0, 300.0), Some((20.0, 20.0, None)));
+        assert_eq!(t.position_at(500.0, 300.0), None);
+        assert_eq!(GpxTrack::default().position_at(0.0, 300.0), None);
+    }
+
+    // A dátumvonalon átlépve a rövidebb úton interpolálunk
+    #[test]
+    fn antimeridian() {
+        let t = track(&[(0.0, 0.0, 179.0, None), (100.0, 0.0, -179.0, None)]);
+        assert_eq!(t.position_at(25.0, 300.0), Some((0.0, 179.5, None)));
+        assert_eq!(t.position_at(75.0, 300.0), Some((0.0, -179.5, None)));
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(parse_offset("+02:00"), Some(7200));
+        assert_eq!(parse_offset(" -05:30 "), Some(-19800));
+        assert_eq!(parse_offset("+00:00"), Some(0));
+        assert_eq!(parse_offset("02:00"), None);
+        assert_eq!(parse_offset("+0200"), None);
+        assert_eq!(parse_offset("+xx:00"), None);
+        assert_eq!(parse_offset(""), None);
+    }
+
+    #[test]
+    fn parse_gpx() {
+        let gpx = r#"<gpx><trk><trkseg>
+            <trkpt lat="47.5" lon="19.0"><ele>120</ele><time>2024-05-01T10:00:10Z</time></trkpt>
+            <trkpt lat="47.4" lon="19.1"><time>2024-05-01T10:00:00.500Z</time></trkpt>
+            <trkpt lat="47.3" lon="19.2"></trkpt>
+        </trkseg></trk></gpx>"#;
+        let t = GpxTrack::parse(gpx).unwrap();
+        assert_eq!(t.points.len(), 2);
+        assert_eq!(t.points[0].time, 1714557600.5);
+        assert_eq!(t.points[1].ele, Some(120.0));
+        assert!(GpxTrack::parse("<gpx></gpx>").is_err());
+        assert!(GpxTrack::parse("<gpx>").is_err());
+    }
+}
@@ -2,6 +2,7 @@ use eframe::wgpu;
 use crate::colors::ColorSettings;
 //use wgpu::util::DeviceExt;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
 // Ez kényszeríti a Rustot, hogy figyelje a shader fájlt
 const _: &str = include_str!("shaders.wgsl");
@@ -21,7 +22,10 @@ pub struct GpuColorSettings {
     pub show_g: u32,
     pub show_b: u32,
     pub oklab: u32,
-    pub _padding: u32, // 16 bájtos igazítás
+    pub use_tone_table: u32, // a tone_table_buffer érvényes (szintek, görbék)
+    pub wb_gains: [f32; 3],  // fehéregyensúly szorzók (R, G, B)
+    pub use_hsl: u32,
+    pub hsl_bands: [[f32; 4]; crate::hsl::HSL_BANDS], // sávközép, árnyalat (0..1), telítettség, világosság
 }
 
 #[repr(C)]
@@ -35,6 +39,22 @@ pub struct GpuSharpenSettings {
     pub transparency_tolerance: f32,
     pub use_transparency: u32,
     pub rough_transparency: u32, // for gif
+    pub local_sigma: f32, // a térbeli korrekciók Gauss szigmája pixelben
+    pub vibrance: f32,
+    pub clarity: f32,
+    pub shadows: f32,
+    pub highlights: f32,
+    pub denoise_luma: f32,
+    pub denoise_chroma: f32,
+    pub sharpen_threshold: f32, // 0.0 .. 1.0 skálán
+    pub sharpen_luma: u32,
+    pub lens_k1: f32,
+    pub lens_k2: f32,
+    pub ca_red: f32,  // a csatorna nagyítása - 1.0
+    pub ca_blue: f32,
+    pub vignette_amount: f32,
+    pub vignette_midpoint: f32,
+    pub vignette_roundness: f32,
     pub _padding: u32, // 16 bájtos igazítás
 }
 
@@ -44,9 +64,12 @@ pub struct GpuInterface {
     pub queue: Arc<wgpu::Queue>,
     pipe_gen_lut: wgpu::ComputePipeline,
     pipe_apply: wgpu::ComputePipeline,
+    pipe_denoise: wgpu::ComputePipeline,
+    pipe_lens: wgpu::ComputePipeline,
     tex_identity: wgpu::Texture,
     pub tex_processed_lut: wgpu::Texture,
     color_params_buffer: wgpu::Buffer,
+    tone_table_buffer: wgpu::Buffer,
     sharpen_params_buffer: wgpu::Buffer,
     hist_buffer: wgpu::Buffer,
     hist_staging_buffer: wgpu::Buffer,
@@ -54,6 +77,9 @@ pub struct GpuInterface {
     bind_group_gen: wgpu::BindGroup,
     bind_group_apply_0: wgpu::BindGroup,
     bg_layout_apply: wgpu::BindGroupLayout,
+    bg_layout_pre: wgpu::BindGroupLayout,
+    denoise_active: AtomicBool, // a change_colorcorrection állítja, a generate_image ez alapján futtatja a zajszűrést
+    lens_active: AtomicBool,    // ugyanígy az objektív korrekciót
     colset: ColorSettings,
 }
 
@@ -114,6 +140,14 @@ impl GpuInterface {
             mapped_at_creation: false,
         });
 
+        // Szintek és tónusgörbék 256 szintre, szintenként (R, G, B, -) vec4
+        let tone_table_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Tone Table Buffer"),
+            size: (crate::curves::TONE_TABLE_SIZE * 16) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
         let sharpen_params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
             label: Some("Sharpen Params Buffer"),
             size: std::mem::size_of::<GpuSharpenSettings>() as u64,
@@ -144,6 +178,12 @@ impl GpuInterface {
                     ty: wgpu::BindingType::StorageTexture { access: wgpu::StorageTextureAccess::WriteOnly, format: wgpu::TextureFormat::Rgba8Unorm, view_dimension: wgpu::TextureViewDimension::D3 },
                     count: None,
                 },
+                wgpu::BindGroupLayoutEntry { // tone_table
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None },
+                    count: None,
+                },
             ],
         });
 
@@ -175,6 +215,22 @@ impl GpuInterface {
         });
 
 
+        // Előkészítő menetek (objektív korrekció, zajszűrés) az élesítés előtt, közös 0-ás csoporttal (10: forrás, 11: kimenet, 12: paraméterek)
+        let bg_layout_pre = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Layout Pre Pass"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry { binding: 10, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Texture { sample_type: wgpu::TextureSampleType::Float { filterable: true }, view_dimension: wgpu::TextureViewDimension::D2, multisampled: false }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 11, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::StorageTexture { access: wgpu::StorageTextureAccess::WriteOnly, format: wgpu::TextureFormat::Rgba8Unorm, view_dimension: wgpu::TextureViewDimension::D2 }, count: None },
+                wgpu::BindGroupLayoutEntry { binding: 12, visibility: wgpu::ShaderStages::COMPUTE, ty: wgpu::BindingType::Buffer { ty: wgpu::BufferBindingType::Uniform, has_dynamic_offset: false, min_binding_size: None }, count: None },
+            ],
+        });
+
+        let layout_pre = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Layout Pre Pass"),
+            bind_group_layouts: &[&bg_layout_pre],
+            push_constant_ranges: &[],
+        });
+
         // Layout a LUT generálóhoz (csak 0-ás csoport)
         let layout_gen_lut = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("Layout Gen LUT"),
@@ -208,6 +264,24 @@ impl GpuInterface {
             cache: None,
         });
 
+        let pipe_denoise = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Denoise Pipeline"),
+            layout: Some(&layout_pre),
+            module: &shader,
+            entry_point: Some("denoise"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
+        let pipe_lens = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("Lens Correction Pipeline"),
+            layout: Some(&layout_pre),
+            module: &shader,
+            entry_point: Some("lens_correct"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+
         // Helyes BindGroup létrehozás
         let bind_group_gen = device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Bind Group Gen"),
@@ -216,6 +290,7 @@ impl GpuInterface {
                 wgpu::BindGroupEntry { binding: 0, resource: color_params_buffer.as_entire_binding(), },
                 wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&tex_identity.create_view(&wgpu::TextureViewDescriptor::default()) ), },
                 wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&tex_processed_lut.create_view(&wgpu::TextureViewDescriptor::default()) ), },
+                wgpu::BindGroupEntry { binding: 3, resource: tone_table_buffer.as_entire_binding(), },
             ],
         });
 
@@ -251,9 +326,12 @@ impl GpuInterface {
             queue: queue.into(),
             pipe_gen_lut,
             pipe_apply,
+            pipe_denoise,
+            pipe_lens,
             tex_identity,
             tex_processed_lut,
             color_params_buffer,
+            tone_table_buffer,
             sharpen_params_buffer,
             hist_buffer,
             hist_staging_buffer,
@@ -261,6 +339,9 @@ impl GpuInterface {
             bind_group_gen, // Ezt is hozzá kell adni a struct-hoz!
             bind_group_apply_0,
             bg_layout_apply, // Későbbi kép-bindinghoz
+            bg_layout_pre,
+            denoise_active: AtomicBool::new(false),
+            lens_active: AtomicBool::new(false),
             colset: ColorSettings::default(),
         })
     }
@@ -280,9 +361,17 @@ impl GpuInterface {
             show_g: if colset.show_g { 1 } else { 0 },
             show_b: if colset.show_b { 1 } else { 0 },
             oklab: if colset.oklab { 1 } else { 0 },
-            _padding: 0,
+            use_tone_table: if colset.has_tone() { 1 } else { 0 },
+            wb_gains: colset.white_balance_gains(),
+            use_hsl: if colset.has_hsl() { 1 } else { 0 },
+            hsl_bands: {
+                let centers = crate::hsl::band_centers(colset.oklab);
+                std::array::from_fn(|i| [centers[i], colset.hsl[i].hue / 360.0, colset.hsl[i].saturation, colset.hsl[i].lightness])
+            },
         };
         self.queue.write_buffer(&self.color_params_buffer, 0, bytemuck::bytes_of(&gpu_settings));
+        let tone_table = crate::curves::tone_table(colset);
+        self.queue.write_buffer(&self.tone_table_buffer, 0, bytemuck::cast_slice(&tone_table));
 
         let gpu_filter = GpuSharpenSettings {
             sharpen_radius: colset.sharpen_radius,
@@ -296,10 +385,28 @@ impl GpuInterface {
                 0.0,
             ],
             transparency_tolerance: colset.transparency_tolerance,
-            use_transparency: if colset.use_transparency { 1 } else { 0 },
+            use_transparency: if colset.use_transparency && !colset.transparency_flood { 1 } else { 0 },
             rough_transparency: if colset.rough_transparency { 1 } else { 0 },
+            local_sigma: crate::local_adjust::local_sigma(width as u32, height as u32),
+            vibrance: colset.vibrance,
+            clarity: colset.clarity,
+            shadows: colset.shadows,
+            highlights: colset.highlights,
+            denoise_luma: colset.denoise_luma,
+            denoise_chroma: colset.denoise_chroma,
+            sharpen_threshold: colset.sharpen_threshold / 255.0,
+            sharpen_luma: if colset.sharpen_luma { 1 } else { 0 },
+            lens_k1: colset.lens.k1,
+            lens_k2: colset.lens.k2,
+            ca_red: colset.lens.ca_red / 100.0,
+            ca_blue: colset.lens.ca_blue / 100.0,
+            vignette_amount: colset.lens.vignette_amount,
+            vignette_midpoint: colset.lens.vignette_midpoint,
+            vignette_roundness: colset.lens.vignette_roundness,
             _padding: 0,
         };
+        self.denoise_active.store(colset.has_denoise(), Ordering::Relaxed);
+        self.lens_active.store(colset.has_lens_correction(), Ordering::Relaxed);
         self.queue.write_buffer(&self.sharpen_params_buffer, 0, bytemuck::bytes_of(&gpu_filter));
 
         let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
@@ -358,12 +465,35 @@ impl GpuInterface {
             view_formats: &[],
         });
 
+        // 3. Előkészítő menetek (objektív korrekció, zajszűrés) egy-egy köztes textúrába, az élesítés már az utolsót olvassa
+        let pre_passes: Vec<&wgpu::ComputePipeline> = [
+            (self.lens_active.load(Ordering::Relaxed), &self.pipe_lens),
+            (self.denoise_active.load(Ordering::Relaxed), &self.pipe_denoise),
+        ]
+        .into_iter()
+        .filter_map(|(active, pipe)| active.then_some(pipe))
+        .collect();
+        let mut views = vec![tex_src.create_view(&Default::default())];
+        for _ in &pre_passes {
+            let tex = self.device.create_texture(&wgpu::TextureDescriptor {
+                label: Some("Pre Pass Image"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::STORAGE_BINDING,
+                view_formats: &[],
+            });
+            views.push(tex.create_view(&Default::default()));
+        }
+
         // 4. Bind Group létrehozása a képfeldolgozáshoz
         let bind_group_apply = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
             label: Some("Apply Bind Group"),
             layout: &self.bg_layout_apply,
             entries: &[
-                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&tex_src.create_view(&Default::default())) },
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(views.last().unwrap()) },
                 wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(&self.sampler) },
                 wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&self.tex_processed_lut.create_view(&Default::default())) },
                 wgpu::BindGroupEntry { binding: 3, resource: self.sharpen_params_buffer.as_entire_binding() },
@@ -375,6 +505,22 @@ impl GpuInterface {
         let mut encoder = self.device.create_command_encoder(&Default::default());
         encoder.clear_buffer(&self.hist_buffer, 0, None);
 
+        for (i, pipe) in pre_passes.iter().enumerate() {
+            let bind_group_pre = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: Some("Pre Pass Bind Group"),
+                layout: &self.bg_layout_pre,
+                entries: &[
+                    wgpu::BindGroupEntry { binding: 10, resource: wgpu::BindingResource::TextureView(&views[i]) },
+                    wgpu::BindGroupEntry { binding: 11, resource: wgpu::BindingResource::TextureView(&views[i + 1]) },
+                    wgpu::BindGroupEntry { binding: 12, resource: self.sharpen_params_buffer.as_entire_binding() },
+                ],
+            });
+            let mut cpass = encoder.begin_compute_pass(&Default::default());
+            cpass.set_pipeline(pipe);
+            cpass.set_bind_group(0, &bind_group_pre, &[]);
+            cpass.dispatch_workgroups(width.div_ceil(16), height.div_ceil(16), 1);
+        }
+
         {
             let mut cpass = encoder.begin_compute_pass(&Default::default());
             cpass.set_pipeline(&self.pipe_apply);
@@ -0,0 +1,159 @@
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use crate::colors::ColorSettings;
+use crate::ImageViewer;
+
+// HSL keverő: nyolc színsáv saját árnyalat, telítettség és világosság eltolással.
+// A sávok között simán átmenetes interpoláció van, a szürkék (0 telítettség) érintetlenek maradnak.
+
+pub const HSL_BANDS: usize = 8;
+pub const MAX_BAND_HUE_SHIFT: f32 = 30.0; // fok
+const BAND_NAMES: [&str; HSL_BANDS] = ["Reds", "Oranges", "Yellows", "Greens", "Aquas", "Blues", "Purples", "Magentas"];
+const BAND_HSV_HUES: [f32; HSL_BANDS] = [0.0, 30.0, 60.0, 120.0, 180.0, 240.0, 270.0, 300.0];
+const MODE_NAMES: [&str; 3] = ["Hue", "Saturation", "Lightness"];
+
+// A sávok közepe a színkörön (0.0 .. 1.0) HSV és Oklab térben, ugyanazokból a referencia színekből
+static BAND_CENTERS: LazyLock<[[f32; HSL_BANDS]; 2]> = LazyLock::new(|| {
+    let hsv = BAND_HSV_HUES.map(|h| h / 360.0);
+    let oklab = hsv.map(|h| ColorSettings::rgb_to_oklab(ColorSettings::hsv_to_rgb([h, 1.0, 1.0, 1.0]))[0]);
+    [hsv, oklab]
+});
+
+pub fn band_centers(oklab: bool) -> [f32; HSL_BANDS] {
+    BAND_CENTERS[oklab as usize]
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(default)]
+pub struct HslBand {
+    pub hue: f32,        // -30.0 .. 30.0 (fok)
+    pub saturation: f32, // -1.0 .. 1.0
+    pub lightness: f32,  // -1.0 .. 1.0
+}
+
+impl HslBand {
+    pub fn is_identity(&self) -> bool {
+        self.hue.abs() < 0.001 && self.saturation.abs() < 0.001 && self.lightness.abs() < 0.001
+    }
+}
+
+impl ColorSettings {
+    pub fn has_hsl(&self) -> bool {
+        !self.hsl.iter().all(|b| b.is_identity())
+    }
+
+    // hsv: [árnyalat, telítettség, világosság] a választott térben; a két szomszédos sáv beállítása keverve
+    pub fn apply_hsl(&self, hsv: &mut [f32; 4]) {
+        let centers = band_centers(self.oklab);
+        let h = hsv[0];
+        let mut i = HSL_BANDS - 1;
+        for (k, c) in centers.iter().enumerate() {
+            if h >= *c {
+                i = k;
+            }
+        }
+        let j = (i + 1) % HSL_BANDS;
+        let span = (centers[j] - centers[i]).rem_euclid(1.0).max(0.001);
+        let t = ((h - centers[i]).rem_euclid(1.0) / span).clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+        let (a, b) = (&self.hsl[i], &self.hsl[j]);
+        let hue = a.hue + (b.hue - a.hue) * t;
+        let sat = a.saturation + (b.saturation - a.saturation) * t;
+        let light = a.lightness + (b.lightness - a.lightness) * t;
+
+        let weight = hsv[1].clamp(0.0, 1.0);
+        hsv[0] = (hsv[0] + hue / 360.0 * weight).rem_euclid(1.0);
+        if sat > 0.0 {
+            hsv[1] += (1.0 - hsv[1]) * sat * weight;
+        } else {
+            hsv[1] *= 1.0 + sat;
+        }
+        hsv[2] *= 1.0 + 0.5 * light * weight;
+    }
+}
+
+impl ImageViewer {
+    pub fn hsl_panel(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        ui.horizontal(|ui| {
+            for (i, name) in MODE_NAMES.iter().enumerate() {
+                if ui.selectable_label(self.hsl_mode == i, *name).clicked() {
+                    self.hsl_mode = i;
+                }
+            }
+            ui.add_space(20.0);
+            if ui.button("Reset All Bands").clicked() {
+                self.color_settings.hsl = Default::default();
+                *changed = true;
+            }
+        });
+        let gpu = self.gpu_interface.is_some();
+        let oklab = self.color_settings.oklab;
+        for (b, name) in BAND_NAMES.iter().enumerate() {
+            ui.horizontal(|ui| {
+                // a sáv színe a címke előtt
+                let swatch = ColorSettings::hsv_to_rgb([BAND_HSV_HUES[b] / 360.0, 0.8, 0.9, 1.0]);
+                let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                ui.painter().rect_filled(rect, 2.0, egui::Rgba::from_rgb(swatch[0], swatch[1], swatch[2]));
+                let band = &mut self.color_settings.hsl[b];
+                let (value, range) = match self.hsl_mode {
+                    0 => (&mut band.hue, -MAX_BAND_HUE_SHIFT..=MAX_BAND_HUE_SHIFT),
+                    1 => (&mut band.saturation, -1.0..=1.0),
+                    _ => (&mut band.lightness, -1.0..=1.0),
+                };
+                let mut slider = egui::Slider::new(value, range).text(*name);
+                if self.hsl_mode == 0 {
+                    slider = slider.suffix("°");
+                }
+                let res = ui.add(slider);
+                if gpu {
+                    *changed |= res.changed();
+                } else {
+                    *changed |= res.drag_stopped() || (res.changed() && !ui.input(|i| i.pointer.any_down()));
+                }
+                if ui.button("⟲").on_hover_text("Reset Band").clicked() {
+                    *band = HslBand::default();
+                    *changed = true;
+                }
+            });
+        }
+        if !oklab {
+            ui.label(egui::RichText::new("Bands are computed in HSV space (Oklab is off)").weak());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A sávkeresés a színkörön növekvő középpontokat feltételez, mindkét térben
+    #[test]
+    fn band_centers_increase() {
+        for oklab in [false, true] {
+            let centers = band_centers(oklab);
+            assert!(centers.windows(2).all(|w| w[0] < w[1]), "{} {:?}", oklab, centers);
+            assert!(centers[0] >= 0.0 && centers[HSL_BANDS - 1] < 1.0);
+        }
+    }
+
+    // A bíborok és a vörösök közötti árnyalat a kör átfordulásánál a két sávot keveri
+    #[test]
+    fn wraps_between_magentas_and_reds() {
+        let mut colset = ColorSettings::default();
+        colset.hsl[0].hue = -10.0;
+        colset.hsl[HSL_BANDS - 1].hue = 10.0;
+        for oklab in [false, true] {
+            colset.oklab = oklab;
+            let centers = band_centers(oklab);
+            let (magentas, reds) = (centers[HSL_BANDS - 1], centers[0] + 1.0);
+            for f in [0.25, 0.5, 0.75] {
+                let h = (magentas + (reds - magentas) * f).rem_euclid(1.0);
+                let mut hsv = [h, 1.0, 1.0, 1.0];
+                colset.apply_hsl(&mut hsv);
+                let shift = ((hsv[0] - h + 0.5).rem_euclid(1.0) - 0.5) * 360.0;
+                let t = f * f * (3.0 - 2.0 * f);
+                assert!((shift - (10.0 - 20.0 * t)).abs() < 0.01, "{} {} {}", oklab, f, shift);
+            }
+        }
+    }
+}
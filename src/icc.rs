@@ -0,0 +1,229 @@
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use moxcms::{ColorProfile, DataColorSpace, Layout, ProfileText, ToneReprCurve, TransformOptions, Transform8BitExecutor, Xyzd};
+use img_parts::ImageICC;
+
+use crate::file_handlers::SaveFormat;
+use crate::ImageViewer;
+
+// Mentéskor: a pixeleket sRGB-be alakítjuk, vagy megtartjuk az eredeti profilt
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum IccSave {
+    ConvertToSrgb,
+    KeepProfile,
+}
+
+#[derive(Clone)]
+pub struct IccProfile {
+    pub data: Vec<u8>,   // a nyers ICC profil, így kerül vissza a mentett fájlba
+    pub name: String,
+    pub profile: ColorProfile,
+}
+
+impl IccProfile {
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let profile = ColorProfile::new_from_slice(data).ok()?;
+        if profile.color_space != DataColorSpace::Rgb {
+            return None; // CMYK/Gray képeket az image crate már RGB-re alakít
+        }
+        let name = profile.description.as_ref()
+            .map(profile_text)
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "Unnamed ICC profile".to_string());
+        Some(Self { data: data.to_vec(), name, profile })
+    }
+
+    pub fn from_file(path: &PathBuf) -> Option<Self> {
+        let mut data = Vec::new();
+        std::fs::File::open(path).ok()?.read_to_end(&mut data).ok()?;
+        Self::from_bytes(&data)
+    }
+
+    // A név nem mérvadó: a színezőanyagokat és a tónusgörbéket vetjük össze az sRGB-vel.
+    // LUT alapú profilnál nem döntünk, azt mindig átalakítjuk.
+    pub fn is_srgb(&self) -> bool {
+        let p = &self.profile;
+        let srgb = ColorProfile::new_srgb();
+        if p.lut_a_to_b_perceptual.is_some() || p.lut_a_to_b_colorimetric.is_some() || p.lut_a_to_b_saturation.is_some() {
+            return false;
+        }
+        let colorants = [(p.red_colorant, srgb.red_colorant), (p.green_colorant, srgb.green_colorant), (p.blue_colorant, srgb.blue_colorant)];
+        colorants.iter().all(|(a, b)| same_xyz(a, b))
+            && [&p.red_trc, &p.green_trc, &p.blue_trc].iter().all(|trc| same_trc(trc.as_ref(), srgb.red_trc.as_ref()))
+    }
+
+    pub fn transform_to(&self, dst: &ColorProfile) -> Option<Arc<Transform8BitExecutor>> {
+        self.profile
+            .create_transform_8bit(Layout::Rgba, dst, Layout::Rgba, TransformOptions::default())
+            .ok()
+            .map(Arc::from)
+    }
+
+    pub fn transform_to_srgb(&self) -> Option<Arc<Transform8BitExecutor>> {
+        self.transform_to(&ColorProfile::new_srgb())
+    }
+}
+
+// A profilok s15Fixed16 kerekítése és a kissé eltérő adaptációs mátrixok miatt tűréssel
+fn same_xyz(a: &Xyzd, b: &Xyzd) -> bool {
+    (a.x - b.x).abs() < 0.003 && (a.y - b.y).abs() < 0.003 && (a.z - b.z).abs() < 0.003
+}
+
+// A görbéket mintavételezve hasonlítjuk, mert az sRGB paraméteresen és táblázattal is megadható
+fn same_trc(a: Option<&ToneReprCurve>, b: Option<&ToneReprCurve>) -> bool {
+    let (Some(Ok(a)), Some(Ok(b))) = (a.map(|c| c.make_linear_evaluator()), b.map(|c| c.make_linear_evaluator())) else {
+        return false;
+    };
+    (0..=32).map(|i| i as f32 / 32.0).all(|v| (a.evaluate_value(v) - b.evaluate_value(v)).abs() < 0.002)
+}
+
+fn profile_text(text: &ProfileText) -> String {
+    match text {
+        ProfileText::PlainString(s) => s.clone(),
+        ProfileText::Localizable(list) => list.first().map(|l| l.value.clone()).unwrap_or_default(),
+        ProfileText::Description(d) => d.ascii_string.clone(),
+    }
+    .trim_matches(char::from(0))
+    .trim()
+    .to_string()
+}
+
+pub fn apply_icc_transform(transform: &Transform8BitExecutor, img: &mut image::RgbaImage) {
+    let src = img.as_raw().clone();
+    if let Err(e) = transform.transform(&src, img.as_mut()) {
+        println!("ICC konverziós hiba: {}", e);
+    }
+}
+
+pub fn convert_dynamic_image(transform: &Transform8BitExecutor, img: &mut image::DynamicImage) {
+    let mut rgba = img.to_rgba8();
+    apply_icc_transform(transform, &mut rgba);
+    *img = image::DynamicImage::ImageRgba8(rgba);
+}
+
+// Beágyazott ICC profil kiolvasása a fájl konténeréből (JPEG APP2, PNG iCCP, WebP ICCP, TIFF tag)
+pub fn read_icc_profile(buffer: &[u8], format: SaveFormat, path: &PathBuf) -> Option<Vec<u8>> {
+    match format {
+        SaveFormat::Jpeg => img_parts::jpeg::Jpeg::from_bytes(buffer.to_vec().into()).ok()?
+            .icc_profile().map(|b| b.to_vec()),
+        SaveFormat::Png => img_parts::png::Png::from_bytes(buffer.to_vec().into()).ok()?
+            .icc_profile().map(|b| b.to_vec()),
+        SaveFormat::Webp => img_parts::webp::WebP::from_bytes(buffer.to_vec().into()).ok()?
+            .icc_profile().map(|b| b.to_vec()),
+        SaveFormat::Tif => {
+            let file = std::fs::File::open(path).ok()?;
+            let mut decoder = tiff::decoder::Decoder::new(file).ok()?;
+            decoder.get_tag_u8_vec(tiff::tags::Tag::IccProfile).ok()
+        }
+        _ => None,
+    }
+}
+
+pub fn can_embed_icc(format: SaveFormat) -> bool {
+    matches!(format, SaveFormat::Jpeg | SaveFormat::Png | SaveFormat::Webp | SaveFormat::Tif)
+}
+
+// A profil beírása egy már elmentett JPEG/PNG/WebP fájlba (TIFF-nél a tag-et az enkóder írja)
+pub fn embed_icc_profile(path: &PathBuf, format: SaveFormat, icc: &[u8]) -> Result<(), String> {
+    let data = std::fs::read(path).map_err(|e| e.to_string())?;
+    let profile = Some(img_parts::Bytes::copy_from_slice(icc));
+    let mut out = Vec::new();
+    match format {
+        SaveFormat::Jpeg => {
+            let mut jpeg = img_parts::jpeg::Jpeg::from_bytes(data.into()).map_err(|e| e.to_string())?;
+            jpeg.set_icc_profile(profile);
+            jpeg.encoder().write_to(&mut out).map_err(|e| e.to_string())?;
+        }
+        SaveFormat::Png => {
+            let mut png = img_parts::png::Png::from_bytes(data.into()).map_err(|e| e.to_string())?;
+            png.set_icc_profile(profile);
+            png.encoder().write_to(&mut out).map_err(|e| e.to_string())?;
+        }
+        SaveFormat::Webp => {
+            let mut webp = img_parts::webp::WebP::from_bytes(data.into()).map_err(|e| e.to_string())?;
+            webp.set_icc_profile(profile);
+            webp.encoder().write_to(&mut out).map_err(|e| e.to_string())?;
+        }
+        _ => return Ok(()),
+    }
+    std::fs::write(path, out).map_err(|e| e.to_string())
+}
+
+impl ImageViewer {
+
+    // A megjelenítéshez használt transzformáció (kép profil -> monitor profil) újraépítése
+    pub fn update_icc_transform(&mut self) {
+        self.icc_transform = None;
+        if !self.color_management {
+            return;
+        }
+        if let Some(icc) = &self.icc_profile {
+            self.icc_transform = match &self.display_profile {
+                Some(display) => icc.transform_to(&display.profile),
+                None => if icc.is_srgb() { None } else { icc.transform_to_srgb() },
+            };
+        } else if let Some(display) = &self.display_profile {
+            // Profil nélküli képet sRGB-nek tekintünk
+            if let Some(srgb) = IccProfile::from_bytes(&ColorProfile::new_srgb().encode().unwrap_or_default()) {
+                self.icc_transform = srgb.transform_to(&display.profile);
+            }
+        }
+    }
+
+    pub fn load_display_profile(&mut self) {
+        self.display_profile = self.config.display_profile.as_ref().and_then(IccProfile::from_file);
+        self.update_icc_transform();
+    }
+
+    pub fn choose_display_profile(&mut self, ctx: &egui::Context) {
+        let dialog = rfd::FileDialog::new()
+            .set_title("iView 🔍 Display ICC profile")
+            .add_filter("ICC profile", &["icc", "icm"]);
+        if let Some(path) = dialog.pick_file() {
+            if IccProfile::from_file(&path).is_some() {
+                self.config.display_profile = Some(path);
+            } else {
+                println!("Nem RGB ICC profil: {:?}", path);
+                self.config.display_profile = None;
+            }
+        } else {
+            self.config.display_profile = None;
+        }
+        self.load_display_profile();
+        self.review(ctx, true, false);
+    }
+
+    // Vágólapra és sRGB-s mentéshez: kép profil -> sRGB
+    pub fn to_srgb(&self, img: &mut image::DynamicImage) {
+        let transform = self.icc_profile.as_ref()
+            .filter(|icc| !icc.is_srgb())
+            .and_then(|icc| icc.transform_to_srgb());
+        if let Some(transform) = transform {
+            convert_dynamic_image(transform.as_ref(), img);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(mut profile: ColorProfile, name: &str) -> IccProfile {
+        profile.description = Some(ProfileText::PlainString(name.to_string()));
+        let data = profile.encode().unwrap();
+        IccProfile::from_bytes(&data).unwrap()
+    }
+
+    #[test]
+    fn srgb_by_content() {
+        assert!(profile(ColorProfile::new_srgb(), "Untitled").is_srgb());
+        let mut gamma = ColorProfile::new_srgb();
+        gamma.red_trc = Some(moxcms::curve_from_gamma(2.2));
+        gamma.green_trc = gamma.red_trc.clone();
+        gamma.blue_trc = gamma.red_trc.clone();
+        assert!(!profile(gamma, "sRGB").is_srgb());
+        assert!(!profile(ColorProfile::new_display_p3(), "sRGB look").is_srgb());
+        assert!(!profile(ColorProfile::new_adobe_rgb(), "Adobe RGB").is_srgb());
+    }
+}
@@ -0,0 +1,105 @@
+use crate::exif_my::BYTESPERFORMAT;
+
+// TIFF könyvtárak (IFD) határellenőrzött olvasása; az EXIF szerkesztő és a MakerNote feldolgozás közös alapja
+
+pub const FMT_BYTE: u16 = 1;
+pub const FMT_ASCII: u16 = 2;
+pub const FMT_ULONG: u16 = 4;
+pub const FMT_URATIONAL: u16 = 5;
+
+#[derive(Clone, Debug)]
+pub struct IfdEntry {
+    pub tag: u16,
+    pub format: u16,
+    pub count: u32,
+    pub data: Vec<u8>, // az érték bájtjai a blokk bájtsorrendjében
+    pub offset: u32,   // az eredeti helye a blokkban (0, ha az entry-ben volt)
+}
+
+impl IfdEntry {
+    pub fn pointer(tag: u16) -> Self {
+        Self { tag, format: FMT_ULONG, count: 1, data: vec![0; 4], offset: 0 }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Ifd {
+    pub entries: Vec<IfdEntry>,
+}
+
+impl Ifd {
+    pub fn get(&self, tag: u16) -> Option<&IfdEntry> {
+        self.entries.iter().find(|e| e.tag == tag)
+    }
+
+    pub fn set(&mut self, entry: IfdEntry) {
+        self.remove(entry.tag);
+        self.entries.push(entry);
+    }
+
+    pub fn remove(&mut self, tag: u16) {
+        self.entries.retain(|e| e.tag != tag);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+pub(crate) struct Reader<'a> {
+    pub buf: &'a [u8],
+    pub motorola: bool,
+}
+
+impl Reader<'_> {
+    pub fn u16(&self, pos: usize) -> Option<u16> {
+        let bytes = self.buf.get(pos..pos.checked_add(2)?)?.try_into().ok()?;
+        Some(if self.motorola { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) })
+    }
+
+    pub fn u32(&self, pos: usize) -> Option<u32> {
+        let bytes = self.buf.get(pos..pos.checked_add(4)?)?.try_into().ok()?;
+        Some(if self.motorola { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    }
+
+    pub fn value_u32(&self, entry: &IfdEntry) -> Option<u32> {
+        let reader = Reader { buf: &entry.data, motorola: self.motorola };
+        match entry.format {
+            3 => reader.u16(0).map(|v| v as u32),
+            4 | 13 => reader.u32(0),
+            _ => None,
+        }
+    }
+
+    pub fn pointer(&self, ifd: &Ifd, tag: u16) -> Option<usize> {
+        ifd.get(tag).and_then(|e| self.value_u32(e)).map(|v| v as usize)
+    }
+
+    // Egy könyvtár beolvasása; a hibás entry-ket (ismeretlen formátum, blokkon kívüli adat) eldobjuk
+    pub fn ifd(&self, pos: usize) -> Result<(Ifd, u32), String> {
+        let count = self.u16(pos).ok_or("IFD a blokkon kívül")? as usize;
+        let mut ifd = Ifd::default();
+        for i in 0..count {
+            let p = pos + 2 + 12 * i;
+            let (Some(tag), Some(format), Some(n)) = (self.u16(p), self.u16(p + 2), self.u32(p + 4)) else {
+                return Err("Csonka IFD".into());
+            };
+            // 13: IFD mutató (pl. Olympus MakerNote alkönyvtárai)
+            let size = if format == 13 { 4 } else { BYTESPERFORMAT.get(format as usize).copied().unwrap_or(0) };
+            let Some(len) = size.checked_mul(n as usize).filter(|len| *len > 0) else {
+                continue;
+            };
+            let (data, offset) = if len <= 4 {
+                (self.buf.get(p + 8..p + 8 + len), 0)
+            } else {
+                let offset = self.u32(p + 8).unwrap_or(0);
+                (self.buf.get(offset as usize..(offset as usize).saturating_add(len)), offset)
+            };
+            if let Some(data) = data {
+                ifd.entries.push(IfdEntry { tag, format, count: n, data: data.to_vec(), offset });
+            }
+        }
+        let next = self.u32(pos + 2 + 12 * count).unwrap_or(0);
+        Ok((ifd, next))
+    }
+}
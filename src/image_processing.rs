@@ -94,8 +94,10 @@ pub struct Resolution {
 
 pub struct AnimatedImage {
     //pub anim_frames: Vec<egui::TextureHandle>, // GPU textúrák // old
-    pub anim_frames: Vec<image::DynamicImage>,
+    pub anim_frames: Vec<Option<image::DynamicImage>>, // None: még be nem töltött oldal
     pub delays: Vec<std::time::Duration>, // Időzítések
+    pub is_pages: bool, // többoldalas dokumentum (TIFF, ICO), nem animáció
+    pub source: Option<crate::pages::PageSource>, // innen töltjük be az oldalakat, amikor először kellenek
 }
 
 /*pub fn color_image_to_dynamic(color_image: egui::ColorImage) -> image::DynamicImage {
@@ -193,7 +195,31 @@ impl ImageViewer {
         }
         
         let img : & mut image::DynamicImage = &mut Default::default();
-        if let Some(resized_image) = self.resized_image.clone() {
+        let mut render_scale = 1.0;
+        if let Some(svg) = &mut self.svg {
+            // vektoros kép: az aktuális nagyításon raszterizálunk
+            let max_side = ctx.input(|i| i.max_texture_side) as u32;
+            if let Some((rendered, scale)) = svg.render(self.magnify, max_side) {
+                *img = rendered;
+                render_scale = scale;
+            }
+            svg.scale = self.magnify;
+            svg.render_scale = render_scale;
+            self.original_image_size = svg.size().into();
+            self.resize = 1.0;
+        }
+        else if let Some(pdf) = &mut self.pdf {
+            // PDF oldal: 1.0 nagyítás 72 dpi-nek felel meg
+            let max_side = ctx.input(|i| i.max_texture_side) as u32;
+            if let Some((rendered, scale)) = pdf.render(self.current_frame, self.magnify, max_side) {
+                *img = rendered;
+                render_scale = scale;
+            }
+            pdf.scale = self.magnify;
+            pdf.render_scale = render_scale;
+            self.resize = 1.0;
+        }
+        else if let Some(resized_image) = self.resized_image.clone() {
             *img = resized_image;
         }
         else {
@@ -233,13 +259,17 @@ impl ImageViewer {
 
         let mut rgba_image = img.to_rgba8();
         let (width, height) = rgba_image.dimensions();
-        self.image_size = (width, height).into();
+        // a pipetták a korrekciók előtti értéket mérik
+        let differs = self.modified || self.alpha_view != crate::alpha::AlphaView::Normal;
+        self.source_image = differs.then(|| rgba_image.clone());
+        let size: crate::pf32::Pf32 = (width, height).into();
+        self.image_size = size / render_scale;
         
         if let Some(interface) = &self.gpu_interface {
             interface.change_colorcorrection(
                 if self.show_original_only { &default_settings } else { &self.color_settings },
-                self.image_size.x,
-                self.image_size.y);
+                width as f32,
+                height as f32);
         }
 
         if self.modified {
@@ -260,9 +290,21 @@ impl ImageViewer {
             //println!("c");
         }
 
+        // varázspálca: az összefüggő terület maszkja a korrigált képen; előnézetben pirossal jelölve
+        if self.modified {
+            self.color_settings.flood_mask = crate::magic_wand::build_flood_mask(&rgba_image, &self.color_settings).map(std::sync::Arc::new);
+            crate::magic_wand::apply_flood_mask(&mut rgba_image, &self.color_settings, self.show_mask);
+            crate::alpha::apply_alpha_edits(&mut rgba_image, &self.color_settings);
+        }
+        crate::alpha::apply_alpha_view(&mut rgba_image, self.alpha_view, self.color_settings.matte_color);
+
         self.rgba_image = Some(rgba_image.clone());
-        
-        
+
+        // színkezelés: kép profil -> monitor profil, csak a textúrán; a kiolvasott értékek a kép terében maradnak
+        if let Some(transform) = &self.icc_transform {
+            crate::icc::apply_icc_transform(transform.as_ref(), &mut rgba_image);
+        }
+
         let pixel_data = rgba_image.into_raw();
         let color_image = egui::ColorImage::from_rgba_unmultiplied(
             [width as usize, height as usize],
@@ -272,7 +314,16 @@ impl ImageViewer {
         self.texture = Some(ctx.load_texture("kep", color_image, Default::default()));
     }
 
+    // A megjelenítési koordináta a raszterizált képen (vektoros képnél más a felbontás)
+    fn image_pixel(&self, pixel_x: u32, pixel_y: u32) -> (u32, u32) {
+        match self.vector_render_scale() {
+            Some(scale) => ((pixel_x as f32 * scale) as u32, (pixel_y as f32 * scale) as u32),
+            None => (pixel_x, pixel_y),
+        }
+    }
+
     pub fn pick_color(&self, pixel_x : u32,pixel_y: u32) -> Option<egui::Color32> {
+        let (pixel_x, pixel_y) = self.image_pixel(pixel_x, pixel_y);
         if let Some(rgba_image) = &self.rgba_image {
             if pixel_x < rgba_image.width() && pixel_y < rgba_image.height() {
                 let pixel = rgba_image.get_pixel(pixel_x, pixel_y);
@@ -282,6 +333,29 @@ impl ImageViewer {
         None
     }
     
+    // Ugyanaz a pont a korrekciók előtti képen (0.0 .. 1.0)
+    pub fn pick_source(&self, pixel_x: u32, pixel_y: u32) -> Option<[f32; 4]> {
+        let (pixel_x, pixel_y) = self.image_pixel(pixel_x, pixel_y);
+        let img = self.source_image.as_ref().or(self.rgba_image.as_ref())?;
+        (pixel_x < img.width() && pixel_y < img.height())
+            .then(|| img.get_pixel(pixel_x, pixel_y).0.map(|v| v as f32 / 255.0))
+    }
+
+    // A korrekciók előtti kép R, G, B hisztogramja a stage után; nagy képnél ritkítva, a vágási pontokhoz elég
+    pub fn source_histogram(&self, stage: impl Fn(&mut [f32; 4])) -> Option<Vec<u32>> {
+        let img = self.source_image.as_ref().or(self.rgba_image.as_ref())?;
+        let step = ((img.width() as usize * img.height() as usize) / 1_000_000).max(1);
+        let mut hist = vec![0u32; 768];
+        for p in img.pixels().step_by(step) {
+            let mut color = p.0.map(|v| v as f32 / 255.0);
+            stage(&mut color);
+            for c in 0..3 {
+                hist[c * 256 + (color[c].clamp(0.0, 1.0) * 255.0 + 0.5) as usize] += 1;
+            }
+        }
+        Some(hist)
+    }
+
     pub fn calculate_histogram_only(&mut self, img: &image::RgbaImage) {
         self.hist = vec![0u32; 1024];
         img.pixels().for_each(|p| {
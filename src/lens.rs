@@ -0,0 +1,197 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::colors::ColorSettings;
+use crate::ImageViewer;
+
+// Objektív korrekció: radiális torzítás (k1, k2), oldalirányú színi hiba (a vörös és kék csatorna nagyítása)
+// és vignetta. A koordináták a kép közepéhez képest, a fél átlóval normálva (a sarok r = 1).
+// A shaders.wgsl lens_correct belépési pontja ugyanezt számolja; minden más szűrő előtt fut.
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct LensCorrection {
+    pub k1: f32,                 // -0.5 .. 0.5, negatív: hordótorzítás javítása
+    pub k2: f32,                 // -0.5 .. 0.5
+    pub ca_red: f32,             // -1.0 .. 1.0 (%), a vörös csatorna nagyítása
+    pub ca_blue: f32,            // -1.0 .. 1.0 (%)
+    pub vignette_amount: f32,    // -1.0 .. 1.0, negatív sötétít, pozitív világosít (eltávolítás)
+    pub vignette_midpoint: f32,  // 0.0 .. 1.0, ahol az átmenet kezdődik
+    pub vignette_roundness: f32, // -1.0 (szögletes) .. 0.0 (képarányos ellipszis) .. 1.0 (kör)
+}
+
+impl Default for LensCorrection {
+    fn default() -> Self {
+        Self { k1: 0.0, k2: 0.0, ca_red: 0.0, ca_blue: 0.0, vignette_amount: 0.0, vignette_midpoint: 0.5, vignette_roundness: 0.0 }
+    }
+}
+
+impl LensCorrection {
+    pub fn has_geometry(&self) -> bool {
+        self.k1.abs() >= 0.0001 || self.k2.abs() >= 0.0001 || self.ca_red.abs() >= 0.0001 || self.ca_blue.abs() >= 0.0001
+    }
+
+    pub fn has_vignette(&self) -> bool {
+        self.vignette_amount.abs() >= 0.001
+    }
+
+    pub fn is_identity(&self) -> bool {
+        !self.has_geometry() && !self.has_vignette()
+    }
+
+    // A forrás helye (fél átlóval normálva) a kimeneti p pontra
+    fn distort(&self, p: [f32; 2]) -> [f32; 2] {
+        let r2 = p[0] * p[0] + p[1] * p[1];
+        let scale = 1.0 + self.k1 * r2 + self.k2 * r2 * r2;
+        [p[0] * scale, p[1] * scale]
+    }
+
+    // Vignetta szorzó; q: a kép közepéhez képest, a fél szélesség / magasság egység
+    pub fn vignette_factor(&self, q: [f32; 2], aspect: f32) -> f32 {
+        let circle = [q[0] * aspect.max(1.0), q[1] / aspect.min(1.0)];
+        let round = self.vignette_roundness.max(0.0);
+        let q = [q[0] + (circle[0] - q[0]) * round, q[1] + (circle[1] - q[1]) * round];
+        let n = 2.0 - self.vignette_roundness.min(0.0) * 6.0;
+        let d = (q[0].abs().powf(n) + q[1].abs().powf(n)).powf(1.0 / n);
+        let start = self.vignette_midpoint;
+        let t = ((d - start) / 0.75).clamp(0.0, 1.0);
+        1.0 + self.vignette_amount * t * t * (3.0 - 2.0 * t)
+    }
+}
+
+impl ColorSettings {
+    pub fn has_lens_correction(&self) -> bool {
+        !self.lens.is_identity()
+    }
+}
+
+fn sample_bilinear(src: &image::RgbaImage, x: f32, y: f32) -> Option<[f32; 4]> {
+    let (w, h) = src.dimensions();
+    if x < -0.5 || y < -0.5 || x > w as f32 - 0.5 || y > h as f32 - 0.5 {
+        return None;
+    }
+    let x = x.clamp(0.0, (w - 1) as f32);
+    let y = y.clamp(0.0, (h - 1) as f32);
+    let (x0, y0) = (x as u32, y as u32);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+    let (a, b, c, d) = (src.get_pixel(x0, y0), src.get_pixel(x1, y0), src.get_pixel(x0, y1), src.get_pixel(x1, y1));
+    let mut out = [0.0f32; 4];
+    for i in 0..4 {
+        let top = a[i] as f32 + (b[i] as f32 - a[i] as f32) * tx;
+        let bottom = c[i] as f32 + (d[i] as f32 - c[i] as f32) * tx;
+        out[i] = top + (bottom - top) * ty;
+    }
+    Some(out)
+}
+
+// A kép területén kívülre eső pontok átlátszók lesznek
+pub fn lens_correct(img: &mut image::RgbaImage, lens: &LensCorrection) {
+    let (width, height) = img.dimensions();
+    let source = if lens.has_geometry() { Some(img.clone()) } else { None };
+    let (cx, cy) = (width as f32 / 2.0, height as f32 / 2.0);
+    let radius = (cx * cx + cy * cy).sqrt();
+    let aspect = width as f32 / height as f32;
+    let ca = [1.0 + lens.ca_red / 100.0, 1.0, 1.0 + lens.ca_blue / 100.0];
+
+    img.par_chunks_mut(width as usize * 4).enumerate().for_each(|(y, row)| {
+        for x in 0..width as usize {
+            let px = &mut row[x * 4..x * 4 + 4];
+            let (fx, fy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+            if let Some(source) = &source {
+                let p = lens.distort([fx / radius, fy / radius]);
+                let mut out = [0.0f32; 4];
+                let mut inside = true;
+                for c in 0..3 {
+                    let sx = p[0] * ca[c] * radius + cx - 0.5;
+                    let sy = p[1] * ca[c] * radius + cy - 0.5;
+                    match sample_bilinear(source, sx, sy) {
+                        Some(s) => {
+                            out[c] = s[c];
+                            if c == 1 {
+                                out[3] = s[3];
+                            }
+                        }
+                        None => inside = false,
+                    }
+                }
+                if !inside {
+                    out = [0.0; 4];
+                }
+                for i in 0..4 {
+                    px[i] = (out[i] + 0.5).clamp(0.0, 255.0) as u8;
+                }
+            }
+            if lens.has_vignette() {
+                let factor = lens.vignette_factor([fx / cx, fy / cy], aspect);
+                for v in px.iter_mut().take(3) {
+                    *v = (*v as f32 * factor + 0.5).clamp(0.0, 255.0) as u8;
+                }
+            }
+        }
+    });
+}
+
+impl ImageViewer {
+    pub fn lens_panel(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        let gpu = self.gpu_interface.is_some();
+        let lens = &mut self.color_settings.lens;
+        let defaults = LensCorrection::default();
+        let rows: [(&mut f32, f32, std::ops::RangeInclusive<f32>, &str, &str); 7] = [
+            (&mut lens.k1, defaults.k1, -0.5..=0.5, "Distortion", "Negative: corrects barrel, positive: corrects pincushion"),
+            (&mut lens.k2, defaults.k2, -0.5..=0.5, "Distortion (edges)", "Higher order term, mostly affects the corners"),
+            (&mut lens.ca_red, defaults.ca_red, -1.0..=1.0, "Red/Cyan fringe", "Scale of the red channel in percent"),
+            (&mut lens.ca_blue, defaults.ca_blue, -1.0..=1.0, "Blue/Yellow fringe", "Scale of the blue channel in percent"),
+            (&mut lens.vignette_amount, defaults.vignette_amount, -1.0..=1.0, "Vignette", "Negative: darken corners, positive: remove lens vignetting"),
+            (&mut lens.vignette_midpoint, defaults.vignette_midpoint, 0.0..=1.0, "Midpoint", "Where the vignette starts"),
+            (&mut lens.vignette_roundness, defaults.vignette_roundness, -1.0..=1.0, "Roundness", "-1: rectangular, 0: follows the image, 1: circle"),
+        ];
+        for (value, default, range, name, hint) in rows {
+            ui.horizontal(|ui| {
+                let res = ui.add(egui::Slider::new(value, range).text(name)).on_hover_text(hint);
+                if gpu {
+                    *changed |= res.changed();
+                } else {
+                    *changed |= res.drag_stopped() || (res.changed() && !ui.input(|i| i.pointer.any_down()));
+                }
+                if ui.button("⟲").on_hover_text(format!("Reset {}", name)).clicked() {
+                    *value = default;
+                    *changed = true;
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pattern(width: u32, height: u32) -> image::RgbaImage {
+        image::RgbaImage::from_fn(width, height, |x, y| image::Rgba([(x * 9) as u8, (y * 13) as u8, ((x + y) * 5) as u8, 255]))
+    }
+
+    #[test]
+    fn zero_correction_keeps_image() {
+        let original = pattern(21, 15);
+        let mut img = original.clone();
+        lens_correct(&mut img, &LensCorrection::default());
+        assert_eq!(img, original);
+        // a középpont a torzításnál sem mozdul
+        let lens = LensCorrection { k1: -0.2, k2: 0.1, ..Default::default() };
+        lens_correct(&mut img, &lens);
+        assert_eq!(img.get_pixel(10, 7), original.get_pixel(10, 7));
+        assert_ne!(img.get_pixel(1, 1), original.get_pixel(1, 1));
+    }
+
+    #[test]
+    fn negative_vignette_darkens_corners() {
+        let mut img = image::RgbaImage::from_pixel(21, 15, image::Rgba([200, 200, 200, 255]));
+        let lens = LensCorrection { vignette_amount: -0.5, ..Default::default() };
+        lens_correct(&mut img, &lens);
+        assert_eq!(img.get_pixel(10, 7), &image::Rgba([200, 200, 200, 255]));
+        for (x, y) in [(0, 0), (20, 0), (0, 14), (20, 14)] {
+            let p = img.get_pixel(x, y);
+            assert!(p[0] < 150 && p[0] == p[1] && p[1] == p[2] && p[3] == 255, "{:?}", p);
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use serde::{Deserialize, Serialize};
+use crate::ImageViewer;
+
+// Szintek: bemeneti fekete/fehér pont és középtónus (gamma), kimeneti tartomány.
+// A levels[0] a közös (RGB), 1..3 a R, G, B csatornáé, mint a görbéknél.
+
+const AUTO_CLIP: f64 = 0.001; // az automatikus szinteknél ennyi pixel vágható le mindkét végén
+const CHANNEL_NAMES: [&str; 4] = ["RGB", "R", "G", "B"];
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct Levels {
+    pub black: f32,     // 0.0 .. 1.0
+    pub white: f32,     // 0.0 .. 1.0
+    pub gamma: f32,     // 0.1 .. 10.0, 1.0 felett világosabb középtónusok
+    pub out_black: f32, // 0.0 .. 1.0
+    pub out_white: f32, // 0.0 .. 1.0
+}
+
+impl Default for Levels {
+    fn default() -> Self {
+        Self { black: 0.0, white: 1.0, gamma: 1.0, out_black: 0.0, out_white: 1.0 }
+    }
+}
+
+impl Levels {
+    pub fn is_identity(&self) -> bool {
+        *self == Levels::default()
+    }
+
+    pub fn apply(&self, value: f32) -> f32 {
+        let range = (self.white - self.black).max(0.001);
+        let mut t = ((value - self.black) / range).clamp(0.0, 1.0);
+        if (self.gamma - 1.0).abs() > 0.001 {
+            t = t.powf(1.0 / self.gamma);
+        }
+        self.out_black + (self.out_white - self.out_black) * t
+    }
+
+    // A középső csúszka helye: ahol a kimenet félúton van
+    fn mid_point(&self) -> f32 {
+        self.black + (self.white - self.black) * 0.5f32.powf(self.gamma)
+    }
+
+    fn set_mid_point(&mut self, x: f32) {
+        let range = (self.white - self.black).max(0.001);
+        let t = ((x - self.black) / range).clamp(0.01, 0.99);
+        self.gamma = (t.ln() / 0.5f32.ln()).clamp(0.1, 10.0);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Eyedropper {
+    Black,
+    Gray,
+    White,
+    Neutral, // fehéregyensúly
+}
+
+// Alsó és felső vágási pont a hisztogramból
+fn clip_points(bins: &[u32]) -> Option<(f32, f32)> {
+    let total: u64 = bins.iter().map(|b| *b as u64).sum();
+    if total == 0 {
+        return None;
+    }
+    let clip = (total as f64 * AUTO_CLIP) as u64;
+    let mut sum = 0u64;
+    let lo = bins.iter().position(|b| {
+        sum += *b as u64;
+        sum > clip
+    })?;
+    sum = 0;
+    let hi = bins.len() - 1 - bins.iter().rev().position(|b| {
+        sum += *b as u64;
+        sum > clip
+    })?;
+    let last = (bins.len() - 1) as f32;
+    (hi > lo).then(|| (lo as f32 / last, hi as f32 / last))
+}
+
+// Csatornánkénti középtónus a szürke pipettához, hogy a három csatorna az átlagukra kerüljön
+fn gray_point_gammas(levels: &[Levels], v: [f32; 3]) -> [f32; 3] {
+    let t = [0, 1, 2].map(|c| {
+        let l = &levels[c + 1];
+        ((v[c] - l.black) / (l.white - l.black).max(0.001)).clamp(0.01, 0.99)
+    });
+    let target = (t[0] + t[1] + t[2]) / 3.0;
+    t.map(|t| (t.ln() / target.ln()).clamp(0.1, 10.0))
+}
+
+impl ImageViewer {
+    // A vágási pontok a szintek előtti állapot hisztogramjából (a görbék és a térbeli szűrők nélkül)
+    pub fn auto_levels(&mut self, per_channel: bool) {
+        self.color_settings.levels = Default::default();
+        let cs = self.color_settings.clone();
+        let Some(hist) = self.source_histogram(|color| cs.convert_before_tone(color)) else {
+            return;
+        };
+        if per_channel {
+            // csatornánként: a színeltolódást is kiegyenlíti
+            for c in 0..3 {
+                if let Some((lo, hi)) = clip_points(&hist[c * 256..c * 256 + 256]) {
+                    self.color_settings.levels[c + 1].black = lo;
+                    self.color_settings.levels[c + 1].white = hi;
+                }
+            }
+        } else {
+            // a három csatorna együtt: a színárnyalat marad
+            let combined: Vec<u32> = (0..256).map(|i| hist[i] + hist[256 + i] + hist[512 + i]).collect();
+            if let Some((lo, hi)) = clip_points(&combined) {
+                self.color_settings.levels[0].black = lo;
+                self.color_settings.levels[0].white = hi;
+            }
+        }
+    }
+
+    // A kattintott pont a korrekciók előtti képről, a szintek előtti állapotig számolva
+    pub fn apply_eyedropper(&mut self, ctx: &egui::Context, tool: Eyedropper, pixel_x: u32, pixel_y: u32) {
+        let Some(mut v) = self.pick_source(pixel_x, pixel_y) else {
+            return;
+        };
+        if tool == Eyedropper::Neutral {
+            self.neutralize_color([v[0], v[1], v[2]]);
+            self.review(ctx, true, false);
+            return;
+        }
+        self.color_settings.convert_before_tone(&mut v);
+        let levels = &mut self.color_settings.levels;
+        match tool {
+            Eyedropper::Black => {
+                for c in 0..3 {
+                    levels[c + 1].black = v[c].min(levels[c + 1].white - 0.01).max(0.0);
+                }
+            }
+            Eyedropper::White => {
+                for c in 0..3 {
+                    levels[c + 1].white = v[c].max(levels[c + 1].black + 0.01).min(1.0);
+                }
+            }
+            Eyedropper::Neutral => {}
+            Eyedropper::Gray => {
+                let gammas = gray_point_gammas(levels, [v[0], v[1], v[2]]);
+                for c in 0..3 {
+                    levels[c + 1].gamma = gammas[c];
+                }
+            }
+        }
+        self.review(ctx, true, false);
+    }
+
+    pub fn levels_panel(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        ui.horizontal(|ui| {
+            ui.label("Channel:");
+            for (i, name) in CHANNEL_NAMES.iter().enumerate() {
+                if ui.selectable_label(self.levels_channel == i, *name).clicked() {
+                    self.levels_channel = i;
+                }
+            }
+            ui.add_space(20.0);
+            if ui.button("⟲").on_hover_text("Reset Levels").clicked() {
+                self.color_settings.levels[self.levels_channel] = Levels::default();
+                *changed = true;
+            }
+            if ui.button("Reset All Levels").clicked() {
+                self.color_settings.levels = Default::default();
+                *changed = true;
+            }
+        });
+
+        // Hisztogram a bemeneti csúszkákkal
+        let channel = self.levels_channel;
+        let size = egui::vec2(ui.available_width().min(400.0), 60.0);
+        let (response, painter) = ui.allocate_painter(size, egui::Sense::click_and_drag());
+        let rect = response.rect;
+        let hist_rect = egui::Rect::from_min_max(rect.min, egui::pos2(rect.max.x, rect.max.y - 10.0));
+        painter.rect_filled(hist_rect, 2.0, egui::Color32::from_gray(30));
+        if self.hist.len() == 1024 {
+            let offset = match channel {
+                0 => 768,
+                c => (c - 1) * 256,
+            };
+            let bins = &self.hist[offset..offset + 256];
+            let max_val = bins.iter().copied().max().unwrap_or(0) as f32;
+            if max_val > 0.0 {
+                let bin_w = hist_rect.width() / 256.0;
+                for (i, v) in bins.iter().enumerate() {
+                    let h = (*v as f32 + 1.0).ln() / (max_val + 1.0).ln() * hist_rect.height();
+                    let x = hist_rect.left() + i as f32 * bin_w;
+                    painter.rect_filled(egui::Rect::from_min_max(egui::pos2(x, hist_rect.bottom() - h), egui::pos2(x + bin_w, hist_rect.bottom())), 0.0, egui::Color32::from_gray(120));
+                }
+            }
+        }
+        let levels = &mut self.color_settings.levels[channel];
+        let to_x = |v: f32| rect.left() + v * rect.width();
+        let handles = [levels.black, levels.mid_point(), levels.white];
+        let fills = [egui::Color32::BLACK, egui::Color32::GRAY, egui::Color32::WHITE];
+        for (v, fill) in handles.iter().zip(fills) {
+            let x = to_x(*v);
+            let tri = vec![egui::pos2(x, rect.bottom() - 10.0), egui::pos2(x - 5.0, rect.bottom()), egui::pos2(x + 5.0, rect.bottom())];
+            painter.add(egui::Shape::convex_polygon(tri, fill, egui::Stroke::new(1.0, egui::Color32::from_gray(160))));
+        }
+        let mut edited = false;
+        if let Some(pos) = response.interact_pointer_pos() {
+            let v = ((pos.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+            if response.drag_started() {
+                // a legközelebbi csúszkát fogjuk meg
+                let nearest = handles.iter()
+                    .enumerate()
+                    .min_by(|a, b| (a.1 - v).abs().total_cmp(&(b.1 - v).abs()))
+                    .map(|(i, _)| i);
+                self.levels_drag = nearest;
+            }
+            if response.dragged() {
+                match self.levels_drag {
+                    Some(0) => levels.black = v.min(levels.white - 0.01),
+                    Some(1) => levels.set_mid_point(v),
+                    Some(2) => levels.white = v.max(levels.black + 0.01),
+                    _ => {}
+                }
+                edited = true;
+            }
+        }
+        let released = response.drag_stopped();
+        if released {
+            self.levels_drag = None;
+        }
+
+        let mut value_changed = false;
+        let mut value_released = false;
+        let mut track = |res: egui::Response| {
+            value_changed |= res.changed();
+            value_released |= res.drag_stopped() || res.lost_focus();
+        };
+        ui.horizontal(|ui| {
+            ui.label("Input:");
+            let mut black = levels.black * 255.0;
+            let mut white = levels.white * 255.0;
+            let res = ui.add(egui::DragValue::new(&mut black).range(0.0..=254.0).max_decimals(0));
+            if res.changed() {
+                levels.black = (black / 255.0).min(levels.white - 0.01);
+            }
+            track(res);
+            track(ui.add(egui::DragValue::new(&mut levels.gamma).range(0.1..=10.0).speed(0.01).max_decimals(2)));
+            let res = ui.add(egui::DragValue::new(&mut white).range(1.0..=255.0).max_decimals(0));
+            if res.changed() {
+                levels.white = (white / 255.0).max(levels.black + 0.01);
+            }
+            track(res);
+            ui.add_space(20.0);
+            ui.label("Output:");
+            let mut out_black = levels.out_black * 255.0;
+            let mut out_white = levels.out_white * 255.0;
+            let res = ui.add(egui::DragValue::new(&mut out_black).range(0.0..=255.0).max_decimals(0));
+            if res.changed() {
+                levels.out_black = out_black / 255.0;
+            }
+            track(res);
+            let res = ui.add(egui::DragValue::new(&mut out_white).range(0.0..=255.0).max_decimals(0));
+            if res.changed() {
+                levels.out_white = out_white / 255.0;
+            }
+            track(res);
+        });
+
+        // CPU esetén csak elengedéskor számolunk újra, mint a csúszkáknál
+        if self.gpu_interface.is_none() {
+            if released || value_released || (value_changed && !ui.input(|i| i.pointer.any_down())) {
+                *changed = true;
+            }
+        } else if edited || released || value_changed {
+            *changed = true;
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Auto Levels").on_hover_text("Stretch each channel, also removes color casts").clicked() {
+                self.auto_levels(true);
+                *changed = true;
+            }
+            if ui.button("Auto Contrast").on_hover_text("Stretch all channels together, keeps the colors").clicked() {
+                self.auto_levels(false);
+                *changed = true;
+            }
+            ui.add_space(20.0);
+            for (tool, label, hint) in [
+                (Eyedropper::Black, "⚫", "Pick black point on the image"),
+                (Eyedropper::Gray, "◐", "Pick neutral gray on the image"),
+                (Eyedropper::White, "⚪", "Pick white point on the image"),
+            ] {
+                if ui.selectable_label(self.eyedropper == Some(tool), label).on_hover_text(hint).clicked() {
+                    self.eyedropper = if self.eyedropper == Some(tool) { None } else { Some(tool) };
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mid_point_round_trip() {
+        for gamma in [0.3, 1.0, 2.5] {
+            let levels = Levels { black: 0.1, white: 0.9, gamma, ..Default::default() };
+            let mut moved = levels;
+            moved.set_mid_point(levels.mid_point());
+            assert!((moved.gamma - gamma).abs() < 1e-4, "{} {}", gamma, moved.gamma);
+            assert!((levels.apply(levels.mid_point()) - 0.5).abs() < 1e-4);
+        }
+    }
+
+    // Mindkét végén legfeljebb 0.1% pixel esik a vágási pontokon kívülre
+    #[test]
+    fn clip_points_skip_outliers() {
+        let mut bins = vec![0u32; 256];
+        bins[3] = 50;
+        bins[250] = 60;
+        for b in &mut bins[10..=200] {
+            *b = 520;
+        }
+        // 99430 pixel: a vágás 99 pixel, a szélső 50 és 60 pixel elhagyható
+        assert_eq!(clip_points(&bins), Some((10.0 / 255.0, 200.0 / 255.0)));
+        bins[3] = 200;
+        assert_eq!(clip_points(&bins), Some((3.0 / 255.0, 200.0 / 255.0)));
+        assert_eq!(clip_points(&[0; 256]), None);
+        let mut single = vec![0u32; 256];
+        single[128] = 1000;
+        assert_eq!(clip_points(&single), None);
+    }
+
+    // A szürke pipetta gammáival a kiválasztott pont mindhárom csatornája ugyanoda kerül
+    #[test]
+    fn gray_point_equalizes_channels() {
+        let mut levels = [Levels::default(); 4];
+        levels[2].black = 0.05;
+        levels[3].white = 0.8;
+        let v = [0.62, 0.45, 0.3];
+        let gammas = gray_point_gammas(&levels, v);
+        let out: Vec<f32> = (0..3).map(|c| Levels { gamma: gammas[c], ..levels[c + 1] }.apply(v[c])).collect();
+        assert!((out[0] - out[1]).abs() < 1e-4 && (out[1] - out[2]).abs() < 1e-4, "{:?}", out);
+    }
+}
@@ -0,0 +1,244 @@
+use rayon::prelude::*;
+use crate::colors::{gaussian_weights, ColorSettings, Lut4ColorSettings};
+use crate::ImageViewer;
+
+// Térbeli (környezetfüggő) korrekciók: vibrancia, tisztaság (helyi kontraszt) és árnyék/csúcsfény visszahozás.
+// A LUT előtt futnak az élesítés után; a környezet világosságát nagy szigmájú Gauss-maggal becsüljük,
+// a szigma a kép méretéhez igazodik. A shaders.wgsl apply_effects ritkított mintavétellel számolja ugyanezt,
+// a CPU kicsinyített világosság képen dolgozik.
+
+pub const LOCAL_RADIUS: f32 = 0.01;     // a Gauss szigma a kép hosszabb oldalához képest
+const LOCAL_KERNEL_SIGMA: f32 = 1.5;    // szigma mintavételi lépésben (GPU), illetve kicsinyített pixelben (CPU)
+const LUMA: [f32; 3] = [0.299, 0.587, 0.114];
+
+pub fn local_sigma(width: u32, height: u32) -> f32 {
+    (width.max(height) as f32 * LOCAL_RADIUS).clamp(2.0, 64.0)
+}
+
+fn smoothstep(e0: f32, e1: f32, x: f32) -> f32 {
+    let t = ((x - e0) / (e1 - e0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// rgb és l: a pixel és világossága, blur: a környezet világossága (mind 0.0 .. 1.0)
+pub fn local_tone(rgb: [f32; 3], l: f32, blur: f32, vibrance: f32, clarity: f32, shadows: f32, highlights: f32) -> [f32; 3] {
+    let mut delta = 0.0;
+    if clarity != 0.0 {
+        // csak a középtónusokban, -1.0 a teljes elmosás
+        delta += clarity * (l - blur) * (1.0 - (2.0 * l - 1.0).powi(2));
+    }
+    if shadows != 0.0 {
+        delta += shadows * (1.0 - smoothstep(0.0, 0.5, blur)) * (1.0 - l) * l.max(0.0).sqrt();
+    }
+    if highlights != 0.0 {
+        delta += highlights * smoothstep(0.5, 1.0, blur) * 0.5 * l * l;
+    }
+    let mut out = rgb.map(|c| c + delta);
+    if vibrance != 0.0 {
+        // a kevésbé telített színek jobban változnak
+        let max = out[0].max(out[1]).max(out[2]);
+        let min = out[0].min(out[1]).min(out[2]);
+        let amount = vibrance * (1.0 - (max - min).clamp(0.0, 1.0)).powi(2);
+        let gray = out[0] * LUMA[0] + out[1] * LUMA[1] + out[2] * LUMA[2];
+        out = out.map(|c| gray + (c - gray) * (1.0 + amount));
+    }
+    out.map(|c| c.clamp(0.0, 1.0))
+}
+
+impl ColorSettings {
+    pub fn has_local_adjustments(&self) -> bool {
+        self.vibrance.abs() >= 0.001 || self.clarity.abs() >= 0.001 || self.shadows.abs() >= 0.001 || self.highlights.abs() >= 0.001
+    }
+}
+
+// CPU út: a világosság dobozszűrővel kicsinyítve és elmosva, pixelenként bilineárisan olvasva
+pub struct LocalAdjust {
+    blur: Vec<f32>,
+    width: usize, // a kicsinyített kép mérete
+    height: usize,
+    factor: u32,  // kicsinyítés mértéke
+    vibrance: f32,
+    clarity: f32,
+    shadows: f32,
+    highlights: f32,
+}
+
+impl LocalAdjust {
+    pub fn new(lut: &Lut4ColorSettings, img: &image::RgbaImage) -> Option<Self> {
+        if lut.vibrance == 0.0 && lut.clarity == 0.0 && lut.shadows == 0.0 && lut.highlights == 0.0 {
+            return None;
+        }
+        let (w, h) = img.dimensions();
+        let sigma = local_sigma(w, h);
+        let factor = ((sigma / LOCAL_KERNEL_SIGMA) as u32).max(1);
+        let (width, height) = (w.div_ceil(factor) as usize, h.div_ceil(factor) as usize);
+
+        let mut sum = vec![0.0f32; width * height];
+        let mut count = vec![0u32; width * height];
+        for (x, y, p) in img.enumerate_pixels() {
+            let idx = (y / factor) as usize * width + (x / factor) as usize;
+            sum[idx] += (p[0] as f32 * LUMA[0] + p[1] as f32 * LUMA[1] + p[2] as f32 * LUMA[2]) / 255.0;
+            count[idx] += 1;
+        }
+        let small: Vec<f32> = sum.iter().zip(&count).map(|(s, c)| s / (*c).max(1) as f32).collect();
+
+        let sigma_small = sigma / factor as f32;
+        let r = (sigma_small * 3.0).ceil() as i32;
+        let weights = gaussian_weights(r, sigma_small);
+        let d = (2 * r + 1) as usize;
+        let mut blur = vec![0.0f32; width * height];
+        blur.par_iter_mut().enumerate().for_each(|(idx, out)| {
+            let (cx, cy) = ((idx % width) as i32, (idx / width) as i32);
+            let mut acc = 0.0f32;
+            let mut total_w = 0.0f32;
+            for dy in -r..=r {
+                let py = (cy + dy).clamp(0, height as i32 - 1) as usize;
+                for dx in -r..=r {
+                    let px = (cx + dx).clamp(0, width as i32 - 1) as usize;
+                    let w = weights[(dy + r) as usize * d + (dx + r) as usize];
+                    acc += small[py * width + px] * w;
+                    total_w += w;
+                }
+            }
+            *out = acc / total_w;
+        });
+        Some(Self {
+            blur,
+            width,
+            height,
+            factor,
+            vibrance: lut.vibrance,
+            clarity: lut.clarity,
+            shadows: lut.shadows,
+            highlights: lut.highlights,
+        })
+    }
+
+    fn blurred_luma(&self, x: u32, y: u32) -> f32 {
+        let f = self.factor as f32;
+        let fx = ((x as f32 + 0.5) / f - 0.5).clamp(0.0, (self.width - 1) as f32);
+        let fy = ((y as f32 + 0.5) / f - 0.5).clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (fx as usize, fy as usize);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+        let at = |x: usize, y: usize| self.blur[y * self.width + x];
+        let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * tx;
+        let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * tx;
+        top + (bottom - top) * ty
+    }
+
+    pub fn apply_pixel(&self, x: u32, y: u32, pix: &mut image::Rgba<u8>) {
+        let rgb = [pix[0] as f32 / 255.0, pix[1] as f32 / 255.0, pix[2] as f32 / 255.0];
+        let l = rgb[0] * LUMA[0] + rgb[1] * LUMA[1] + rgb[2] * LUMA[2];
+        let out = local_tone(rgb, l, self.blurred_luma(x, y), self.vibrance, self.clarity, self.shadows, self.highlights);
+        for i in 0..3 {
+            pix[i] = (out[i] * 255.0 + 0.5) as u8;
+        }
+    }
+}
+
+impl ImageViewer {
+    pub fn local_adjust_panel(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        let gpu = self.gpu_interface.is_some();
+        let cs = &mut self.color_settings;
+        for (value, name, hint) in [
+            (&mut cs.vibrance, "Vibrance", "Saturation weighted toward muted colors"),
+            (&mut cs.clarity, "Clarity", "Local contrast in the midtones"),
+            (&mut cs.shadows, "Shadows", "Positive: lift dark areas"),
+            (&mut cs.highlights, "Highlights", "Negative: recover bright areas"),
+        ] {
+            ui.horizontal(|ui| {
+                let res = ui.add(egui::Slider::new(value, -1.0..=1.0).text(name)).on_hover_text(hint);
+                if gpu {
+                    *changed |= res.changed();
+                } else {
+                    *changed |= res.drag_stopped() || (res.changed() && !ui.input(|i| i.pointer.any_down()));
+                }
+                if ui.button("⟲").on_hover_text(format!("Reset {}", name)).clicked() {
+                    *value = 0.0;
+                    *changed = true;
+                }
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn luma(rgb: [f32; 3]) -> f32 {
+        rgb[0] * LUMA[0] + rgb[1] * LUMA[1] + rgb[2] * LUMA[2]
+    }
+
+    #[test]
+    fn zero_amounts_are_identity() {
+        for rgb in [[0.0, 0.0, 0.0], [0.8, 0.3, 0.1], [0.25, 0.5, 0.75], [1.0, 1.0, 1.0]] {
+            for blur in [0.0, 0.3, 0.9] {
+                assert_eq!(local_tone(rgb, luma(rgb), blur, 0.0, 0.0, 0.0, 0.0), rgb);
+            }
+        }
+    }
+
+    // Pozitív tisztaság a környezetétől való eltérést növeli, mindkét irányban
+    #[test]
+    fn clarity_increases_local_contrast() {
+        let rgb = [0.6, 0.5, 0.4];
+        let l = luma(rgb);
+        for blur in [0.3, 0.7] {
+            let out = local_tone(rgb, l, blur, 0.0, 0.5, 0.0, 0.0);
+            assert!((luma(out) - blur).abs() > (l - blur).abs(), "{} {:?}", blur, out);
+            assert_eq!((luma(out) - blur).signum(), (l - blur).signum());
+        }
+    }
+
+    #[test]
+    fn vibrance_keeps_grays() {
+        for v in [0.1, 0.5, 0.9] {
+            for vibrance in [-1.0, 1.0] {
+                let out = local_tone([v; 3], v, 0.5, vibrance, 0.0, 0.0, 0.0);
+                assert!(out.iter().all(|c| (c - v).abs() < 1e-5), "{:?}", out);
+            }
+        }
+        let out = local_tone([0.6, 0.5, 0.4], 0.51, 0.5, 1.0, 0.0, 0.0, 0.0);
+        assert!(out[0] - out[2] > 0.2);
+    }
+
+    // A shaders.wgsl local_adjust ritkított 9x9 mintavételének átirata
+    fn gpu_blur(img: &image::RgbaImage, x: i32, y: i32) -> f32 {
+        let (w, h) = img.dimensions();
+        let step = local_sigma(w, h) / LOCAL_KERNEL_SIGMA;
+        let (mut sum, mut total) = (0.0, 0.0);
+        for j in -4..=4 {
+            for i in -4..=4 {
+                let weight = (-((i * i + j * j) as f32) / (2.0 * LOCAL_KERNEL_SIGMA * LOCAL_KERNEL_SIGMA)).exp();
+                let sx = (x + (i as f32 * step).round() as i32).clamp(0, w as i32 - 1);
+                let sy = (y + (j as f32 * step).round() as i32).clamp(0, h as i32 - 1);
+                let p = img.get_pixel(sx as u32, sy as u32);
+                sum += luma([p[0] as f32 / 255.0, p[1] as f32 / 255.0, p[2] as f32 / 255.0]) * weight;
+                total += weight;
+            }
+        }
+        sum / total
+    }
+
+    // A CPU kicsinyített elmosása és a GPU ritkított mintavétele közel azonos környezeti világosságot ad
+    #[test]
+    fn cpu_blur_matches_gpu_sampling() {
+        let img = image::RgbaImage::from_fn(640, 400, |x, y| {
+            let v = 0.5 + 0.3 * (x as f32 / 23.0).sin() * (y as f32 / 31.0).cos() + if x > 320 { 0.15 } else { -0.15 };
+            let c = (v.clamp(0.0, 1.0) * 255.0) as u8;
+            image::Rgba([c, c / 2 + 60, 255 - c, 255])
+        });
+        let mut lut = Lut4ColorSettings::default();
+        lut.clarity = 0.5;
+        let local = LocalAdjust::new(&lut, &img).unwrap();
+        let mut worst = 0.0f32;
+        for y in (0..400).step_by(7) {
+            for x in (0..640).step_by(7) {
+                worst = worst.max((local.blurred_luma(x, y) - gpu_blur(&img, x as i32, y as i32)).abs());
+            }
+        }
+        assert!(worst < 0.02, "{}", worst);
+    }
+}
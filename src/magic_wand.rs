@@ -0,0 +1,212 @@
+use std::collections::VecDeque;
+use crate::colors::ColorSettings;
+use crate::ImageViewer;
+
+// Varázspálca: a kattintott ponttól összefüggő, a tűréshatáron belüli terület lesz átlátszó,
+// nem az egész képen minden hasonló szín. A maszk széle elmosható, és előnézetben megjeleníthető.
+// A maszk egyszer, a megjelenített feldolgozott (korrigált) képen készül; mentéskor ugyanezt méretezzük
+// a teljes felbontásra, mert kisebb felbontáson az összefüggőség más lehet. Animációnál az aktuális kocka maszkja.
+
+pub const MAX_FEATHER: f32 = 5.0; // %, a kép hosszabb oldalához képest
+const PREVIEW_COLOR: [f32; 3] = [255.0, 40.0, 40.0];
+const PREVIEW_OPACITY: f32 = 0.6;
+
+impl ColorSettings {
+    pub fn has_flood_mask(&self) -> bool {
+        self.use_transparency && self.transparency_flood && self.flood_seed.is_some()
+    }
+}
+
+pub struct FloodMask {
+    width: u32,
+    height: u32,
+    values: Vec<f32>,
+}
+
+impl FloodMask {
+    // Bilineárisan a cél méretre; azonos méretnél a saját értékei
+    fn sample(&self, x: u32, y: u32, width: u32, height: u32) -> f32 {
+        let (mw, mh) = (self.width as usize, self.height as usize);
+        if width == self.width && height == self.height {
+            return self.values[y as usize * mw + x as usize];
+        }
+        let fx = ((x as f32 + 0.5) * self.width as f32 / width as f32 - 0.5).clamp(0.0, (mw - 1) as f32);
+        let fy = ((y as f32 + 0.5) * self.height as f32 / height as f32 - 0.5).clamp(0.0, (mh - 1) as f32);
+        let (x0, y0) = (fx as usize, fy as usize);
+        let (x1, y1) = ((x0 + 1).min(mw - 1), (y0 + 1).min(mh - 1));
+        let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+        let at = |x: usize, y: usize| self.values[y * mw + x];
+        let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * tx;
+        let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * tx;
+        top + (bottom - top) * ty
+    }
+}
+
+// 1.0: megmarad, 0.0: átlátszó; a viszonyítási szín a kiindulópont színe
+fn flood_mask(img: &image::RgbaImage, seed: (u32, u32), tolerance: f32) -> Vec<f32> {
+    let (width, height) = img.dimensions();
+    let (w, h) = (width as usize, height as usize);
+    let mut mask = vec![1.0f32; w * h];
+    let reference = *img.get_pixel(seed.0, seed.1);
+    let max_dist = (tolerance * 441.0).max(0.5);
+    let similar = |p: &image::Rgba<u8>| {
+        let d = (0..3).map(|i| (p[i] as f32 - reference[i] as f32).powi(2)).sum::<f32>().sqrt();
+        d < max_dist
+    };
+    let mut queue = VecDeque::new();
+    queue.push_back((seed.0 as usize, seed.1 as usize));
+    mask[seed.1 as usize * w + seed.0 as usize] = 0.0;
+    while let Some((x, y)) = queue.pop_front() {
+        let neighbours = [
+            (x.wrapping_sub(1), y),
+            (x + 1, y),
+            (x, y.wrapping_sub(1)),
+            (x, y + 1),
+        ];
+        for (nx, ny) in neighbours {
+            if nx >= w || ny >= h || mask[ny * w + nx] == 0.0 {
+                continue;
+            }
+            if similar(img.get_pixel(nx as u32, ny as u32)) {
+                mask[ny * w + nx] = 0.0;
+                queue.push_back((nx, ny));
+            }
+        }
+    }
+    mask
+}
+
+// Kétszeri dobozszűrő sorokra és oszlopokra, közel Gauss
+fn feather_mask(mask: &mut [f32], width: usize, height: usize, radius: usize) {
+    if radius == 0 {
+        return;
+    }
+    let mut tmp = vec![0.0f32; mask.len()];
+    for _ in 0..2 {
+        for y in 0..height {
+            let row = &mask[y * width..(y + 1) * width];
+            let mut sum: f32 = (0..=radius).map(|i| row[i.min(width - 1)]).sum::<f32>() + row[0] * radius as f32;
+            for x in 0..width {
+                tmp[y * width + x] = sum / (2 * radius + 1) as f32;
+                sum += row[(x + radius + 1).min(width - 1)] - row[x.saturating_sub(radius)];
+            }
+        }
+        for x in 0..width {
+            let at = |y: usize| tmp[y.min(height - 1) * width + x];
+            let mut sum: f32 = (0..=radius).map(at).sum::<f32>() + at(0) * radius as f32;
+            for y in 0..height {
+                mask[y * width + x] = sum / (2 * radius + 1) as f32;
+                sum += at(y + radius + 1) - at(y.saturating_sub(radius));
+            }
+        }
+    }
+}
+
+// A megjelenített, már korrigált képen, a maszk alkalmazása előtt
+pub fn build_flood_mask(img: &image::RgbaImage, colset: &ColorSettings) -> Option<FloodMask> {
+    let seed = colset.flood_seed.filter(|_| colset.has_flood_mask())?;
+    let (width, height) = img.dimensions();
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let sx = ((seed[0] * width as f32) as u32).min(width - 1);
+    let sy = ((seed[1] * height as f32) as u32).min(height - 1);
+    let mut values = flood_mask(img, (sx, sy), colset.transparency_tolerance);
+    let radius = (colset.transparency_feather / 100.0 * width.max(height) as f32 / 2.0).round() as usize;
+    feather_mask(&mut values, width as usize, height as usize, radius);
+    Some(FloodMask { width, height, values })
+}
+
+// Az alfát a maszkkal szorozza; preview esetén a levágott rész pirosan, átlátszatlanul látszik
+pub fn apply_flood_mask(img: &mut image::RgbaImage, colset: &ColorSettings, preview: bool) {
+    let Some(mask) = colset.flood_mask.as_ref().filter(|_| colset.has_flood_mask()) else {
+        return;
+    };
+    let (width, height) = img.dimensions();
+    for (x, y, pixel) in img.enumerate_pixels_mut() {
+        let m = mask.sample(x, y, width, height);
+        if preview {
+            let t = (1.0 - m) * PREVIEW_OPACITY;
+            for i in 0..3 {
+                pixel[i] = (pixel[i] as f32 + (PREVIEW_COLOR[i] - pixel[i] as f32) * t) as u8;
+            }
+        } else {
+            pixel[3] = (pixel[3] as f32 * m + 0.5) as u8;
+        }
+    }
+}
+
+impl ImageViewer {
+    // A kattintott pont a megjelenített kép méretéhez képest (0.0 .. 1.0), így mentéskor a teljes felbontásra is jó
+    pub fn set_flood_seed(&mut self, pixel_x: u32, pixel_y: u32) {
+        let (pixel_x, pixel_y) = match self.vector_render_scale() {
+            Some(scale) => (pixel_x as f32 * scale, pixel_y as f32 * scale),
+            None => (pixel_x as f32, pixel_y as f32),
+        };
+        if let Some(img) = &self.rgba_image
+            && pixel_x < img.width() as f32 && pixel_y < img.height() as f32 {
+            self.color_settings.flood_seed = Some([(pixel_x + 0.5) / img.width() as f32, (pixel_y + 0.5) / img.height() as f32]);
+        }
+    }
+
+    pub fn magic_wand_panel(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        ui.horizontal(|ui| {
+            if ui.checkbox(&mut self.color_settings.transparency_flood, "Contiguous (magic wand)")
+                .on_hover_text("Only the area connected to the clicked point becomes transparent. Ctrl+click the image to set the point")
+                .changed() {
+                *changed = true;
+            }
+            if self.color_settings.transparency_flood {
+                if self.color_settings.flood_seed.is_none() {
+                    ui.label("Ctrl+click the image");
+                }
+                else if ui.button("Clear point").clicked() {
+                    self.color_settings.flood_seed = None;
+                    *changed = true;
+                }
+            }
+        });
+        if !self.color_settings.transparency_flood {
+            return;
+        }
+        ui.horizontal(|ui| {
+            // a maszk a CPU-n készül, ezért csak elengedéskor számolunk újra
+            let res = ui.add(egui::Slider::new(&mut self.color_settings.transparency_feather, 0.0..=MAX_FEATHER)
+                .text("Feather").suffix(" %"))
+                .on_hover_text("Soften the mask edge");
+            *changed |= res.drag_stopped() || (res.changed() && !ui.input(|i| i.pointer.any_down()));
+            if ui.button("⟲").on_hover_text("Reset Feather").clicked() {
+                self.color_settings.transparency_feather = 0.0;
+                *changed = true;
+            }
+            if ui.checkbox(&mut self.show_mask, "Show mask").on_hover_text("Mark the removed area in red").changed() {
+                *changed = true;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A kisebb előnézeten számolt maszk a teljes felbontáson ugyanazt a területet vágja ki
+    #[test]
+    fn mask_scales_to_full_resolution() {
+        let halves = |size: u32| image::RgbaImage::from_fn(size, size, |x, _| {
+            if x < size / 2 { image::Rgba([0, 0, 0, 255]) } else { image::Rgba([255, 255, 255, 255]) }
+        });
+        let colset = ColorSettings {
+            use_transparency: true,
+            transparency_flood: true,
+            flood_seed: Some([0.1, 0.5]),
+            ..Default::default()
+        };
+        let mask = build_flood_mask(&halves(4), &colset).unwrap();
+        let colset = ColorSettings { flood_mask: Some(std::sync::Arc::new(mask)), ..colset };
+        let mut full = halves(16);
+        apply_flood_mask(&mut full, &colset, false);
+        assert!((0..16).all(|y| full.get_pixel(0, y)[3] == 0 && full.get_pixel(15, y)[3] == 255));
+        assert!((0..16).all(|y| full.get_pixel(7, y)[3] < 255 && full.get_pixel(9, y)[3] > 0));
+    }
+}
@@ -11,6 +11,15 @@ Created by Ferenc Takács in 2026
 //mod exif;
 mod gpu_colors;
 mod colors;
+mod curves;
+mod levels;
+mod white_balance;
+mod hsl;
+mod local_adjust;
+mod denoise;
+mod lens;
+mod magic_wand;
+mod alpha;
 mod file_handlers;
 mod ui_elements;
 mod ui_shortcuts;
@@ -20,19 +29,70 @@ mod ui_dialogs;
 mod image_processing;
 mod exif_my;
 mod pf32;
+mod icc;
+mod pages;
+mod vector;
+mod exif_edit;
+mod xmp;
+mod makernote;
+mod ifd;
+mod metadata_export;
+mod map;
+mod geotag;
 use colors::*;
 use crate::image_processing::*;
 use crate::file_handlers::*;
 use crate::exif_my::*;
 use crate::pf32::Pf32;
+use crate::icc::IccProfile;
 use eframe::egui;
 use std::env;
 use std::fs;
 use std::path::PathBuf;
 use pollster;
 
+// Windows release build grafikus alkalmazás, a parancssori kimenethez a hívó konzoljára csatlakozunk
+#[cfg(windows)]
+fn attach_console() {
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_console() {}
+
 fn main() -> eframe::Result<()> {
     
+    let args: Vec<String> = env::args().collect();
+    // Grafikus felület nélkül: iview --export-metadata <mappa> <kimenet.csv|kimenet.jsonl>
+    if args.len() > 1 && args[1] == "--export-metadata" {
+        attach_console();
+        if args.len() != 4 {
+            eprintln!("Használat: iview --export-metadata <mappa> <kimenet.csv|kimenet.jsonl>");
+            std::process::exit(2);
+        }
+        let folder = PathBuf::from(&args[2]);
+        if !folder.is_dir() {
+            eprintln!("Nem létező mappa: {}", args[2]);
+            std::process::exit(2);
+        }
+        let images = metadata_export::folder_images(&folder);
+        match metadata_export::export_metadata(&images, &PathBuf::from(&args[3])) {
+            Ok(count) => println!("{} kép metaadata exportálva: {}", count, args[3]),
+            Err(e) => {
+                eprintln!("Metaadat export hiba: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return Ok(());
+    }
+
     let has_wgpu = pollster::block_on(check_wgpu_support());
     
     let renderer = if has_wgpu {
@@ -43,7 +103,6 @@ fn main() -> eframe::Result<()> {
         eframe::Renderer::Glow
     };
     
-    let args: Vec<String> = env::args().collect();
     let (start_image, clipboard) = if args.len() > 1 {
         // Ha van argumentum, azt útvonalként kezeljük
         (Some(PathBuf::from(&args[1])), false)
@@ -130,6 +189,7 @@ struct ImageViewer {
     pub original_image: Option<image::DynamicImage>,
     pub resized_image: Option<image::DynamicImage>,
     pub rgba_image: Option<image::ImageBuffer<image::Rgba<u8>, Vec<u8>>>,
+    pub source_image: Option<image::RgbaImage>, // a korrekciók előtti kép, ha a megjelenített eltér tőle
     pub image_size: Pf32, // beolvasott, és átméretezett kép mérete pixelben
     pub inner_size: Pf32,
     pub original_image_size: Pf32,  // beolvasott kép mérete pixelben
@@ -157,6 +217,14 @@ struct ImageViewer {
     pub last_frame_time: std::time::Instant,
     pub anim_data: Option<AnimatedImage>,
     pub show_original_only: bool,
+    pub curve_channel: usize,      // a görbe szerkesztőben kiválasztott csatorna
+    pub curve_drag: Option<usize>, // a húzott görbepont
+    pub levels_channel: usize,
+    pub levels_drag: Option<usize>, // 0: fekete, 1: közép, 2: fehér csúszka
+    pub hsl_mode: usize, // HSL keverő: 0: árnyalat, 1: telítettség, 2: világosság
+    pub eyedropper: Option<levels::Eyedropper>, // kattintásra a képből vesz mintát
+    pub show_mask: bool, // a varázspálca maszkjának előnézete, mentéskor nem számít
+    pub alpha_view: alpha::AlphaView, // az alfa csatorna megjelenítése, mentéskor nem számít
     pub gpu_interface : Option<gpu_colors::GpuInterface>,
     pub gpu_tried_init: bool,
     pub use_gpu: bool,
@@ -176,6 +244,17 @@ struct ImageViewer {
     pub show_rgb_histogram: bool,
     pub use_log_scale: bool,
     pub hist_texture: Option<egui::TextureHandle>,
+    pub icc_profile: Option<IccProfile>,     // a képbe ágyazott színprofil
+    pub display_profile: Option<IccProfile>, // monitor profil, ha nincs: sRGB
+    pub icc_transform: Option<std::sync::Arc<moxcms::Transform8BitExecutor>>,
+    pub color_management: bool,
+    pub svg: Option<vector::SvgDocument>, // vektoros kép, nagyításkor újrarajzoljuk
+    pub pdf: Option<vector::PdfFile>,     // PDF oldalai az animációs léptetővel
+    pub exif_editor: Option<exif_edit::ExifEditor>, // nyitott metaadat szerkesztő
+    pub xmp: Option<xmp::XmpIptc>, // XMP és IPTC adatok
+    pub metadata_filter: String,   // keresés az EXIF/XMP/IPTC részletekben
+    pub map_view: Option<map::MapView>, // nyitott térkép ablak
+    pub geotagger: Option<geotag::Geotagger>, // GPX geotag ablak
 }
 
 
@@ -199,6 +278,7 @@ impl Default for ImageViewer {
             original_image: None,
             resized_image: None,
             rgba_image: None,
+            source_image: None,
             image_size: (800.0, 600.0).into(),
             inner_size: (800.0, 600.0).into(),
             original_image_size: (800.0, 600.0).into(),
@@ -226,6 +306,14 @@ impl Default for ImageViewer {
             last_frame_time: std::time::Instant::now(),
             anim_data: None,
             show_original_only: false,
+            curve_channel: 0,
+            curve_drag: None,
+            levels_channel: 0,
+            levels_drag: None,
+            hsl_mode: 0,
+            eyedropper: None,
+            show_mask: false,
+            alpha_view: alpha::AlphaView::Normal,
             gpu_interface : None,
             gpu_tried_init: false,
             use_gpu: true,
@@ -245,6 +333,17 @@ impl Default for ImageViewer {
             show_rgb_histogram: true,
             use_log_scale: false,
             hist_texture: None,
+            icc_profile: None,
+            display_profile: None,
+            icc_transform: None,
+            color_management: true,
+            svg: None,
+            pdf: None,
+            exif_editor: None,
+            xmp: None,
+            metadata_filter: String::new(),
+            map_view: None,
+            geotagger: None,
         }
     }
 }
@@ -354,6 +453,8 @@ impl eframe::App for ImageViewer {
 
         self.draw_image_area(ctx);
 
+        self.refresh_vector(ctx);
+
     }
 
     fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
@@ -0,0 +1,397 @@
+use serde_json::{json, Value};
+
+use crate::ifd::{Ifd, IfdEntry, Reader};
+use crate::exif_my::{ExifBlock, ExifTagEntry};
+
+// Gyártói MakerNote dekódolás: objektív, exponálásszám, fókuszpont, képstílus / filmszimuláció.
+// A mezők "Gyártó.Mező" néven kerülnek az entry_data_vector-ba (pl. "Nikon.ShutterCount").
+
+fn text(entry: &IfdEntry) -> Option<String> {
+    let clean = entry.data.split(|&b| b == 0).next().unwrap_or(&[]);
+    let text = String::from_utf8_lossy(clean).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn u16s(entry: &IfdEntry, motorola: bool) -> Vec<u16> {
+    let r = Reader { buf: &entry.data, motorola };
+    (0..entry.data.len() / 2).filter_map(|i| r.u16(i * 2)).collect()
+}
+
+fn u32s(entry: &IfdEntry, motorola: bool) -> Vec<u32> {
+    let r = Reader { buf: &entry.data, motorola };
+    (0..entry.data.len() / 4).filter_map(|i| r.u32(i * 4)).collect()
+}
+
+fn rationals(entry: &IfdEntry, motorola: bool) -> Vec<f64> {
+    u32s(entry, motorola)
+        .chunks_exact(2)
+        .map(|c| if c[1] == 0 { 0.0 } else { c[0] as f64 / c[1] as f64 })
+        .collect()
+}
+
+// Egy könyvtár a base-től számított offsetekkel (a base a raw_exif-en belüli index)
+fn note_ifd(raw: &[u8], base: usize, dir: usize, motorola: bool) -> Ifd {
+    let Some(buf) = raw.get(base..) else {
+        return Ifd::default();
+    };
+    let reader = Reader { buf, motorola };
+    dir.checked_sub(base)
+        .and_then(|pos| reader.ifd(pos).ok())
+        .map(|(ifd, _)| ifd)
+        .unwrap_or_default()
+}
+
+// "18-55mm f/3.5-5.6" a gyújtótáv és rekesz határokból
+fn lens_range(min_f: f64, max_f: f64, min_a: f64, max_a: f64) -> Option<String> {
+    if min_f <= 0.0 {
+        return None;
+    }
+    let focal = if (max_f - min_f).abs() < 0.5 { format!("{:.0}mm", min_f) } else { format!("{:.0}-{:.0}mm", min_f, max_f) };
+    let aperture = if (max_a - min_a).abs() < 0.05 { format!("f/{:.1}", min_a) } else { format!("f/{:.1}-{:.1}", min_a, max_a) };
+    Some(format!("{} {}", focal, aperture))
+}
+
+// A bekapcsolt bitek sorszáma egy bitmaszkban (fókuszpontok)
+fn set_bits(words: &[u16], count: usize) -> Vec<usize> {
+    (0..count).filter(|i| words.get(i / 16).is_some_and(|w| w & (1 << (i % 16)) != 0)).collect()
+}
+
+const NIKON_AF_POINTS: [&str; 11] = [
+    "Center", "Top", "Bottom", "Mid-left", "Mid-right", "Upper-left",
+    "Upper-right", "Lower-left", "Lower-right", "Far Left", "Far Right",
+];
+
+fn canon_picture_style(v: u16) -> String {
+    match v {
+        0x00 => "None",
+        0x01 | 0x81 => "Standard",
+        0x02 | 0x82 => "Portrait",
+        0x03 | 0x83 => "Landscape",
+        0x04 | 0x84 => "Neutral",
+        0x05 | 0x85 => "Faithful",
+        0x06 | 0x86 => "Monochrome",
+        0x87 => "Auto",
+        0x88 => "Fine Detail",
+        0x21 => "User Def. 1",
+        0x22 => "User Def. 2",
+        0x23 => "User Def. 3",
+        0x41 => "PC 1",
+        0x42 => "PC 2",
+        0x43 => "PC 3",
+        _ => return format!("Unknown ({:#x})", v),
+    }
+    .to_string()
+}
+
+fn fuji_film_mode(v: u16) -> String {
+    match v {
+        0x000 => "Provia / Standard",
+        0x100 | 0x110 | 0x120 | 0x130 => "Studio Portrait",
+        0x200 | 0x400 => "Velvia / Vivid",
+        0x300 => "Astia / Soft",
+        0x500 => "Pro Neg. Std",
+        0x501 => "Pro Neg. Hi",
+        0x600 => "Classic Chrome",
+        0x700 => "Eterna",
+        0x800 => "Classic Negative",
+        0x900 => "Eterna Bleach Bypass",
+        0xA00 => "Nostalgic Neg",
+        0xB00 => "Reala ACE",
+        _ => return format!("Unknown ({:#x})", v),
+    }
+    .to_string()
+}
+
+// Fekete-fehér szimulációk a Saturation tag-ben
+fn fuji_monochrome(v: u16) -> Option<&'static str> {
+    Some(match v {
+        0x300 => "Monochrome",
+        0x301 => "Monochrome + R Filter",
+        0x302 => "Monochrome + Ye Filter",
+        0x303 => "Monochrome + G Filter",
+        0x310 => "Sepia",
+        0x500 => "Acros",
+        0x501 => "Acros + R Filter",
+        0x502 => "Acros + Ye Filter",
+        0x503 => "Acros + G Filter",
+        _ => return None,
+    })
+}
+
+fn olympus_picture_mode(v: u16) -> String {
+    match v {
+        1 => "Vivid",
+        2 => "Natural",
+        3 => "Muted",
+        4 => "Portrait",
+        5 => "i-Enhance",
+        6 => "e-Portrait",
+        7 => "Color Creator",
+        8 => "Underwater",
+        9 => "Color Profile 1",
+        10 => "Color Profile 2",
+        11 => "Color Profile 3",
+        12 => "Monochrome Profile 1",
+        13 => "Monochrome Profile 2",
+        14 => "Monochrome Profile 3",
+        256 => "Monotone",
+        512 => "Sepia",
+        _ => return format!("Unknown ({})", v),
+    }
+    .to_string()
+}
+
+fn panasonic_photo_style(v: u16) -> String {
+    match v {
+        0 => "Auto",
+        1 => "Standard or Custom",
+        2 => "Vivid",
+        3 => "Natural",
+        4 => "Monochrome",
+        5 => "Scenery",
+        6 => "Portrait",
+        8 => "Cinelike D",
+        9 => "Cinelike V",
+        11 => "L. Monochrome",
+        12 => "Like709",
+        15 => "L. Monochrome D",
+        17 => "V-Log",
+        18 => "Cinelike D2",
+        _ => return format!("Unknown ({})", v),
+    }
+    .to_string()
+}
+
+// A Sony 0x9050-es blokk egyszerű helyettesítéses titkosítása: c = b^3 mod 249
+fn sony_decipher(data: &[u8]) -> Vec<u8> {
+    let mut table = [0u8; 256];
+    for (i, t) in table.iter_mut().enumerate() {
+        *t = i as u8;
+    }
+    for b in 0..249u32 {
+        table[(b * b * b % 249) as usize] = b as u8;
+    }
+    data.iter().map(|&c| table[c as usize]).collect()
+}
+
+impl ExifBlock {
+
+    fn push_note_field(&mut self, name: String, value: Value, offset: usize) {
+        let typ = if value.is_string() { "STRING" } else { "ULONG" };
+        let count = value.as_str().map_or(1, |s| s.len());
+        let json = json!({ "type": typ, "count": count, "val": value });
+        self.entry_data_vector.push(ExifTagEntry { name, value: json, offset });
+    }
+
+    // valueptr: a MakerNote kezdete a raw_exif-ben; a TIFF fejléc a 6. bájtnál van
+    pub fn process_maker_note(&mut self, valueptr: usize, bytecount: usize) {
+        let Some(note) = self.raw_exif.get(valueptr..valueptr.saturating_add(bytecount)).map(|n| n.to_vec()) else {
+            return;
+        };
+        let make = self.make.to_uppercase();
+        let (maker, fields) = if make.starts_with("CANON") {
+            ("Canon", self.canon_note(valueptr))
+        } else if make.starts_with("NIKON") {
+            ("Nikon", self.nikon_note(&note, valueptr))
+        } else if make.starts_with("SONY") {
+            ("Sony", self.sony_note(&note, valueptr))
+        } else if make.starts_with("FUJIFILM") {
+            ("Fujifilm", Self::fuji_note(&note))
+        } else if make.starts_with("OLYMPUS") || make.starts_with("OM DIGITAL") {
+            ("Olympus", self.olympus_note(&note, valueptr))
+        } else if make.starts_with("PANASONIC") {
+            ("Panasonic", self.panasonic_note(&note, valueptr))
+        } else {
+            return;
+        };
+        for (field, value) in fields {
+            self.push_note_field(format!("{}.{}", maker, field), value, valueptr);
+        }
+    }
+
+    // Canon: fejléc nélküli IFD, a fő TIFF fejléchez képesti offsetekkel.
+    // Exponálásszámot a Canon a JPEG MakerNote-ban nem tárol megbízhatóan, ezért nem adjuk.
+    fn canon_note(&self, valueptr: usize) -> Vec<(&'static str, Value)> {
+        let m = self.motorola_order;
+        let ifd = note_ifd(&self.raw_exif, 6, valueptr, m);
+        let mut fields = Vec::new();
+        if let Some(lens) = ifd.get(0x0095).and_then(text) {
+            fields.push(("LensModel", json!(lens)));
+        }
+        if let Some(serial) = ifd.get(0x000C).and_then(|e| u32s(e, m).first().copied()) {
+            fields.push(("SerialNumber", json!(serial)));
+        }
+        // AFInfo2: méret, mód, pontok száma, érvényes pontok, 4 méret, 4 tömb, majd a fókuszban lévők bitmaszkja
+        if let Some(af) = ifd.get(0x0026).map(|e| u16s(e, m)) {
+            let points = af.get(2).copied().unwrap_or(0) as usize;
+            let start = 8 + 4 * points;
+            let words = points.div_ceil(16);
+            if let Some(mask) = af.get(start..start + words) {
+                let in_focus: Vec<String> = set_bits(mask, points).iter().map(|p| p.to_string()).collect();
+                if !in_focus.is_empty() {
+                    fields.push(("FocusPoint", json!(in_focus.join(", "))));
+                }
+            }
+        }
+        // Processing (0x00A0), 10. elem: PictureStyle
+        if let Some(style) = ifd.get(0x00A0).and_then(|e| u16s(e, m).get(10).copied()) {
+            fields.push(("PictureStyle", json!(canon_picture_style(style))));
+        }
+        fields
+    }
+
+    // Nikon 3-as típus: "Nikon\0" + verzió, majd saját TIFF fejléc a 10. bájttól, ahhoz képesti offsetekkel
+    fn nikon_note(&self, note: &[u8], valueptr: usize) -> Vec<(&'static str, Value)> {
+        let (ifd, m) = if note.starts_with(b"Nikon\0\x02") {
+            let base = valueptr + 10;
+            let m = note.get(10..12) == Some(b"MM");
+            let header = Reader { buf: note.get(10..).unwrap_or_default(), motorola: m };
+            let dir = header.u32(4).unwrap_or(8) as usize;
+            (note_ifd(&self.raw_exif, base, base + dir, m), m)
+        } else if note.starts_with(b"Nikon\0") {
+            (note_ifd(&self.raw_exif, 6, valueptr + 8, self.motorola_order), self.motorola_order)
+        } else {
+            (note_ifd(&self.raw_exif, 6, valueptr, self.motorola_order), self.motorola_order)
+        };
+        let mut fields = Vec::new();
+        if let Some(count) = ifd.get(0x00A7).and_then(|e| u32s(e, m).first().copied()) {
+            fields.push(("ShutterCount", json!(count)));
+        }
+        let lens = ifd.get(0x0084).map(|e| rationals(e, m)).filter(|v| v.len() == 4);
+        if let Some(lens) = lens.and_then(|l| lens_range(l[0], l[1], l[2], l[3])) {
+            fields.push(("LensModel", json!(lens)));
+        }
+        if let Some(serial) = ifd.get(0x001D).and_then(text) {
+            fields.push(("SerialNumber", json!(serial)));
+        }
+        // AFInfo: mód, kiválasztott pont, fókuszban lévő pontok
+        if let Some(point) = ifd.get(0x0088).and_then(|e| e.data.get(1).copied()) {
+            let name = NIKON_AF_POINTS.get(point as usize).map_or_else(|| format!("Point {}", point), |n| n.to_string());
+            fields.push(("FocusPoint", json!(name)));
+        }
+        // PictureControlData: 4 bájt verzió, majd 20 bájtos név
+        if let Some(name) = ifd.get(0x0023).and_then(|e| e.data.get(4..24)) {
+            let name = String::from_utf8_lossy(name.split(|&b| b == 0).next().unwrap_or_default()).trim().to_string();
+            if !name.is_empty() {
+                fields.push(("PictureStyle", json!(name)));
+            }
+        }
+        fields
+    }
+
+    // Sony: "SONY DSC \0\0\0" (vagy CAM/MOBILE) fejléc után IFD, a fő TIFF fejléchez képesti offsetekkel
+    fn sony_note(&self, note: &[u8], valueptr: usize) -> Vec<(&'static str, Value)> {
+        let m = self.motorola_order;
+        let dir = if note.starts_with(b"SONY") { valueptr + 12 } else { valueptr };
+        let ifd = note_ifd(&self.raw_exif, 6, dir, m);
+        let mut fields = Vec::new();
+        if let Some(lens) = ifd.get(0xB027).and_then(|e| u32s(e, m).first().copied()) {
+            fields.push(("LensType", json!(lens))); // Sony objektív azonosító
+        }
+        if let Some(style) = ifd.get(0xB020).and_then(text) {
+            fields.push(("PictureStyle", json!(style)));
+        }
+        if let Some(pos) = ifd.get(0x201D).map(|e| u16s(e, m)).filter(|p| p.len() == 2) {
+            fields.push(("FocusPoint", json!(format!("{}, {}", pos[0], pos[1]))));
+        }
+        // 0x9050 titkosított blokk; az újabb (ILCE) vázaknál 0x3a-n 24 bites exponálásszám
+        if let Some(block) = ifd.get(0x9050).map(|e| sony_decipher(&e.data)) {
+            let reader = Reader { buf: &block, motorola: false };
+            if let Some(count) = reader.u32(0x3A).map(|c| c & 0x00FF_FFFF).filter(|c| *c > 0) {
+                fields.push(("ShutterCount", json!(count)));
+            }
+        }
+        fields
+    }
+
+    // Fujifilm: "FUJIFILM" + 32 bites IFD offset; mindig Intel sorrend, a MakerNote elejéhez képesti offsetek
+    fn fuji_note(note: &[u8]) -> Vec<(&'static str, Value)> {
+        if !note.starts_with(b"FUJIFILM") {
+            return Vec::new();
+        }
+        let reader = Reader { buf: note, motorola: false };
+        let Some((ifd, _)) = reader.u32(8).and_then(|dir| reader.ifd(dir as usize).ok()) else {
+            return Vec::new();
+        };
+        let mut fields = Vec::new();
+        let film = ifd.get(0x1401).and_then(|e| u16s(e, false).first().copied()).map(fuji_film_mode);
+        let mono = ifd.get(0x1003).and_then(|e| u16s(e, false).first().copied()).and_then(fuji_monochrome);
+        if let Some(film) = mono.map(|m| m.to_string()).or(film) {
+            fields.push(("FilmSimulation", json!(film)));
+        }
+        if let Some(count) = ifd.get(0x1438).and_then(|e| u16s(e, false).first().copied()) {
+            fields.push(("ShutterCount", json!(count & 0x7FFF))); // ImageCount
+        }
+        if let Some(pos) = ifd.get(0x1023).map(|e| u16s(e, false)).filter(|p| p.len() == 2) {
+            fields.push(("FocusPoint", json!(format!("{}, {}", pos[0], pos[1]))));
+        }
+        let lens: Vec<f64> = (0x1404..=0x1407)
+            .filter_map(|tag| ifd.get(tag).and_then(|e| rationals(e, false).first().copied()))
+            .collect();
+        if let Some(lens) = (lens.len() == 4).then(|| lens_range(lens[0], lens[1], lens[2], lens[3])).flatten() {
+            fields.push(("LensModel", json!(lens)));
+        }
+        fields
+    }
+
+    // Olympus/OM System: az új típusnál a MakerNote elejéhez képesti offsetek, Equipment és CameraSettings alkönyvtárral
+    fn olympus_note(&self, note: &[u8], valueptr: usize) -> Vec<(&'static str, Value)> {
+        let (base, dir, m) = if note.starts_with(b"OLYMPUS\0") {
+            (valueptr, valueptr + 12, note.get(8..10) == Some(b"MM"))
+        } else if note.starts_with(b"OM SYSTEM\0") {
+            (valueptr, valueptr + 16, note.get(12..14) == Some(b"MM"))
+        } else if note.starts_with(b"OLYMP\0") {
+            (6, valueptr + 8, self.motorola_order)
+        } else {
+            return Vec::new();
+        };
+        let ifd = note_ifd(&self.raw_exif, base, dir, m);
+        let sub = |tag: u16| {
+            ifd.get(tag)
+                .and_then(|e| Reader { buf: &e.data, motorola: m }.u32(0))
+                .map(|offset| note_ifd(&self.raw_exif, base, base + offset as usize, m))
+                .unwrap_or_default()
+        };
+        let equipment = sub(0x2010);
+        let settings = sub(0x2020);
+        let mut fields = Vec::new();
+        if let Some(lens) = equipment.get(0x0203).and_then(text) {
+            fields.push(("LensModel", json!(lens)));
+        }
+        if let Some(serial) = equipment.get(0x0101).and_then(text) {
+            fields.push(("SerialNumber", json!(serial)));
+        }
+        // AFPointSelected: a kép méretéhez viszonyított x, y
+        if let Some(pos) = settings.get(0x0308).map(|e| rationals(e, m)).filter(|p| p.len() >= 2) {
+            fields.push(("FocusPoint", json!(format!("{:.2}, {:.2}", pos[0], pos[1]))));
+        }
+        if let Some(mode) = settings.get(0x0520).and_then(|e| u16s(e, m).first().copied()) {
+            fields.push(("PictureStyle", json!(olympus_picture_mode(mode))));
+        }
+        fields
+    }
+
+    // Panasonic: "Panasonic\0\0\0" után IFD, a fő TIFF fejléchez képesti offsetekkel
+    fn panasonic_note(&self, note: &[u8], valueptr: usize) -> Vec<(&'static str, Value)> {
+        if !note.starts_with(b"Panasonic\0") {
+            return Vec::new();
+        }
+        let m = self.motorola_order;
+        let ifd = note_ifd(&self.raw_exif, 6, valueptr + 12, m);
+        let mut fields = Vec::new();
+        if let Some(lens) = ifd.get(0x0051).and_then(text) {
+            fields.push(("LensModel", json!(lens)));
+        }
+        if let Some(serial) = ifd.get(0x0052).and_then(text) {
+            fields.push(("LensSerialNumber", json!(serial)));
+        }
+        // AFPointPosition: a kép méretéhez viszonyított x, y
+        if let Some(pos) = ifd.get(0x004D).map(|e| rationals(e, m)).filter(|p| p.len() == 2) {
+            fields.push(("FocusPoint", json!(format!("{:.2}, {:.2}", pos[0], pos[1]))));
+        }
+        if let Some(style) = ifd.get(0x0089).and_then(|e| u16s(e, m).first().copied()) {
+            fields.push(("PictureStyle", json!(panasonic_photo_style(style))));
+        }
+        fields
+    }
+}
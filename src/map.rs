@@ -0,0 +1,518 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+use directories::ProjectDirs;
+use rayon::prelude::*;
+
+use crate::exif_my::ExifBlock;
+use crate::file_handlers::format_from_extension;
+use crate::ImageViewer;
+
+// Offline térkép: csempék helyi könyvtárakból a szokásos {z}/{x}/{y}.png elrendezésben, Web Mercator vetületben.
+// Hiányzó csempénél a kisebb nagyítású szülő csempe nagyított részletét rajzoljuk.
+
+const TILE_SIZE: f64 = 256.0;
+const MAX_ZOOM: u8 = 19;
+const TILE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+const MAX_CACHED_TILES: usize = 512;
+const MAX_LATITUDE: f64 = 85.05112878; // a Web Mercator határa
+
+// A jelölők helye fájlonként a módosítás idejével: mappaváltáskor és geotagelés után csak az új vagy megváltozott fájlt olvassuk
+type PinCache = HashMap<PathBuf, (SystemTime, Option<(f64, f64)>)>;
+static PIN_CACHE: LazyLock<Mutex<PinCache>> = LazyLock::new(Default::default);
+
+// Külső térkép a böngészőben: {lat} és {lon} helyére kerül a koordináta
+pub const MAP_PROVIDERS: [(&str, &str); 4] = [
+    ("Google Maps", "https://www.google.com/maps/place/{lat},{lon}"),
+    ("OpenStreetMap", "https://www.openstreetmap.org/?mlat={lat}&mlon={lon}#map=16/{lat}/{lon}"),
+    ("Bing Maps", "https://www.bing.com/maps?cp={lat}~{lon}&lvl=16&sp=point.{lat}_{lon}"),
+    ("geo: URI", "geo:{lat},{lon}"),
+];
+
+pub struct MapPin {
+    pub path: PathBuf,
+    pub name: String,
+    pub lat: f64,
+    pub lon: f64,
+}
+
+pub struct MapView {
+    pub center: (f64, f64), // szélesség, hosszúság
+    pub zoom: u8,
+    pub folder: bool,       // a mappa minden geotag-elt képe, különben csak az aktuális
+    pub pins: Vec<MapPin>,
+    pub pins_folder: Option<PathBuf>,
+    pins_loading: Option<Receiver<Vec<MapPin>>>, // a háttérszál eredménye
+    pub current: Option<(f64, f64)>, // az aktuális kép helye
+    followed: Option<(f64, f64)>,    // képváltáskor ide igazítjuk a térképet
+    fit_pending: bool,               // a terület méretét csak rajzoláskor ismerjük
+    scroll: f32,
+    tiles: HashMap<(u8, u32, u32), Option<egui::TextureHandle>>,
+    tile_dirs: Vec<PathBuf>,
+}
+
+// Világkoordináta a 0..1 tartományban
+fn project(lat: f64, lon: f64) -> (f64, f64) {
+    let lat = lat.clamp(-MAX_LATITUDE, MAX_LATITUDE).to_radians();
+    let x = (lon + 180.0) / 360.0;
+    let y = (1.0 - (lat.tan() + 1.0 / lat.cos()).ln() / std::f64::consts::PI) / 2.0;
+    (x, y)
+}
+
+fn unproject(x: f64, y: f64) -> (f64, f64) {
+    let lon = x.rem_euclid(1.0) * 360.0 - 180.0;
+    let lat = (std::f64::consts::PI * (1.0 - 2.0 * y.clamp(0.0, 1.0))).sinh().atan().to_degrees();
+    (lat, lon)
+}
+
+fn world_size(zoom: u8) -> f64 {
+    TILE_SIZE * (1u32 << zoom) as f64
+}
+
+// Saját könyvtár (beállítás), a gyorsítótár, végül a program mellé csomagolt "tiles"
+pub fn tile_dirs(custom: &Option<PathBuf>) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = custom {
+        dirs.push(dir.clone());
+    }
+    if let Some(dir) = tile_cache_dir() {
+        dirs.push(dir);
+    }
+    if let Some(exe_dir) = std::env::current_exe().ok().and_then(|p| p.parent().map(|d| d.to_path_buf())) {
+        dirs.push(exe_dir.join("tiles"));
+    }
+    dirs
+}
+
+pub fn map_url(template: &str, lat: f64, lon: f64) -> String {
+    template.replace("{lat}", &format!("{:.6}", lat)).replace("{lon}", &format!("{:.6}", lon))
+}
+
+pub fn map_provider_name(template: &str) -> &'static str {
+    MAP_PROVIDERS.iter().find(|(_, t)| *t == template).map_or("Custom", |(name, _)| name)
+}
+
+// 47°29'52.48"N 19°2'24.85"E
+pub fn format_dms(lat: f64, lon: f64) -> String {
+    // századmásodpercre kerekítve bontjuk, hogy a kerekítés átvigyen a percbe és a fokba (nincs 60.00")
+    let dms = |v: f64, pos: char, neg: char| {
+        let cs = (v.abs() * 360_000.0).round() as u64;
+        let (deg, min, sec) = (cs / 360_000, cs / 6000 % 60, cs % 6000);
+        format!("{}°{}'{}.{:02}\"{}", deg, min, sec / 100, sec % 100, if v < 0.0 { neg } else { pos })
+    };
+    format!("{} {}", dms(lat, 'N', 'S'), dms(lon, 'E', 'W'))
+}
+
+pub fn tile_cache_dir() -> Option<PathBuf> {
+    ProjectDirs::from("com", "iview", "iview-rust").map(|p| p.cache_dir().join("tiles"))
+}
+
+impl MapView {
+    pub fn new(current: Option<(f64, f64)>, folder: bool, tile_dirs: Vec<PathBuf>) -> Self {
+        Self {
+            center: current.unwrap_or((20.0, 0.0)),
+            zoom: if current.is_some() { 13 } else { 2 },
+            folder,
+            pins: Vec::new(),
+            pins_folder: None,
+            pins_loading: None,
+            current,
+            followed: current,
+            fit_pending: folder,
+            scroll: 0.0,
+            tiles: HashMap::new(),
+            tile_dirs,
+        }
+    }
+
+    pub fn set_tile_dirs(&mut self, dirs: Vec<PathBuf>) {
+        self.tile_dirs = dirs;
+        self.tiles.clear();
+    }
+
+    pub fn fit(&mut self) {
+        self.fit_pending = true;
+    }
+
+    pub fn zoom_by(&mut self, step: i32) {
+        self.zoom_at(step, egui::Vec2::ZERO);
+    }
+
+    // A mutató alatti pont helyben marad
+    fn zoom_at(&mut self, step: i32, offset: egui::Vec2) {
+        let zoom = (self.zoom as i32 + step).clamp(0, MAX_ZOOM as i32) as u8;
+        let (cx, cy) = project(self.center.0, self.center.1);
+        let (world, new_world) = (world_size(self.zoom), world_size(zoom));
+        let (px, py) = (cx + offset.x as f64 / world, cy + offset.y as f64 / world);
+        self.center = unproject(px - offset.x as f64 / new_world, py - offset.y as f64 / new_world);
+        self.zoom = zoom;
+    }
+
+    // Az összes jelölő (és az aktuális kép) beleférjen a területbe
+    fn fit_to(&mut self, size: egui::Vec2) {
+        let points: Vec<(f64, f64)> = self.pins.iter().map(|p| (p.lat, p.lon)).chain(self.current).collect();
+        let Some(&first) = points.first() else {
+            return;
+        };
+        let projected: Vec<(f64, f64)> = points.iter().map(|&(lat, lon)| project(lat, lon)).collect();
+        let (mut x0, mut y0, mut x1, mut y1) = (1.0f64, 1.0f64, 0.0f64, 0.0f64);
+        for (x, y) in &projected {
+            x0 = x0.min(*x);
+            y0 = y0.min(*y);
+            x1 = x1.max(*x);
+            y1 = y1.max(*y);
+        }
+        if points.len() == 1 {
+            self.center = first;
+            self.zoom = 13;
+            return;
+        }
+        self.center = unproject((x0 + x1) / 2.0, (y0 + y1) / 2.0);
+        let margin = 0.8; // a szélén maradjon hely a jelölőknek
+        self.zoom = (0..=MAX_ZOOM).rev()
+            .find(|z| {
+                let world = world_size(*z);
+                (x1 - x0) * world <= size.x as f64 * margin && (y1 - y0) * world <= size.y as f64 * margin
+            })
+            .unwrap_or(0)
+            .min(16);
+    }
+
+    fn load_tile(&mut self, ctx: &egui::Context, z: u8, x: u32, y: u32) -> Option<egui::TextureHandle> {
+        if let Some(texture) = self.tiles.get(&(z, x, y)) {
+            return texture.clone();
+        }
+        if self.tiles.len() > MAX_CACHED_TILES {
+            self.tiles.clear();
+        }
+        let path = self.tile_dirs.iter()
+            .flat_map(|dir| TILE_EXTENSIONS.iter().map(move |ext| dir.join(format!("{}/{}/{}.{}", z, x, y, ext))))
+            .find(|p| p.is_file());
+        let texture = path.and_then(|p| image::open(&p).ok()).map(|img| {
+            let rgba = img.to_rgba8();
+            let size = [rgba.width() as usize, rgba.height() as usize];
+            let color_image = egui::ColorImage::from_rgba_unmultiplied(size, rgba.as_raw());
+            ctx.load_texture(format!("tile_{}_{}_{}", z, x, y), color_image, egui::TextureOptions::LINEAR)
+        });
+        self.tiles.insert((z, x, y), texture.clone());
+        texture
+    }
+
+    // A csempe, vagy a legközelebbi meglévő szülő csempe megfelelő részlete (uv)
+    fn tile_texture(&mut self, ctx: &egui::Context, z: u8, x: u32, y: u32) -> Option<(egui::TextureHandle, egui::Rect)> {
+        for d in 0..=z {
+            if let Some(texture) = self.load_tile(ctx, z - d, x >> d, y >> d) {
+                let n = (1u32 << d) as f32;
+                let (sx, sy) = ((x - ((x >> d) << d)) as f32, (y - ((y >> d) << d)) as f32);
+                let uv = egui::Rect::from_min_size(egui::pos2(sx / n, sy / n), egui::vec2(1.0 / n, 1.0 / n));
+                return Some((texture, uv));
+            }
+        }
+        None
+    }
+
+    // Csempék nélkül: fokhálózat, hogy a jelölők helye akkor is értelmezhető legyen
+    fn draw_graticule(&self, painter: &egui::Painter, rect: egui::Rect, to_screen: &dyn Fn(f64, f64) -> egui::Pos2) {
+        let step = match self.zoom {
+            0..=2 => 30.0,
+            3..=4 => 10.0,
+            5..=6 => 2.0,
+            7..=8 => 1.0,
+            9..=10 => 0.2,
+            11..=12 => 0.05,
+            13..=14 => 0.01,
+            _ => 0.002,
+        };
+        let (lat_top, lon_left) = {
+            let p = rect.min - rect.center();
+            let (cx, cy) = project(self.center.0, self.center.1);
+            let world = world_size(self.zoom);
+            unproject(cx + p.x as f64 / world, cy + p.y as f64 / world)
+        };
+        let lat_bottom = 2.0 * self.center.0 - lat_top; // közelítés, csak a tartományhoz kell
+        let lon_right = 2.0 * self.center.1 - lon_left;
+        let stroke = egui::Stroke::new(1.0, egui::Color32::from_rgba_unmultiplied(60, 60, 90, 120));
+        let font = egui::FontId::proportional(10.0);
+        let color = egui::Color32::from_rgb(40, 40, 70);
+        let mut lon = (lon_left / step).floor() * step;
+        while lon <= lon_right && lon_right - lon_left < 720.0 {
+            let x = to_screen(self.center.0, lon).x;
+            painter.line_segment([egui::pos2(x, rect.top()), egui::pos2(x, rect.bottom())], stroke);
+            painter.text(egui::pos2(x + 2.0, rect.bottom() - 2.0), egui::Align2::LEFT_BOTTOM, format!("{}°", lon), font.clone(), color);
+            lon += step;
+        }
+        let mut lat = (lat_bottom.max(-MAX_LATITUDE) / step).floor() * step;
+        while lat <= lat_top.min(MAX_LATITUDE) {
+            let y = to_screen(lat, self.center.1).y;
+            painter.line_segment([egui::pos2(rect.left(), y), egui::pos2(rect.right(), y)], stroke);
+            painter.text(egui::pos2(rect.left() + 2.0, y - 2.0), egui::Align2::LEFT_BOTTOM, format!("{}°", lat), font.clone(), color);
+            lat += step;
+        }
+    }
+
+    // Vissza: a kattintott jelölő képe
+    pub fn show(&mut self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        let (response, painter) = ui.allocate_painter(ui.available_size(), egui::Sense::click_and_drag());
+        let rect = response.rect;
+        if self.fit_pending {
+            self.fit_to(rect.size());
+            self.fit_pending = false;
+        }
+        if self.current != self.followed {
+            if let Some(current) = self.current {
+                self.center = current;
+            }
+            self.followed = self.current;
+        }
+
+        // Mozgatás húzással, nagyítás görgővel
+        if response.dragged() {
+            let delta = response.drag_delta();
+            let world = world_size(self.zoom);
+            let (cx, cy) = project(self.center.0, self.center.1);
+            self.center = unproject(cx - delta.x as f64 / world, cy - delta.y as f64 / world);
+        }
+        if let Some(pointer) = response.hover_pos() {
+            self.scroll += ui.input(|i| i.raw_scroll_delta.y);
+            if self.scroll.abs() >= 40.0 {
+                let step = if self.scroll > 0.0 { 1 } else { -1 };
+                self.zoom_at(step, pointer - rect.center());
+                self.scroll = 0.0;
+            }
+        }
+
+        let zoom = self.zoom;
+        let world = world_size(zoom);
+        let (cx, cy) = project(self.center.0, self.center.1);
+        let center = rect.center();
+        let to_screen = move |lat: f64, lon: f64| {
+            let (x, y) = project(lat, lon);
+            let dx = (x - cx) - (x - cx).round(); // a legközelebbi körülfordulás
+            center + egui::vec2((dx * world) as f32, ((y - cy) * world) as f32)
+        };
+
+        painter.rect_filled(rect, 0.0, egui::Color32::from_rgb(200, 214, 226));
+        let n = 1i64 << zoom;
+        let left = cx * world - rect.width() as f64 / 2.0;
+        let top = cy * world - rect.height() as f64 / 2.0;
+        let tx0 = (left / TILE_SIZE).floor() as i64;
+        let tx1 = ((left + rect.width() as f64) / TILE_SIZE).floor() as i64;
+        let ty0 = ((top / TILE_SIZE).floor() as i64).max(0);
+        let ty1 = (((top + rect.height() as f64) / TILE_SIZE).floor() as i64).min(n - 1);
+        let ctx = ui.ctx().clone();
+        let mut found = false;
+        for ty in ty0..=ty1 {
+            for tx in tx0..=tx1 {
+                let x = tx.rem_euclid(n) as u32;
+                let min = rect.min + egui::vec2((tx as f64 * TILE_SIZE - left) as f32, (ty as f64 * TILE_SIZE - top) as f32);
+                let tile_rect = egui::Rect::from_min_size(min, egui::vec2(TILE_SIZE as f32, TILE_SIZE as f32));
+                if let Some((texture, uv)) = self.tile_texture(&ctx, zoom, x, ty as u32) {
+                    painter.image(texture.id(), tile_rect, uv, egui::Color32::WHITE);
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            self.draw_graticule(&painter, rect, &to_screen);
+            let hint = match tile_cache_dir() {
+                Some(dir) => format!("No offline map tiles, none are bundled. Copy {{z}}/{{x}}/{{y}}.png tiles to\n{}\nor choose a tile folder. See Offline map in the README.", dir.display()),
+                None => "No offline map tiles, none are bundled. Choose a tile folder. See Offline map in the README.".to_string(),
+            };
+            painter.text(rect.center_top() + egui::vec2(0.0, 10.0), egui::Align2::CENTER_TOP, hint,
+                egui::FontId::proportional(12.0), egui::Color32::from_rgb(40, 40, 70));
+        }
+
+        // Jelölők; az aktuális kép sárga
+        let pointer = response.hover_pos();
+        let mut hovered: Option<&MapPin> = None;
+        for pin in &self.pins {
+            let p = to_screen(pin.lat, pin.lon);
+            if !rect.expand(10.0).contains(p) {
+                continue;
+            }
+            painter.circle(p, 6.0, egui::Color32::from_rgb(220, 40, 40), egui::Stroke::new(1.5, egui::Color32::WHITE));
+            if pointer.is_some_and(|q| q.distance(p) <= 8.0) {
+                hovered = Some(pin);
+            }
+        }
+        if let Some((lat, lon)) = self.current {
+            let p = to_screen(lat, lon);
+            painter.circle(p, 8.0, egui::Color32::from_rgb(250, 210, 40), egui::Stroke::new(2.0, egui::Color32::BLACK));
+        }
+
+        if let Some(q) = pointer {
+            let offset = q - center;
+            let (lat, lon) = unproject(cx + offset.x as f64 / world, cy + offset.y as f64 / world);
+            let text = format!("{:.5}, {:.5}", lat, lon);
+            let galley = painter.layout_no_wrap(text, egui::FontId::monospace(11.0), egui::Color32::BLACK);
+            let pos = rect.right_bottom() - galley.size() - egui::vec2(4.0, 4.0);
+            painter.rect_filled(egui::Rect::from_min_size(pos, galley.size()).expand(2.0), 2.0,
+                egui::Color32::from_rgba_unmultiplied(255, 255, 255, 200));
+            painter.galley(pos, galley, egui::Color32::BLACK);
+        }
+
+        let clicked = hovered.filter(|_| response.clicked()).map(|pin| pin.path.clone());
+        if let Some(pin) = hovered {
+            response.on_hover_text_at_pointer(&pin.name);
+        }
+        clicked
+    }
+}
+
+fn folder_pin(path: &Path) -> Option<MapPin> {
+    let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok()?;
+    let cached = PIN_CACHE.lock().ok()?.get(path).filter(|(time, _)| *time == modified).map(|(_, pos)| *pos);
+    let position = cached.unwrap_or_else(|| {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        let position = crate::exif_edit::exif_block_from_path(path, format_from_extension(&ext))
+            .and_then(|data| ExifBlock::default().open(&data, data.len()).ok()?.gps_position());
+        if let Ok(mut cache) = PIN_CACHE.lock() {
+            cache.insert(path.to_path_buf(), (modified, position));
+        }
+        position
+    });
+    let (lat, lon) = position?;
+    let name = path.file_name()?.to_string_lossy().to_string();
+    Some(MapPin { path: path.to_path_buf(), name, lat, lon })
+}
+
+impl ImageViewer {
+    pub fn open_map(&mut self, folder: bool) {
+        let current = self.exif.as_ref().and_then(|e| e.gps_position());
+        self.map_view = Some(MapView::new(current, folder, tile_dirs(&self.config.map_tile_dir)));
+    }
+
+    // A mappa képeinek GPS helye háttérszálon; csak a fájlok elejéről az EXIF blokkot olvassuk
+    fn load_folder_pins(&mut self, ctx: &egui::Context) {
+        self.make_image_list();
+        let paths: Vec<PathBuf> = self.list_of_images.iter().map(|e| e.path()).collect();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let pins: Vec<MapPin> = paths.par_iter().filter_map(|path| folder_pin(path)).collect();
+            // ha közben mappát váltottunk, a fogadó már nincs meg
+            if sender.send(pins).is_ok() {
+                ctx.request_repaint();
+            }
+        });
+        if let Some(map) = &mut self.map_view {
+            map.pins_loading = Some(receiver);
+        }
+    }
+
+    pub fn draw_map(&mut self, ctx: &egui::Context) {
+        let current = self.exif.as_ref().and_then(|e| e.gps_position());
+        let Some(map) = &mut self.map_view else {
+            return;
+        };
+        map.current = current;
+        if map.folder && map.pins_folder != self.image_folder {
+            map.pins_folder = self.image_folder.clone();
+            self.load_folder_pins(ctx);
+        }
+        if let Some(map) = &mut self.map_view
+            && let Some(receiver) = &map.pins_loading {
+            match receiver.try_recv() {
+                Ok(pins) => {
+                    map.pins = pins;
+                    map.pins_loading = None;
+                    map.fit();
+                }
+                Err(TryRecvError::Disconnected) => map.pins_loading = None,
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+
+        let mut close = false;
+        let mut choose_dir = false;
+        let mut open_path = None;
+        ctx.show_viewport_immediate(
+            egui::ViewportId::from_hash_of("map_viewport"),
+            egui::ViewportBuilder::default()
+                .with_title("iView 🗺 Map")
+                .with_inner_size([640.0, 480.0]),
+            |ctx, _| {
+                if ctx.input(|i| i.viewport().close_requested() || i.key_pressed(egui::Key::Escape)) {
+                    close = true;
+                }
+                let Some(map) = &mut self.map_view else {
+                    return;
+                };
+                egui::TopBottomPanel::top("map_toolbar").show(ctx, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.selectable_label(!map.folder, "This photo").clicked() && map.folder {
+                            map.folder = false;
+                            map.pins.clear();
+                            map.pins_folder = None;
+                            map.pins_loading = None;
+                            if let Some(current) = map.current {
+                                map.center = current;
+                            }
+                        }
+                        if ui.selectable_label(map.folder, "Whole folder").clicked() {
+                            map.folder = true;
+                        }
+                        ui.separator();
+                        if ui.button("➖").clicked() {
+                            map.zoom_by(-1);
+                        }
+                        ui.label(format!("{}", map.zoom));
+                        if ui.button("➕").clicked() {
+                            map.zoom_by(1);
+                        }
+                        if ui.button("Fit").clicked() {
+                            map.fit();
+                        }
+                        ui.separator();
+                        if ui.button("Tile folder ...").clicked() {
+                            choose_dir = true;
+                        }
+                        if map.pins_loading.is_some() {
+                            ui.spinner();
+                            ui.label("Reading locations");
+                        } else if map.folder {
+                            ui.label(format!("{} geotagged", map.pins.len()));
+                        }
+                    });
+                });
+                egui::CentralPanel::default()
+                    .frame(egui::Frame::NONE)
+                    .show(ctx, |ui| {
+                        open_path = map.show(ui);
+                    });
+            },
+        );
+
+        if close {
+            self.map_view = None;
+        }
+        if choose_dir {
+            let dialog = rfd::FileDialog::new().set_title("iView 🗺 Map tile folder ({z}/{x}/{y}.png)");
+            if let Some(dir) = dialog.pick_folder() {
+                self.config.map_tile_dir = Some(dir);
+                let dirs = tile_dirs(&self.config.map_tile_dir);
+                if let Some(map) = &mut self.map_view {
+                    map.set_tile_dirs(dirs);
+                }
+            }
+        }
+        if let Some(path) = open_path {
+            self.open_image(ctx, &path, true);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dms_rounding_carries() {
+        assert_eq!(format_dms(47.497912, 19.040235), "47°29'52.48\"N 19°2'24.85\"E");
+        assert_eq!(format_dms(47.99999999, -18.99999999), "48°0'0.00\"N 19°0'0.00\"W");
+        assert_eq!(format_dms(-12.0 + -59.0 / 60.0 - 59.996 / 3600.0, 0.0), "13°0'0.00\"S 0°0'0.00\"E");
+        assert_eq!(format_dms(0.5, 0.0), "0°30'0.00\"N 0°0'0.00\"E");
+    }
+}
@@ -0,0 +1,240 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde_json::{Map, Value};
+
+use crate::exif_my::ExifBlock;
+use crate::file_handlers::{format_from_extension, SUPPORTED_EXTENSIONS};
+use crate::ImageViewer;
+
+// Egy mappa képeinek metaadatai soronként: JSON-lines (.jsonl) vagy CSV (.csv) táblázatkezelőhöz
+
+const COLUMNS: [&str; 10] = ["file", "width", "height", "file_size", "date", "camera", "lens", "exposure", "gps", "resolution"];
+
+#[derive(PartialEq, Clone, Copy)]
+pub enum ExportFormat {
+    JsonLines,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+            Some("csv") => ExportFormat::Csv,
+            _ => ExportFormat::JsonLines,
+        }
+    }
+}
+
+// Szöveges mezőnél a JSON idézőjelek nélkül
+fn text_field(exif: &ExifBlock, name: &str) -> Option<String> {
+    let text = match exif.find(name, 0, true)?.get("val")? {
+        Value::String(s) => s.clone(),
+        _ => exif.get_field(name)?,
+    };
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+fn camera(exif: &ExifBlock) -> Option<String> {
+    let make = text_field(exif, "Make");
+    let model = text_field(exif, "Model");
+    match (make, model) {
+        // a Model gyakran már tartalmazza a gyártót
+        (Some(make), Some(model)) if model.to_lowercase().starts_with(&make.to_lowercase()) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{} {}", make, model)),
+        (make, model) => make.or(model),
+    }
+}
+
+// EXIF LensModel, ha nincs, a gyártói MakerNote "*.LensModel" mezője
+fn lens(exif: &ExifBlock) -> Option<String> {
+    text_field(exif, "LensModel").or_else(|| {
+        let name = exif.entry_data_vector.iter()
+            .find(|e| e.name.ends_with(".LensModel"))?
+            .name.clone();
+        text_field(exif, &name)
+    })
+}
+
+fn exposure(exif: &ExifBlock) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(t) = exif.get_num_field("ExposureTime").filter(|t| *t > 0.0) {
+        if t < 1.0 {
+            parts.push(format!("1/{} s", (1.0 / t).round()));
+        } else {
+            parts.push(format!("{} s", t));
+        }
+    }
+    if let Some(f) = exif.get_num_field("FNumber").filter(|f| *f > 0.0) {
+        parts.push(format!("f/{:.1}", f));
+    }
+    if let Some(iso) = exif.get_num_field("ISOSpeedRatings") {
+        parts.push(format!("ISO {}", iso));
+    }
+    if let Some(focal) = exif.get_num_field("FocalLength").filter(|f| *f > 0.0) {
+        parts.push(format!("{} mm", focal.round()));
+    }
+    (!parts.is_empty()).then(|| parts.join(", "))
+}
+
+fn gps(exif: &ExifBlock) -> Option<String> {
+    let (lat, lon) = exif.gps_position()?;
+    Some(format!("{:.6}, {:.6}", lat, lon))
+}
+
+fn resolution(exif: &ExifBlock) -> Option<String> {
+    let xres = exif.get_num_field("XResolution")?;
+    let yres = exif.get_num_field("YResolution").filter(|y| *y != 0.0).unwrap_or(xres);
+    let unit_str = if exif.get_num_field("ResolutionUnit") == Some(3.0) { "dpcm" } else { "dpi" };
+    if xres == yres {
+        Some(format!("{} {}", xres, unit_str))
+    } else {
+        Some(format!("{}x{} {}", xres, yres, unit_str))
+    }
+}
+
+pub fn metadata_row(path: &Path) -> Map<String, Value> {
+    let mut row = Map::new();
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    row.insert("file".into(), name.into());
+    let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+    let format = format_from_extension(&ext);
+
+    let exif = fs::read(path).ok()
+        .and_then(|buffer| crate::exif_edit::exif_block_from_file(&buffer, format))
+        .and_then(|data| ExifBlock::default().open(&data, data.len()).ok());
+
+    if let Ok((mut w, mut h)) = image::image_dimensions(path) {
+        // elforgatott kép: a megjelenített méret
+        if exif.as_ref().and_then(|e| e.get_num_field("Orientation")).is_some_and(|o| o >= 5.0) {
+            std::mem::swap(&mut w, &mut h);
+        }
+        row.insert("width".into(), w.into());
+        row.insert("height".into(), h.into());
+    }
+    if let Ok(meta) = fs::metadata(path) {
+        row.insert("file_size".into(), meta.len().into());
+    }
+    if let Some(exif) = &exif {
+        let fields = [
+            ("date", text_field(exif, "DateTimeOriginal").or_else(|| text_field(exif, "DateTime"))),
+            ("camera", camera(exif)),
+            ("lens", lens(exif)),
+            ("exposure", exposure(exif)),
+            ("gps", gps(exif)),
+            ("resolution", resolution(exif)),
+        ];
+        for (key, value) in fields {
+            if let Some(value) = value {
+                row.insert(key.into(), value.into());
+            }
+        }
+    }
+    row
+}
+
+fn csv_escape(value: &str) -> String {
+    // képletként értelmezné a táblázatkezelő (CSV injection), ezért szövegként jelöljük
+    let value = if value.starts_with(['=', '+', '-', '@', '\t', '\r']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+pub fn export_metadata(images: &[PathBuf], out: &Path) -> Result<usize, String> {
+    let format = ExportFormat::from_path(out);
+    let mut text = String::new();
+    if format == ExportFormat::Csv {
+        text.push('\u{feff}'); // BOM: a táblázatkezelők így ismerik fel az UTF-8-at
+        text.push_str(&COLUMNS.join(","));
+        text.push_str("\r\n");
+    }
+    for path in images {
+        let row = metadata_row(path);
+        match format {
+            ExportFormat::JsonLines => {
+                text.push_str(&Value::Object(row).to_string());
+                text.push('\n');
+            }
+            ExportFormat::Csv => {
+                let cells: Vec<String> = COLUMNS.iter()
+                    .map(|c| match row.get(*c) {
+                        Some(Value::String(s)) => csv_escape(s),
+                        Some(v) => v.to_string(),
+                        None => String::new(),
+                    })
+                    .collect();
+                text.push_str(&cells.join(","));
+                text.push_str("\r\n");
+            }
+        }
+    }
+    let mut file = fs::File::create(out).map_err(|e| e.to_string())?;
+    file.write_all(text.as_bytes()).map_err(|e| e.to_string())?;
+    Ok(images.len())
+}
+
+// Parancssori használathoz: a mappa támogatott képei név szerint rendezve
+pub fn folder_images(folder: &Path) -> Vec<PathBuf> {
+    let mut images: Vec<PathBuf> = fs::read_dir(folder).into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|p| p.is_file())
+        .filter(|p| {
+            p.extension()
+                .and_then(|s| s.to_str())
+                .is_some_and(|ext| SUPPORTED_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .collect();
+    images.sort();
+    images
+}
+
+impl ImageViewer {
+    pub fn export_folder_metadata(&mut self) {
+        self.make_image_list();
+        if self.list_of_images.is_empty() {
+            return;
+        }
+        let folder_name = self.image_folder.as_ref()
+            .and_then(|f| f.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or("images".into());
+        let mut dialog = rfd::FileDialog::new()
+            .set_title("iView 🔍 Export folder metadata")
+            .add_filter("CSV", &["csv"])
+            .add_filter("JSON lines", &["jsonl"])
+            .set_file_name(format!("{}_metadata.csv", folder_name));
+        if let Some(folder) = &self.image_folder {
+            dialog = dialog.set_directory(folder);
+        }
+        if let Some(out) = dialog.save_file() {
+            let images: Vec<PathBuf> = self.list_of_images.iter().map(|e| e.path()).collect();
+            match export_metadata(&images, &out) {
+                Ok(count) => println!("{} kép metaadata exportálva: {:?}", count, out),
+                Err(e) => println!("Metaadat export hiba: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_formula_cells() {
+        assert_eq!(csv_escape("Canon EOS 5D"), "Canon EOS 5D");
+        assert_eq!(csv_escape("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_escape("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_escape("-19.250000, 47.500000"), "\"'-19.250000, 47.500000\"");
+    }
+}
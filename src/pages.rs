@@ -0,0 +1,311 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use crate::file_handlers::{SaveFormat, apply_modifies_to_frame};
+use crate::image_processing::AnimatedImage;
+use crate::ImageViewer;
+
+// Többoldalas fájlok: TIFF oldalak, ICO/CUR méretek; APNG mentés
+
+const PAGE_DELAY: Duration = Duration::from_millis(1000); // GIF/WebP-be mentéskor az oldalak ideje
+
+// A lapok igény szerinti betöltéséhez: csak a megjelenített (vagy mentett) oldalt dekódoljuk
+pub enum PageSource {
+    Tiff(Box<tiff::decoder::Decoder<std::io::BufReader<std::fs::File>>>),
+    Pdf, // a nyitva tartott self.pdf dokumentumból
+}
+
+// A lapok száma a könyvtárak bejárásával, képadat dekódolása nélkül; egyoldalas fájlnál None
+fn tiff_page_source(path: &PathBuf) -> Option<(PageSource, usize)> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut decoder = tiff::decoder::Decoder::new(std::io::BufReader::new(file)).ok()?;
+    if !decoder.more_images() {
+        return None;
+    }
+    let mut count = 1;
+    while decoder.more_images() && decoder.next_image().is_ok() {
+        count += 1;
+    }
+    Some((PageSource::Tiff(Box::new(decoder)), count))
+}
+
+fn tiff_page(decoder: &mut tiff::decoder::Decoder<std::io::BufReader<std::fs::File>>, index: usize) -> Option<image::DynamicImage> {
+    decoder.seek_to_image(index).ok()?;
+    let (w, h) = decoder.dimensions().ok()?;
+    let colortype = decoder.colortype().ok()?;
+    let result = decoder.read_image().ok()?;
+    tiff_page_to_image(w, h, colortype, result)
+}
+
+fn tiff_page_to_image(
+    w: u32,
+    h: u32,
+    colortype: tiff::ColorType,
+    result: tiff::decoder::DecodingResult,
+) -> Option<image::DynamicImage> {
+    use tiff::ColorType;
+    use tiff::decoder::DecodingResult;
+    use image::DynamicImage;
+    match (colortype, result) {
+        (ColorType::Gray(8), DecodingResult::U8(d)) => image::GrayImage::from_raw(w, h, d).map(DynamicImage::ImageLuma8),
+        (ColorType::GrayA(8), DecodingResult::U8(d)) => image::GrayAlphaImage::from_raw(w, h, d).map(DynamicImage::ImageLumaA8),
+        (ColorType::RGB(8), DecodingResult::U8(d)) => image::RgbImage::from_raw(w, h, d).map(DynamicImage::ImageRgb8),
+        (ColorType::RGBA(8), DecodingResult::U8(d)) => image::RgbaImage::from_raw(w, h, d).map(DynamicImage::ImageRgba8),
+        (ColorType::Gray(16), DecodingResult::U16(d)) => image::ImageBuffer::from_raw(w, h, d).map(DynamicImage::ImageLuma16),
+        (ColorType::RGB(16), DecodingResult::U16(d)) => image::ImageBuffer::from_raw(w, h, d).map(DynamicImage::ImageRgb16),
+        (ColorType::RGBA(16), DecodingResult::U16(d)) => image::ImageBuffer::from_raw(w, h, d).map(DynamicImage::ImageRgba16),
+        (ColorType::CMYK(8), DecodingResult::U8(d)) => {
+            let rgb: Vec<u8> = d.chunks_exact(4).flat_map(|p| {
+                let k = 255 - p[3] as u32;
+                [p[0], p[1], p[2]].map(|c| ((255 - c as u32) * k / 255) as u8)
+            }).collect();
+            image::RgbImage::from_raw(w, h, rgb).map(DynamicImage::ImageRgb8)
+        }
+        _ => None,
+    }
+}
+
+// ICO/CUR: minden bejegyzésből egy egyképes ICO-t készítünk, azt az image crate dekódolja
+pub fn ico_pages(buffer: &[u8]) -> Vec<image::DynamicImage> {
+    let mut pages = Vec::new();
+    if buffer.len() < 6 || buffer[0..2] != [0, 0] {
+        return pages;
+    }
+    let count = u16::from_le_bytes([buffer[4], buffer[5]]) as usize;
+    for i in 0..count {
+        let entry_pos = 6 + i * 16;
+        let Some(entry) = buffer.get(entry_pos..entry_pos + 16) else {
+            break;
+        };
+        let size = u32::from_le_bytes(entry[8..12].try_into().unwrap()) as usize;
+        let offset = u32::from_le_bytes(entry[12..16].try_into().unwrap()) as usize;
+        let Some(data) = buffer.get(offset..offset.saturating_add(size)) else {
+            continue;
+        };
+        let mut single = vec![0, 0, 1, 0, 1, 0];
+        single.extend_from_slice(&entry[0..12]);
+        single.extend_from_slice(&22u32.to_le_bytes());
+        single.extend_from_slice(data);
+        if let Ok(img) = image::load_from_memory_with_format(&single, image::ImageFormat::Ico) {
+            pages.push(img);
+        }
+    }
+    pages
+}
+
+// Több oldal egy TIFF fájlba
+pub fn save_tiff_pages(
+    path: &PathBuf,
+    pages: &[image::DynamicImage],
+    resolution: (u32, u32, u16),
+    icc: Option<&[u8]>,
+) -> Result<(), String> {
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut tiff_writer = tiff::encoder::TiffEncoder::new(file)
+        .map_err(|e| e.to_string())?
+        .with_compression(tiff::encoder::Compression::Deflate(tiff::encoder::DeflateLevel::Best));
+    let (x, y, unit) = resolution;
+    for page in pages {
+        let rgba = page.to_rgba8();
+        let mut col = tiff_writer
+            .new_image::<tiff::encoder::colortype::RGBA8>(rgba.width(), rgba.height())
+            .map_err(|e| e.to_string())?;
+        col.encoder().write_tag(tiff::tags::Tag::XResolution, tiff::encoder::Rational { n: x, d: 1000 }).map_err(|e| e.to_string())?;
+        col.encoder().write_tag(tiff::tags::Tag::YResolution, tiff::encoder::Rational { n: y, d: 1000 }).map_err(|e| e.to_string())?;
+        col.encoder().write_tag(tiff::tags::Tag::ResolutionUnit, unit).map_err(|e| e.to_string())?;
+        col.encoder().write_tag(tiff::tags::Tag::Software, "IView 2026").map_err(|e| e.to_string())?;
+        if let Some(icc) = icc {
+            col.encoder().write_tag(tiff::tags::Tag::IccProfile, icc).map_err(|e| e.to_string())?;
+        }
+        col.write_data(rgba.as_raw()).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Több méret egy ICO fájlba, PNG kódolással; 256 pixelnél nagyobb oldalakat kicsinyítünk
+pub fn save_ico_pages(path: &PathBuf, pages: &[image::DynamicImage]) -> Result<(), String> {
+    let mut encoded = Vec::new();
+    for page in pages {
+        let page = if page.width() > 256 || page.height() > 256 {
+            page.thumbnail(256, 256)
+        } else {
+            page.clone()
+        };
+        let rgba = page.to_rgba8();
+        encoded.push(
+            image::codecs::ico::IcoFrame::as_png(
+                rgba.as_raw(),
+                rgba.width(),
+                rgba.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+            .map_err(|e| e.to_string())?,
+        );
+    }
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    image::codecs::ico::IcoEncoder::new(std::io::BufWriter::new(file))
+        .encode_images(&encoded)
+        .map_err(|e| e.to_string())
+}
+
+// Animált PNG: a képkockák teljes méretűek, így dispose None és blend Source elég
+pub fn save_apng(path: &PathBuf, frames: &[image::DynamicImage], delays: &[Duration]) -> Result<(), String> {
+    let Some(first) = frames.first() else {
+        return Err("Nincs képkocka".to_string());
+    };
+    let (w, h) = (first.width(), first.height());
+    let file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+    let mut encoder = png::Encoder::new(std::io::BufWriter::new(file), w, h);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0).map_err(|e| e.to_string())?; // 0: végtelen ismétlés
+    let mut writer = encoder.write_header().map_err(|e| e.to_string())?;
+    for (i, frame) in frames.iter().enumerate() {
+        let delay_ms = delays.get(i).map_or(100, |d| d.as_millis().min(u16::MAX as u128) as u16);
+        writer.set_frame_delay(delay_ms, 1000).map_err(|e| e.to_string())?;
+        writer.set_dispose_op(png::DisposeOp::None).map_err(|e| e.to_string())?;
+        writer.set_blend_op(png::BlendOp::Source).map_err(|e| e.to_string())?;
+        let rgba = if frame.width() != w || frame.height() != h {
+            frame.resize_exact(w, h, image::imageops::FilterType::Triangle).to_rgba8()
+        } else {
+            frame.to_rgba8()
+        };
+        writer.write_image_data(rgba.as_raw()).map_err(|e| e.to_string())?;
+    }
+    writer.finish().map_err(|e| e.to_string())
+}
+
+impl ImageViewer {
+
+    // Oldalak betöltése az anim_data-ba; lapozni a képkocka gombokkal lehet
+    pub fn load_pages(&mut self, path: &PathBuf) {
+        let (pages, source) = match self.image_format {
+            SaveFormat::Tif => {
+                let Some((source, count)) = tiff_page_source(path) else {
+                    return;
+                };
+                let mut pages = vec![None; count];
+                pages[0] = self.original_image.clone(); // az első oldalt a betöltés már dekódolta
+                (pages, Some(source))
+            }
+            SaveFormat::Ico => {
+                let pages = std::fs::read(path).map(|buffer| ico_pages(&buffer)).unwrap_or_default();
+                (pages.into_iter().map(Some).collect(), None)
+            }
+            SaveFormat::Pdf => {
+                // 72 dpi-s előnézetek kéréskor; a megjelenítéshez a review() az aktuális nagyításon rajzol
+                let mut pages = vec![None; self.pdf.as_ref().map_or(0, |pdf| pdf.page_count)];
+                if let Some(first) = pages.first_mut() {
+                    *first = self.original_image.clone(); // az első oldalt a megnyitás már megrajzolta
+                }
+                (pages, Some(PageSource::Pdf))
+            }
+            _ => return,
+        };
+        if pages.len() > 1 {
+            self.total_frames = pages.len();
+            self.anim_data = Some(AnimatedImage {
+                delays: vec![PAGE_DELAY; pages.len()],
+                anim_frames: pages,
+                is_pages: true,
+                source,
+            });
+        }
+    }
+
+    // A kért oldal vagy képkocka; a még be nem töltött oldalt most dekódoljuk
+    pub fn frame_image(&mut self, index: usize) -> Option<image::DynamicImage> {
+        self.load_page(index);
+        self.anim_data.as_ref()?.anim_frames.get(index)?.clone()
+    }
+
+    fn load_page(&mut self, index: usize) {
+        let Some(anim) = &mut self.anim_data else {
+            return;
+        };
+        if anim.anim_frames.get(index).is_none_or(|frame| frame.is_some()) {
+            return;
+        }
+        let page = match &mut anim.source {
+            Some(PageSource::Tiff(decoder)) => tiff_page(decoder, index),
+            Some(PageSource::Pdf) => self.pdf.as_ref()
+                .and_then(|pdf| pdf.render(index, 1.0, 16384))
+                .map(|(img, _)| img),
+            None => None,
+        };
+        if page.is_none() {
+            println!("Az oldal nem olvasható: {}", index + 1);
+        }
+        anim.anim_frames[index] = page;
+    }
+
+    // Mentés előtt minden oldal kell; PDF-nél nem, azt a processed_frames a mentés felbontásával rajzolja
+    pub fn load_all_pages(&mut self) {
+        if self.pdf.is_some() {
+            return;
+        }
+        let count = self.anim_data.as_ref().map_or(0, |anim| anim.anim_frames.len());
+        for index in 0..count {
+            self.load_page(index);
+        }
+    }
+
+    // Az összes oldal a beállított korrekciókkal (mentéshez); PDF-nél a megadott dpi-vel rajzolva
+    pub fn processed_frames(&self, srgb_transform: &Option<std::sync::Arc<moxcms::Transform8BitExecutor>>, pdf_dpi: f32) -> Vec<image::DynamicImage> {
+        let Some(anim) = &self.anim_data else {
+            return Vec::new();
+        };
+        let (frames, magnify) = if self.pdf.is_some() {
+            let pages: Vec<image::DynamicImage> = (0..anim.anim_frames.len())
+                .filter_map(|i| self.render_pdf_page(i, pdf_dpi))
+                .collect();
+            (pages, 1.0)
+        } else {
+            (anim.anim_frames.iter().flatten().cloned().collect(), self.magnify)
+        };
+        let settings = &self.color_settings;
+        let save_original = self.save_original;
+        let lut = &self.lut;
+        let gpu_interface = &self.gpu_interface;
+        frames
+            .into_par_iter()
+            .map(|mut f| {
+                if !save_original {
+                    apply_modifies_to_frame(&mut f, settings, magnify, lut, gpu_interface);
+                }
+                if let Some(transform) = srgb_transform {
+                    crate::icc::convert_dynamic_image(transform.as_ref(), &mut f);
+                }
+                f
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(shade: u8) -> image::DynamicImage {
+        image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(4, 3, image::Rgba([shade, shade, shade, 255])))
+    }
+
+    // A lapszám dekódolás nélkül, a kért oldal pedig önállóan olvasható, visszafelé is
+    #[test]
+    fn tiff_pages_on_demand() {
+        let path = std::env::temp_dir().join(format!("iview_pages_{}.tif", std::process::id()));
+        save_tiff_pages(&PathBuf::from(&path), &[page(10), page(20), page(30)], (72000, 72000, 2), None).unwrap();
+        let Some((PageSource::Tiff(mut decoder), count)) = tiff_page_source(&path) else {
+            panic!("a TIFF lapok nem olvashatók");
+        };
+        assert_eq!(count, 3);
+        for index in [2, 0, 1] {
+            let img = tiff_page(&mut decoder, index).unwrap().to_rgba8();
+            assert_eq!(img.get_pixel(0, 0)[0], 10 * (index as u8 + 1));
+        }
+        page(10).save(&path).unwrap();
+        assert!(tiff_page_source(&path).is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -14,11 +14,16 @@ struct GpuColorSettings {
     show_g: u32,
     show_b: u32,
 	oklab: u32,
+    use_tone_table: u32,
+    wb_gains: vec3<f32>,
+    use_hsl: u32,
+    hsl_bands: array<vec4<f32>, 8>, // sávközép, árnyalat, telítettség, világosság
 }
 
 @group(0) @binding(0) var<uniform> colset: GpuColorSettings;
 @group(0) @binding(1) var t_identity: texture_3d<f32>;
 @group(0) @binding(2) var t_lut_out: texture_storage_3d<rgba8unorm, write>;
+@group(0) @binding(3) var<uniform> tone_table: array<vec4<f32>, 256>; // szintek és tónusgörbék: (R, G, B, -)
 
 @compute @workgroup_size(4, 4, 4)
 fn generate_lut(@builtin(global_invocation_id) id: vec3<u32>) {
@@ -35,6 +40,9 @@ fn generate_lut(@builtin(global_invocation_id) id: vec3<u32>) {
 fn apply_color_math(in_color: vec3<f32>) -> vec3<f32> {
     var out = in_color;
 
+    // 0. Fehéregyensúly
+    out = clamp(out * colset.wb_gains, vec3(0.0), vec3(1.0));
+
     // 1. Invertálás
     if (colset.invert == 1u) { out = 1.0 - out; }
 
@@ -43,6 +51,8 @@ fn apply_color_math(in_color: vec3<f32>) -> vec3<f32> {
 	
 	if( colset.oklab != 0 ) { hsv = rgb_to_oklab(out); }
 	else { hsv = rgb_to_hsv(out); }
+
+    if (colset.use_hsl != 0u) { hsv = apply_hsl(hsv); }
 	
     hsv.r = fract(hsv.r + colset.hue_shift / 360.0);
     if (colset.saturation > 0.0) {
@@ -59,12 +69,48 @@ fn apply_color_math(in_color: vec3<f32>) -> vec3<f32> {
     out = factor * (out + colset.brightness - 0.5) + 0.5;
     out = pow(max(out, vec3(0.0)), vec3(1.0 / colset.gamma));
 
-    // 4. channel mask
+    // 4. Szintek és tónusgörbék
+    if (colset.use_tone_table != 0u) {
+        out = vec3<f32>(tone_at(out.r, 0), tone_at(out.g, 1), tone_at(out.b, 2));
+    }
+
+    // 5. channel mask
     let mask = vec3<f32>(f32(colset.show_r), f32(colset.show_g), f32(colset.show_b));
     return clamp(out * mask, vec3(0.0), vec3(1.0));
 }
 
 
+// HSL keverő: a két szomszédos sáv beállítása keverve, a szürkék érintetlenek
+fn apply_hsl(in_hsv: vec3<f32>) -> vec3<f32> {
+    var hsv = in_hsv;
+    var i = 7u;
+    for (var k = 0u; k < 8u; k++) {
+        if (hsv.x >= colset.hsl_bands[k].x) { i = k; }
+    }
+    let j = (i + 1u) % 8u;
+    let a = colset.hsl_bands[i];
+    let b = colset.hsl_bands[j];
+    let span = max(fract(b.x - a.x + 1.0), 0.001);
+    let t = smoothstep(0.0, 1.0, clamp(fract(hsv.x - a.x + 1.0) / span, 0.0, 1.0));
+    let adj = mix(a.yzw, b.yzw, t);
+    let weight = clamp(hsv.y, 0.0, 1.0);
+    hsv.x = fract(hsv.x + adj.x * weight + 1.0);
+    if (adj.y > 0.0) {
+        hsv.y = hsv.y + (1.0 - hsv.y) * adj.y * weight;
+    } else {
+        hsv.y = hsv.y * (1.0 + adj.y);
+    }
+    hsv.z = hsv.z * (1.0 + 0.5 * adj.z * weight);
+    return hsv;
+}
+
+fn tone_at(v: f32, channel: i32) -> f32 {
+    let x = clamp(v, 0.0, 1.0) * 255.0;
+    let i = u32(floor(x));
+    let j = min(i + 1u, 255u);
+    return mix(tone_table[i][channel], tone_table[j][channel], fract(x));
+}
+
 fn rgb_to_hsv(c: vec3<f32>) -> vec3<f32> {
     let v = max(c.r, max(c.g, c.b));
     let delta = v - min(c.r, min(c.g, c.b));
@@ -157,7 +203,23 @@ struct GpuSharpenSettings {
     transparency_tolerance: f32,
     use_transparency: u32,   // != 0
     rough_transparency: u32,
-    _pad: f32,
+    local_sigma: f32,      // térbeli korrekciók
+    vibrance: f32,
+    clarity: f32,
+    shadows: f32,
+    highlights: f32,
+    denoise_luma: f32,     // 0.0 .. 1.0
+    denoise_chroma: f32,   // 0.0 .. 1.0
+    sharpen_threshold: f32, // 0.0 .. 1.0 skálán
+    sharpen_luma: u32,      // != 0: csak a világosság élesítése
+    lens_k1: f32,
+    lens_k2: f32,
+    ca_red: f32,            // a csatorna nagyítása - 1.0
+    ca_blue: f32,
+    vignette_amount: f32,
+    vignette_midpoint: f32,
+    vignette_roundness: f32,
+    _pad: u32,
 }
 
 @group(1) @binding(0) var t_src: texture_2d<f32>;       // Eredeti kép
@@ -237,9 +299,20 @@ fn apply_effects(@builtin(global_invocation_id) id: vec3<u32>) {
             }
         }
         let average_color = sum / total_weight;
-        let detail = center_color - average_color;
+        var detail = center_color - average_color;
+        if (filt.sharpen_luma != 0u) {
+            detail = vec3<f32>(dot(detail, vec3<f32>(0.299, 0.587, 0.114)));
+        }
+        // életlen maszk küszöb, csak élesítésnél
+        if (filt.sharpen_amount > 0.0 && filt.sharpen_threshold > 0.0) {
+            let t = filt.sharpen_threshold;
+            detail *= smoothstep(vec3<f32>(t * 0.5), vec3<f32>(t), abs(detail));
+        }
         processed = center_color + detail * filt.sharpen_amount;
     }
+    if (filt.vibrance != 0.0 || filt.clarity != 0.0 || filt.shadows != 0.0 || filt.highlights != 0.0) {
+        processed = local_adjust(processed, coords, dims);
+    }
     let lut_size = 33.0;
     let lut_coords = clamp(processed, vec3(0.0), vec3(1.0)) * ((lut_size - 1.0) / lut_size) + (0.5 / lut_size);
     var corrected_rgb = textureSampleLevel(t_lut, s_linear, lut_coords, 0.0).rgb;
@@ -260,6 +333,156 @@ fn apply_effects(@builtin(global_invocation_id) id: vec3<u32>) {
     textureStore(t_out, coords, final_color);
 }
 
+// 3. ELJÁRÁS: Előkészítő menetek az apply_effects előtt, közös kötésekkel: objektív korrekció, zajszűrés
+
+@group(0) @binding(10) var t_pre_src: texture_2d<f32>;
+@group(0) @binding(11) var t_pre_out: texture_storage_2d<rgba8unorm, write>;
+@group(0) @binding(12) var<uniform> pre: GpuSharpenSettings;
+
+// Bilineáris olvasás pixel koordinátán; a képen kívül negatív alfa jelzi
+fn load_bilinear(pos: vec2<f32>, dims: vec2<i32>) -> vec4<f32> {
+    let fdims = vec2<f32>(dims);
+    if (any(pos < vec2<f32>(-0.5)) || any(pos > fdims - vec2<f32>(0.5))) { return vec4<f32>(-1.0); }
+    let p = clamp(pos, vec2<f32>(0.0), fdims - vec2<f32>(1.0));
+    let p0 = vec2<i32>(floor(p));
+    let p1 = min(p0 + vec2<i32>(1), dims - vec2<i32>(1));
+    let t = p - floor(p);
+    let top = mix(textureLoad(t_pre_src, p0, 0), textureLoad(t_pre_src, vec2<i32>(p1.x, p0.y), 0), t.x);
+    let bottom = mix(textureLoad(t_pre_src, vec2<i32>(p0.x, p1.y), 0), textureLoad(t_pre_src, p1, 0), t.x);
+    return mix(top, bottom, t.y);
+}
+
+// Objektív korrekció (mint lens.rs): torzítás és színi hiba a forrás helyén, utána vignetta
+@compute @workgroup_size(16, 16)
+fn lens_correct(@builtin(global_invocation_id) id: vec3<u32>) {
+    let dims_u32 = textureDimensions(t_pre_src);
+    if (id.x >= dims_u32.x || id.y >= dims_u32.y) { return; }
+    let coords = vec2<i32>(id.xy);
+    let dims = vec2<i32>(dims_u32);
+    let center = vec2<f32>(dims) / 2.0;
+    let radius = length(center);
+    let f = vec2<f32>(coords) + vec2<f32>(0.5) - center;
+
+    var color = textureLoad(t_pre_src, coords, 0);
+    if (pre.lens_k1 != 0.0 || pre.lens_k2 != 0.0 || pre.ca_red != 0.0 || pre.ca_blue != 0.0) {
+        let p = f / radius;
+        let r2 = dot(p, p);
+        let src = p * (1.0 + pre.lens_k1 * r2 + pre.lens_k2 * r2 * r2);
+        let red = load_bilinear(src * (1.0 + pre.ca_red) * radius + center - vec2<f32>(0.5), dims);
+        let green = load_bilinear(src * radius + center - vec2<f32>(0.5), dims);
+        let blue = load_bilinear(src * (1.0 + pre.ca_blue) * radius + center - vec2<f32>(0.5), dims);
+        if (red.a < 0.0 || green.a < 0.0 || blue.a < 0.0) {
+            color = vec4<f32>(0.0);
+        } else {
+            color = vec4<f32>(red.r, green.g, blue.b, green.a);
+        }
+    }
+    if (abs(pre.vignette_amount) >= 0.001) {
+        let aspect = f32(dims.x) / f32(dims.y);
+        var q = f / center;
+        let circle = vec2<f32>(q.x * max(aspect, 1.0), q.y / min(aspect, 1.0));
+        q = mix(q, circle, max(pre.vignette_roundness, 0.0));
+        let n = 2.0 - min(pre.vignette_roundness, 0.0) * 6.0;
+        let d = pow(pow(abs(q.x), n) + pow(abs(q.y), n), 1.0 / n);
+        let t = smoothstep(0.0, 1.0, (d - pre.vignette_midpoint) / 0.75);
+        color = vec4<f32>(clamp(color.rgb * (1.0 + pre.vignette_amount * t), vec3(0.0), vec3(1.0)), color.a);
+    }
+    textureStore(t_pre_out, coords, color);
+}
+
+// Zajszűrés (kétoldali szűrő YCbCr térben, mint denoise.rs)
+
+fn to_ycc(rgb: vec3<f32>) -> vec3<f32> {
+    let y = dot(rgb, vec3<f32>(0.299, 0.587, 0.114));
+    return vec3<f32>(y, (rgb.b - y) * 0.564, (rgb.r - y) * 0.713);
+}
+
+fn from_ycc(ycc: vec3<f32>) -> vec3<f32> {
+    let r = ycc.x + ycc.z / 0.713;
+    let b = ycc.x + ycc.y / 0.564;
+    let g = (ycc.x - 0.299 * r - 0.114 * b) / 0.587;
+    return vec3<f32>(r, g, b);
+}
+
+@compute @workgroup_size(16, 16)
+fn denoise(@builtin(global_invocation_id) id: vec3<u32>) {
+    let dims_u32 = textureDimensions(t_pre_src);
+    if (id.x >= dims_u32.x || id.y >= dims_u32.y) { return; }
+    let coords = vec2<i32>(id.xy);
+    let dims = vec2<i32>(dims_u32);
+    let original_pixel = textureLoad(t_pre_src, coords, 0);
+    let center = to_ycc(original_pixel.rgb);
+
+    let luma_range = 2.0 * pow(max(pre.denoise_luma * 0.12, 0.001), 2.0);
+    let chroma_range = 2.0 * pow(max(pre.denoise_chroma * 0.15, 0.001), 2.0);
+    let chroma_edge = 2.0 * 0.1 * 0.1;
+    var sum_y = 0.0;
+    var total_y = 0.0;
+    var sum_c = vec2<f32>(0.0);
+    var total_c = 0.0;
+    for (var dy: i32 = -4; dy <= 4; dy++) {
+        for (var dx: i32 = -4; dx <= 4; dx++) {
+            let sample_coords = clamp(coords + vec2<i32>(dx, dy), vec2<i32>(0), dims - vec2<i32>(1));
+            let s = to_ycc(textureLoad(t_pre_src, sample_coords, 0).rgb);
+            let d2 = f32(dx * dx + dy * dy);
+            let dl = s.x - center.x;
+            let dc = dot(s.yz - center.yz, s.yz - center.yz);
+            if (pre.denoise_luma > 0.0) {
+                let wy = exp(-d2 / (2.0 * 1.5 * 1.5) - dl * dl / luma_range);
+                sum_y += s.x * wy;
+                total_y += wy;
+            }
+            if (pre.denoise_chroma > 0.0) {
+                let wc = exp(-d2 / (2.0 * 3.0 * 3.0) - dl * dl / chroma_edge - dc / chroma_range);
+                sum_c += s.yz * wc;
+                total_c += wc;
+            }
+        }
+    }
+    var out = center;
+    if (total_y > 0.0) { out.x = sum_y / total_y; }
+    if (total_c > 0.0) { out = vec3<f32>(out.x, sum_c / total_c); }
+    textureStore(t_pre_out, coords, vec4<f32>(clamp(from_ycc(out), vec3(0.0), vec3(1.0)), original_pixel.a));
+}
+
+// Vibrancia, tisztaság, árnyék/csúcsfény: a környezet világossága ritkított 9x9 Gauss-maggal (mint local_adjust.rs)
+fn local_adjust(rgb: vec3<f32>, coords: vec2<i32>, dims: vec2<i32>) -> vec3<f32> {
+    let luma_w = vec3<f32>(0.299, 0.587, 0.114);
+    let step = filt.local_sigma / 1.5;
+    var sum = 0.0;
+    var total_weight = 0.0;
+    for (var j: i32 = -4; j <= 4; j++) {
+        for (var i: i32 = -4; i <= 4; i++) {
+            let weight = get_gaussian_weight(length(vec2<f32>(f32(i), f32(j))), 1.5);
+            let offset = vec2<i32>(round(vec2<f32>(f32(i), f32(j)) * step));
+            let sample_coords = clamp(coords + offset, vec2<i32>(0), dims - vec2<i32>(1));
+            sum += dot(textureLoad(t_src, sample_coords, 0).rgb, luma_w) * weight;
+            total_weight += weight;
+        }
+    }
+    let blur = sum / total_weight;
+    let l = dot(rgb, luma_w);
+
+    var delta = 0.0;
+    if (filt.clarity != 0.0) {
+        delta += filt.clarity * (l - blur) * (1.0 - (2.0 * l - 1.0) * (2.0 * l - 1.0));
+    }
+    if (filt.shadows != 0.0) {
+        delta += filt.shadows * (1.0 - smoothstep(0.0, 0.5, blur)) * (1.0 - l) * sqrt(max(l, 0.0));
+    }
+    if (filt.highlights != 0.0) {
+        delta += filt.highlights * smoothstep(0.5, 1.0, blur) * 0.5 * l * l;
+    }
+    var out = rgb + delta;
+    if (filt.vibrance != 0.0) {
+        let sat = clamp(max(out.r, max(out.g, out.b)) - min(out.r, min(out.g, out.b)), 0.0, 1.0);
+        let amount = filt.vibrance * (1.0 - sat) * (1.0 - sat);
+        let gray = dot(out, luma_w);
+        out = gray + (out - gray) * (1.0 + amount);
+    }
+    return clamp(out, vec3(0.0), vec3(1.0));
+}
+
 fn color_to_alpha(pixel: vec4<f32> ) -> vec4<f32> {
     var out = pixel;
     let tolerance = filt.transparency_tolerance * 1.7294;
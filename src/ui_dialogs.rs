@@ -2,6 +2,8 @@ use crate::colors::*;
 use crate::file_handlers::*;
 //use crate::image_processing::*;
 use crate::ImageViewer;
+use crate::icc::IccSave;
+use crate::exif_edit::MetadataMode;
 
 
 impl ImageViewer {
@@ -147,17 +149,53 @@ impl ImageViewer {
                             if save_data.saveformat != SaveFormat::Bmp && save_data.saveformat != SaveFormat::Png {
                                 ui.separator();
                             }
-                            let txt = format!("📝 Include EXIF metadata (+ {} bytes) ",exif.raw_exif_length);
-                            ui.checkbox(&mut save_data.include_exif, txt);
+                            let txt = format!("📝 EXIF metadata ({} bytes):", exif.raw_exif_length);
+                            egui::ComboBox::from_label(txt)
+                                .selected_text(save_data.metadata.label())
+                                .show_ui(ui, |ui| {
+                                    for mode in [MetadataMode::KeepAll, MetadataMode::RemoveGps, MetadataMode::Minimal, MetadataMode::StripAll] {
+                                        ui.selectable_value(&mut save_data.metadata, mode, mode.label());
+                                    }
+                                });
                         }
                     }
+                    if self.pdf.is_some() {
+                        ui.add(
+                            egui::Slider::new(&mut save_data.pdf_dpi, 36.0..=600.0)
+                                .step_by(1.0)
+                                .text("Resolution (dpi)"),
+                        );
+                    }
+                    if let (true, Some(icc)) = (save_data.can_keep_profile, &self.icc_profile) {
+                        // Strip all mellett a profil sem marad, a pixelek sRGB-be kerülnek
+                        let strip_all = save_data.can_include_exif && save_data.metadata == MetadataMode::StripAll;
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(format!("🎨 {}:", icc.name)).strong());
+                            let mut mode = if strip_all { IccSave::ConvertToSrgb } else { save_data.icc_mode };
+                            ui.add_enabled_ui(!strip_all, |ui| {
+                                ui.radio_value(&mut mode, IccSave::KeepProfile, "Keep profile");
+                                ui.radio_value(&mut mode, IccSave::ConvertToSrgb, "Convert to sRGB");
+                            }).response.on_disabled_hover_text("Strip all removes the profile too");
+                            if !strip_all {
+                                save_data.icc_mode = mode;
+                            }
+                        });
+                    }
                     ui.add_space(10.0);
                     
                     if save_data.is_animation {
+                        let is_pages = self.anim_data.as_ref().is_some_and(|a| a.is_pages);
                         ui.horizontal(|ui| {
-                            ui.label(egui::RichText::new("Animation detected:").strong());
-                            ui.radio_value(&mut save_data.save_all_frames, false, "Current Frame Only");
-                            ui.radio_value(&mut save_data.save_all_frames, true, "Full Animation");
+                            if is_pages {
+                                ui.label(egui::RichText::new("Multiple pages:").strong());
+                                ui.radio_value(&mut save_data.save_all_frames, false, "Current Page Only");
+                                ui.radio_value(&mut save_data.save_all_frames, true, "All Pages");
+                            } else {
+                                ui.label(egui::RichText::new("Animation detected:").strong());
+                                ui.radio_value(&mut save_data.save_all_frames, false, "Current Frame Only");
+                                let full_txt = if save_data.saveformat == SaveFormat::Png { "Full Animation (APNG)" } else { "Full Animation" };
+                                ui.radio_value(&mut save_data.save_all_frames, true, full_txt);
+                            }
                         });
                     }
 
@@ -177,6 +215,8 @@ impl ImageViewer {
             }
         }
 
+        let mut open_editor = false;
+        let mut open_map = false;
         if self.show_info {
             egui::Window::new("iView 🔍 Image Info")
                 .open(&mut self.show_info) // Bezáró gomb (X) kezelése
@@ -255,6 +295,12 @@ impl ImageViewer {
                                 ui.end_row();
                             }
 
+                            if let Some(icc) = &self.icc_profile {
+                                ui.label("Color profile:");
+                                ui.label(icc.name.clone());
+                                ui.end_row();
+                            }
+
                             if let Some(exif) = &self.exif {
                                 if let Some(f) = exif.get_field("DateTimeOriginal".into())
                                 {
@@ -269,60 +315,139 @@ impl ImageViewer {
                                     ui.end_row();
                                 }
 
-                                let la = exif .get_num_field("GPSLatitude".into());
-                                    //.and_then(exif_to_decimal);
-                                let lo = exif.get_num_field("GPSLongitude".into());
-                                    //.and_then(exif_to_decimal);
-                                let lar = exif.get_field("GPSLatitudeRef".into());
-                                let lor = exif.get_field("GPSLongitudeRef".into());
-
-                                if let (Some(mut la_), Some(mut lo_), Some(lar_), Some(lor_), ) = (la, lo, lar, lor) {
-                                    // S (Dél) és W (Nyugat) esetén negatív előjel
-                                    if lar_.contains('S') {
-                                        la_ = -la_;
-                                    }
-                                    if lor_.contains('W') {
-                                        lo_ = -lo_;
-                                    }
+                                if let Some((la_, lo_)) = exif.gps_position() {
                                     ui.label("GeoLocation:");
                                     let koord_szoveg = format!("{:.6}, {:.6}", la_, lo_);
-                                    ui.label(&koord_szoveg);
+                                    ui.horizontal(|ui| {
+                                        ui.label(&koord_szoveg);
+                                        ui.menu_button("📋", |ui| {
+                                            if ui.button(&koord_szoveg).on_hover_text("Copy decimal degrees").clicked() {
+                                                ui.ctx().copy_text(koord_szoveg.clone());
+                                                ui.close();
+                                            }
+                                            let dms = crate::map::format_dms(la_, lo_);
+                                            if ui.button(&dms).on_hover_text("Copy degrees, minutes, seconds").clicked() {
+                                                ui.ctx().copy_text(dms);
+                                                ui.close();
+                                            }
+                                        });
+                                    });
                                     ui.end_row();
 
+                                    if let Some(alt) = exif.gps_altitude() {
+                                        ui.label("Altitude:");
+                                        ui.label(format!("{:.1} m", alt));
+                                        ui.end_row();
+                                    }
+                                    if let Some((direction, magnetic)) = exif.gps_direction() {
+                                        ui.label("Direction:");
+                                        let north = if magnetic { "magnetic north" } else { "true north" };
+                                        ui.label(format!("{:.1}° ({})", direction, north));
+                                        ui.end_row();
+                                    }
+
                                     ui.label("Map:");
-                                    let map_url = format!(
-                                        "https://www.google.com/maps/place/{:.6},{:.6}",
-                                        la_, lo_
-                                    );
-                                    if ui.link("Open in browser 🌍").clicked() {
-                                        if let Err(e) = webbrowser::open(&map_url) {
-                                            eprintln!("Can not open the Browser: {}", e);
+                                    let map_url = crate::map::map_url(&self.config.map_url, la_, lo_);
+                                    ui.horizontal(|ui| {
+                                        if ui.link("Show map 🗺").clicked() {
+                                            open_map = true;
                                         }
-                                    }
+                                        if ui.link("Open in browser 🌍").on_hover_text(&map_url).clicked() {
+                                            if let Err(e) = webbrowser::open(&map_url) {
+                                                eprintln!("Can not open the Browser: {}", e);
+                                            }
+                                        }
+                                        egui::ComboBox::from_id_salt("map_provider")
+                                            .selected_text(crate::map::map_provider_name(&self.config.map_url))
+                                            .show_ui(ui, |ui| {
+                                                for (name, template) in crate::map::MAP_PROVIDERS {
+                                                    ui.selectable_value(&mut self.config.map_url, template.to_string(), name);
+                                                }
+                                            });
+                                    });
+                                    ui.end_row();
+                                }
+                            }
+
+                            // XMP / IPTC
+                            if let Some(meta) = &self.xmp {
+                                if let Some(title) = &meta.title {
+                                    ui.label("Title:");
+                                    ui.label(title);
+                                    ui.end_row();
+                                }
+                                if let Some(caption) = &meta.caption {
+                                    ui.label("Caption:");
+                                    ui.label(caption);
+                                    ui.end_row();
+                                }
+                                if !meta.creator.is_empty() {
+                                    ui.label("Creator:");
+                                    ui.label(meta.creator.join(", "));
+                                    ui.end_row();
+                                }
+                                if let Some(rights) = &meta.rights {
+                                    ui.label("Rights:");
+                                    ui.label(rights);
+                                    ui.end_row();
+                                }
+                                if !meta.keywords.is_empty() {
+                                    ui.label("Keywords:");
+                                    ui.label(meta.keywords.join(", "));
+                                    ui.end_row();
+                                }
+                                if let Some(rating) = meta.rating {
+                                    ui.label("Rating:");
+                                    // -1: elutasított
+                                    let stars = if rating < 0 { "✖".to_string() } else { "★".repeat(rating.min(5) as usize) + &"☆".repeat(5 - rating.min(5) as usize) };
+                                    ui.label(stars);
                                     ui.end_row();
                                 }
                             }
                         });
-                        if self.exif.is_some() {
+                        let has_details = self.exif.is_some() || self.xmp.is_some();
+                        if has_details || crate::exif_edit::can_write_exif(self.image_format) {
                             ui.separator();
-                            if ui.button("EXIF adatok részletezése").clicked() {
-                                self.show_exif_details = !self.show_exif_details;
-                            }
+                            ui.horizontal(|ui| {
+                                if has_details && ui.button("EXIF adatok részletezése").clicked() {
+                                    self.show_exif_details = !self.show_exif_details;
+                                }
+                                if ui.button("Edit metadata ✏").clicked() {
+                                    open_editor = true;
+                                }
+                            });
 
                             if self.show_exif_details {
+                                ui.horizontal(|ui| {
+                                    ui.label("🔍");
+                                    ui.add(egui::TextEdit::singleline(&mut self.metadata_filter).hint_text("Search in metadata"));
+                                });
+                                let filter = self.metadata_filter.to_lowercase();
+                                let visible = |name: &str, value: &str| {
+                                    filter.is_empty() || name.to_lowercase().contains(&filter) || value.to_lowercase().contains(&filter)
+                                };
                                 egui::ScrollArea::vertical()
                                     .max_height(300.0) // Korlátozzuk a magasságot, hogy ne nyúljon túl
                                     .show(ui, |ui| {
                                         ui.group(|ui| {
                                             if let Some(exif) = &self.exif {
                                                 for (name, val, _off) in exif.fields() {
+                                                    let value = val.get("val").unwrap_or(val).to_string();
+                                                    if !visible(name, &value) {
+                                                        continue;
+                                                    }
                                                     ui.horizontal(|ui| {
                                                         // Tag neve (pl. "Make", "DateTime")
-                                                        ui.label(egui::RichText::new(format!("{}:", name.to_string())).strong());
-                                                        if let Some(v) = val.get("val") {
-                                                            ui.label(v.to_string());
-                                                        }
-                                                        else { ui.label(val.to_string()); }
+                                                        ui.label(egui::RichText::new(format!("{}:", name)).strong());
+                                                        ui.label(value);
+                                                    });
+                                                }
+                                            }
+                                            if let Some(meta) = &self.xmp {
+                                                for (name, value) in meta.fields.iter().filter(|(n, v)| visible(n, v)) {
+                                                    ui.horizontal(|ui| {
+                                                        ui.label(egui::RichText::new(format!("{}:", name)).strong());
+                                                        ui.label(value);
                                                     });
                                                 }
                                             }
@@ -333,12 +458,86 @@ impl ImageViewer {
                 });
         }
 
+        if open_editor {
+            self.open_exif_editor();
+        }
+
+        if open_map {
+            self.open_map(false);
+        }
+        self.draw_map(ctx);
+        self.draw_geotag(ctx);
+
+        if self.exif_editor.is_some() {
+            let mut open = true;
+            let mut apply = None;
+            let can_write = crate::exif_edit::can_write_exif(self.image_format) && self.image_full_path.is_some();
+            egui::Window::new("iView ✏ Edit Metadata")
+                .open(&mut open)
+                .show(ctx, |ui| {
+                    if let Some(editor) = &mut self.exif_editor {
+                        egui::Grid::new("exif_editor_grid")
+                            .num_columns(2)
+                            .spacing([20.0, 4.0])
+                            .show(ui, |ui| {
+                                ui.label("Artist:");
+                                ui.text_edit_singleline(&mut editor.artist);
+                                ui.end_row();
+                                ui.label("Copyright:");
+                                ui.text_edit_singleline(&mut editor.copyright);
+                                ui.end_row();
+                                ui.label("Description:");
+                                ui.text_edit_multiline(&mut editor.description);
+                                ui.end_row();
+                                ui.label("Date taken:");
+                                ui.add(egui::TextEdit::singleline(&mut editor.date_original).hint_text("YYYY:MM:DD HH:MM:SS"));
+                                ui.end_row();
+                                ui.label("Latitude:");
+                                ui.add(egui::TextEdit::singleline(&mut editor.latitude).hint_text("47.497912"));
+                                ui.end_row();
+                                ui.label("Longitude:");
+                                ui.add(egui::TextEdit::singleline(&mut editor.longitude).hint_text("19.040235"));
+                                ui.end_row();
+                                ui.label("Altitude (m):");
+                                ui.text_edit_singleline(&mut editor.altitude);
+                                ui.end_row();
+                            });
+                        if let Some(error) = &editor.error {
+                            ui.colored_label(egui::Color32::RED, error);
+                        }
+                        ui.separator();
+                        ui.horizontal(|ui| {
+                            if ui.button("Apply").on_hover_text("Only in memory, used at next save").clicked() {
+                                apply = Some(false);
+                            }
+                            if ui.add_enabled(can_write, egui::Button::new("Write to file")).clicked() {
+                                apply = Some(true);
+                            }
+                        });
+                    }
+                });
+            if let Some(write) = apply {
+                match self.apply_exif_editor(write) {
+                    Ok(()) => self.exif_editor = None,
+                    Err(e) => {
+                        println!("EXIF írási hiba: {}", e);
+                        if let Some(editor) = &mut self.exif_editor {
+                            editor.error = Some(e);
+                        }
+                    }
+                }
+            }
+            if !open {
+                self.exif_editor = None;
+            }
+        }
+
         if self.color_correction_dialog && !self.menvar.hided {
             let mut changed = false;
             ctx.show_viewport_immediate(
                 egui::ViewportId::from_hash_of("colorcorrection_viewport"),
                 egui::ViewportBuilder::default()
-                .with_inner_size([440.0, if self.hist.len() != 1024 { 400.0 } else { 550.0 }])
+                .with_inner_size([440.0, if self.hist.len() != 1024 { 450.0 } else { 600.0 }])
                 .with_decorations(false)
                 .with_always_on_top(),
                 |ctx, _| {
@@ -389,6 +588,8 @@ impl ImageViewer {
 
                 ui.separator(); // Ez választja el a fejlécet a tartalomtól
 
+                egui::ScrollArea::vertical().show(ui, |ui| {
+
                 ui.spacing_mut().slider_width = 300.0;
 
                  ui.horizontal(|ui| {
@@ -416,6 +617,11 @@ impl ImageViewer {
                             self.color_settings.show_b = !self.color_settings.show_b;
                             changed = true;
                         }
+                        let alpha_only = self.alpha_view == crate::alpha::AlphaView::Alpha;
+                        if ui.selectable_label(alpha_only, " A ").on_hover_text("Show the alpha channel only").clicked() {
+                            self.alpha_view = if alpha_only { crate::alpha::AlphaView::Normal } else { crate::alpha::AlphaView::Alpha };
+                            changed = true;
+                        }
                 });
 
                 let gam = ui.add(egui::Slider::new(
@@ -499,6 +705,22 @@ impl ImageViewer {
 
                 });                
 
+                // --- Objektív korrekció (minden más szűrő előtt fut) ---
+                egui::CollapsingHeader::new(egui::RichText::new("Lens Correction & Vignette").strong())
+                    .id_salt("lens_correction")
+                    .default_open(self.color_settings.has_lens_correction())
+                    .show(ui, |ui| {
+                        self.lens_panel(ui, &mut changed);
+                    });
+
+                // --- Zajszűrés (az élesítés előtt fut) ---
+                egui::CollapsingHeader::new(egui::RichText::new("Noise Reduction").strong())
+                    .id_salt("denoise")
+                    .default_open(self.color_settings.has_denoise())
+                    .show(ui, |ui| {
+                        self.denoise_panel(ui, &mut changed);
+                    });
+
                 // --- Élesítés / Blur (GPU előkészítés) ---
                 ui.label(egui::RichText::new("Sharpen (Amount > 0) & Blur (Amount < 0)").strong());
                 ui.horizontal(|ui| {
@@ -541,6 +763,32 @@ impl ImageViewer {
                     }
                 });
 
+                ui.horizontal(|ui| {
+                    let res = ui.add(egui::Slider::new(
+                        &mut self.color_settings.sharpen_threshold, 0.0..=64.0)
+                        .text("Threshold"))
+                        .on_hover_text("Differences below this level are not sharpened (noise, JPEG blocks)");
+                    if self.gpu_interface.is_none() {
+                        if res.drag_stopped() || (res.changed() && !ui.input(|i| i.pointer.any_down())) {
+                            changed = true;
+                        }
+                    }
+                    else {
+                        if res.changed() {
+                            changed = true;
+                        }
+                    }
+                    if ui.button("⟲").on_hover_text("Reset Threshold").clicked() {
+                        self.color_settings.sharpen_threshold = 0.0;
+                        changed = true;
+                    }
+                    if ui.checkbox(&mut self.color_settings.sharpen_luma, "Luminance only")
+                        .on_hover_text("Sharpen brightness only, avoids color fringing")
+                        .changed() {
+                        changed = true;
+                    }
+                });
+
 
                 ui.group(|ui| {
                     if ui.checkbox(&mut self.color_settings.use_transparency, "Use transparency color").changed() {
@@ -591,8 +839,51 @@ impl ImageViewer {
                             changed=true;
                         }
                     });
+                    self.magic_wand_panel(ui, &mut changed);
                 });
 
+                egui::CollapsingHeader::new(egui::RichText::new("Alpha Channel").strong())
+                    .id_salt("alpha_channel")
+                    .default_open(self.color_settings.has_alpha_edits() || self.alpha_view != crate::alpha::AlphaView::Normal)
+                    .show(ui, |ui| {
+                        self.alpha_panel(ui, &mut changed);
+                    });
+
+                egui::CollapsingHeader::new(egui::RichText::new("Vibrance, Clarity, Shadows/Highlights").strong())
+                    .id_salt("local_adjust")
+                    .default_open(self.color_settings.has_local_adjustments())
+                    .show(ui, |ui| {
+                        self.local_adjust_panel(ui, &mut changed);
+                    });
+
+                egui::CollapsingHeader::new(egui::RichText::new("HSL Mixer").strong())
+                    .id_salt("hsl_mixer")
+                    .default_open(self.color_settings.has_hsl())
+                    .show(ui, |ui| {
+                        self.hsl_panel(ui, &mut changed);
+                    });
+
+                egui::CollapsingHeader::new(egui::RichText::new("White Balance").strong())
+                    .id_salt("white_balance")
+                    .default_open(self.color_settings.has_white_balance())
+                    .show(ui, |ui| {
+                        self.white_balance_panel(ui, &mut changed);
+                    });
+
+                egui::CollapsingHeader::new(egui::RichText::new("Levels").strong())
+                    .id_salt("levels")
+                    .default_open(self.color_settings.has_levels())
+                    .show(ui, |ui| {
+                        self.levels_panel(ui, &mut changed);
+                    });
+
+                egui::CollapsingHeader::new(egui::RichText::new("Tone Curves").strong())
+                    .id_salt("tone_curves")
+                    .default_open(self.color_settings.has_curves())
+                    .show(ui, |ui| {
+                        self.curves_panel(ui, &mut changed);
+                    });
+
                 if self.hist.len() == 1024 {
                 let max_val = if self.show_rgb_histogram {
                     self.hist[0..768].iter().cloned().max().unwrap_or(1) as f32
@@ -711,6 +1002,7 @@ impl ImageViewer {
                         changed = true;
                     }
                        
+                });
                 });
                 if ctx.input(|i| i.viewport().close_requested()) {
                     self.color_correction_dialog = false;
@@ -168,8 +168,11 @@ impl ImageViewer {
                         self.review(ctx, true, false);
                     }*/
 
-                    // Csak akkor fut le, ha a Ctrl le van nyomva
-                    if ctx.input(|i| i.modifiers.ctrl ) {
+                    // Csak akkor fut le, ha a Ctrl le van nyomva, vagy pipetta van kiválasztva
+                    if self.eyedropper.is_some() {
+                        ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
+                    }
+                    if ctx.input(|i| i.modifiers.ctrl ) || self.eyedropper.is_some() {
                         if let Some(pointer_pos) = ctx.pointer_latest_pos() {
                             let inner_rect = output.inner_rect;
                             if inner_rect.contains(pointer_pos) {
@@ -199,7 +202,17 @@ impl ImageViewer {
                                             });
                                         }
                                     );
-                                    if ctx.input(|i| i.pointer.primary_clicked()) {
+                                    if ctx.input(|i| i.pointer.primary_clicked()) && let Some(tool) = self.eyedropper.take() {
+                                        self.apply_eyedropper(ctx, tool, pixel_x, pixel_y);
+                                    }
+                                    else if ctx.input(|i| i.pointer.primary_clicked()) && self.color_settings.transparency_flood {
+                                        // varázspálca: a kattintott pont lesz a kiindulópont
+                                        self.set_flood_seed(pixel_x, pixel_y);
+                                        if self.color_settings.use_transparency {
+                                            self.review(ctx, true, false);
+                                        }
+                                    }
+                                    else if ctx.input(|i| i.pointer.primary_clicked()) {
                                         // Átváltjuk a színt f32-re a shader/korrekció számára
                                         self.color_settings.transparent_color = [
                                             color.r() as u8,
@@ -45,24 +45,28 @@ impl ImageViewer {
     }
 
     pub fn anim_prev_frame(&mut self, ctx: &egui::Context){
-        if let Some(anim) = &self.anim_data {
+        if self.anim_data.is_some() {
             self.anim_playing = false;
             if self.current_frame == 0 {
                 self.current_frame = self.total_frames - 1;
             } else {
                 self.current_frame -= 1;
             }
-            self.original_image = Some(anim.anim_frames[self.current_frame].clone());
+            if let Some(frame) = self.frame_image(self.current_frame) {
+                self.original_image = Some(frame);
+            }
             self.review(ctx, true, false);
             ctx.request_repaint();
         }
     }
 
     pub fn anim_next_frame(&mut self, ctx: &egui::Context){
-        if let Some(anim) = &self.anim_data {
+        if self.anim_data.is_some() {
             self.anim_playing = false;
             self.current_frame = (self.current_frame + 1) % self.total_frames;
-            self.original_image = Some(anim.anim_frames[self.current_frame].clone());
+            if let Some(frame) = self.frame_image(self.current_frame) {
+                self.original_image = Some(frame);
+            }
             self.review(ctx, true, false);
             ctx.request_repaint();
         }
@@ -106,7 +110,9 @@ impl ImageViewer {
 
                         // Textúra frissítése a megjelenítéshez
                         if self.current_frame != old_frame {
-                            self.original_image = Some(anim.anim_frames[self.current_frame].clone());
+                            if let Some(frame) = self.frame_image(self.current_frame) {
+                                self.original_image = Some(frame);
+                            }
                             self.review(ctx, true, false);
                             // Azonnali újrarajzolás a váltás után
                             ctx.request_repaint();
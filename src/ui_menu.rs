@@ -331,7 +331,8 @@ impl ImageViewer {
                 self.navigation(ctx, 1);
             }
 
-            if self.anim_data.is_some() {
+            if let Some(anim) = &self.anim_data {
+                let is_pages = anim.is_pages;
                 
                 separator(ui);
                 
@@ -340,8 +341,8 @@ impl ImageViewer {
                 } else {
                     "▶ Play"
                 };
-                if ui.button(play_btn).clicked()
-                    || ui.input(|i| i.key_pressed(egui::Key::Space))
+                if !is_pages && (ui.button(play_btn).clicked()
+                    || ui.input(|i| i.key_pressed(egui::Key::Space)))
                 {
                     self.menvar.change_menu(ctx,Menu::None);
                     self.anim_play_stop(ctx);
@@ -365,7 +366,8 @@ impl ImageViewer {
                     self.anim_next_frame(ctx);
                 }
                 ui.label(format!(
-                    "Frame: {} / {}",
+                    "{}: {} / {}",
+                    if is_pages { "Page" } else { "Frame" },
                     self.current_frame + 1,
                     self.total_frames
                 ));
@@ -418,6 +420,16 @@ impl ImageViewer {
                 self.menvar.change_menu(ctx,Menu::Recents);
             }
 
+            if ui.button("Export folder metadata ...").clicked() {
+                self.menvar.change_menu(ctx,Menu::None);
+                self.export_folder_metadata();
+            }
+
+            if ui.button("Geotag from GPX ...").clicked() {
+                self.menvar.change_menu(ctx,Menu::None);
+                self.open_geotag();
+            }
+
             separator(ui);
 
             let copy_button = egui::Button::new("Copy").shortcut_text(ctx.format_shortcut(
@@ -576,6 +588,13 @@ impl ImageViewer {
                 self.show_info = true;
                 self.menvar.change_menu(ctx,Menu::None);
             }
+            let map_button = egui::Button::new("Map                               ").shortcut_text(ctx.format_shortcut(
+                &egui::KeyboardShortcut::new(egui::Modifiers::NONE, egui::Key::M),
+            ));
+            if ui.add(map_button).clicked() {
+                self.open_map(true);
+                self.menvar.change_menu(ctx,Menu::None);
+            }
             
             let position_btn = ui.button("Window position        >");
             if position_btn.clicked() {
@@ -623,6 +642,20 @@ impl ImageViewer {
                 self.menvar.change_menu(ctx,Menu::None);
             }
 
+            if ui.selectable_label(self.color_management, "Color Management").clicked()
+            {
+                self.color_management = !self.color_management;
+                self.update_icc_transform();
+                self.review(ctx, true, false);
+                self.menvar.change_menu(ctx,Menu::None);
+            }
+
+            let profile_txt = if self.display_profile.is_some() { "Display Profile ✔" } else { "Display Profile ..." };
+            if ui.button(profile_txt).clicked() {
+                self.menvar.change_menu(ctx,Menu::None);
+                self.choose_display_profile(ctx);
+            }
+
         });
 
         // sort menu
@@ -813,6 +846,13 @@ impl ImageViewer {
                 self.color_settings.invert = !self.color_settings.invert;
                 self.review(ctx, true, false);
             }
+
+            let alpha_only = self.alpha_view == crate::alpha::AlphaView::Alpha;
+            if ui.button(format!("Alpha{}", if alpha_only { "✔" } else { "" })).clicked() {
+                self.menvar.change_menu(ctx,Menu::None);
+                self.alpha_view = if alpha_only { crate::alpha::AlphaView::Normal } else { crate::alpha::AlphaView::Alpha };
+                self.review(ctx, true, false);
+            }
         });
 
         // rotate menu
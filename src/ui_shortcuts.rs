@@ -198,6 +198,18 @@ impl ImageViewer {
         }) {
             // info
             self.show_info = !self.show_info;
+        } else if ctx.input_mut(|i| {
+            i.consume_shortcut(&egui::KeyboardShortcut::new(
+                egui::Modifiers::NONE,
+                egui::Key::M,
+            ))
+        }) {
+            // térkép
+            if self.map_view.is_some() {
+                self.map_view = None;
+            } else {
+                self.open_map(true);
+            }
         } else if ctx.input_mut(|i| {
             i.consume_shortcut(&egui::KeyboardShortcut::new(
                 egui::Modifiers::NONE,
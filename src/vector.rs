@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+use resvg::{tiny_skia, usvg};
+use pdfium_render::prelude::{Pdfium, PdfDocument, PdfRenderConfig};
+
+use crate::ImageViewer;
+
+// SVG/SVGZ és PDF: a vektoros képet mindig az aktuális nagyításon rajzoljuk újra
+
+// A rendszer betűkészleteinek beolvasása lassú, ezért csak az első SVG megnyitásakor, utána közösen használjuk
+static SYSTEM_FONTS: LazyLock<Arc<usvg::fontdb::Database>> = LazyLock::new(|| {
+    let mut fontdb = usvg::fontdb::Database::new();
+    fontdb.load_system_fonts();
+    Arc::new(fontdb)
+});
+
+pub struct SvgDocument {
+    pub tree: usvg::Tree,
+    pub scale: f32,        // ezen a nagyításon készült a legutóbbi raszter
+    pub render_scale: f32, // a ténylegesen használt nagyítás (textúra korlát miatt kisebb lehet)
+}
+
+impl SvgDocument {
+    pub fn open(path: &PathBuf) -> Result<Self, String> {
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let options = usvg::Options {
+            resources_dir: path.parent().map(|p| p.to_path_buf()),
+            fontdb: SYSTEM_FONTS.clone(),
+            ..Default::default()
+        };
+        // a gzip-elt (svgz) adatot a from_data maga bontja ki
+        let tree = usvg::Tree::from_data(&data, &options).map_err(|e| e.to_string())?;
+        Ok(Self { tree, scale: 1.0, render_scale: 1.0 })
+    }
+
+    pub fn size(&self) -> (f32, f32) {
+        let size = self.tree.size();
+        (size.width(), size.height())
+    }
+
+    // Raszterizálás adott nagyítással; a max_side a textúra méretkorlát
+    pub fn render(&self, scale: f32, max_side: u32) -> Option<(image::DynamicImage, f32)> {
+        let (w, h) = self.size();
+        let limit = (max_side as f32 / w.max(h)).max(0.01);
+        let scale = scale.min(limit);
+        let width = ((w * scale).ceil() as u32).max(1);
+        let height = ((h * scale).ceil() as u32).max(1);
+        let mut pixmap = tiny_skia::Pixmap::new(width, height)?;
+        resvg::render(&self.tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+        // a tiny-skia premultiplikált színeket ad
+        let data: Vec<u8> = pixmap.pixels().iter().flat_map(|p| {
+            let c = p.demultiply();
+            [c.red(), c.green(), c.blue(), c.alpha()]
+        }).collect();
+        image::RgbaImage::from_raw(width, height, data).map(|img| (image::DynamicImage::ImageRgba8(img), scale))
+    }
+}
+
+// A pdfium könyvtárat a program mellől vagy a rendszerből egyszer töltjük be, minden PDF ezt használja
+static PDFIUM: LazyLock<Result<Pdfium, String>> = LazyLock::new(|| {
+    let exe_dir = std::env::current_exe().ok()
+        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+        .unwrap_or_else(|| PathBuf::from("./"));
+    Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&exe_dir))
+        .or_else(|_| Pdfium::bind_to_system_library())
+        .map(Pdfium::new)
+        .map_err(|e| format!("pdfium könyvtár nem található: {}", e))
+});
+
+// PDF oldalak a pdfium könyvtárral; a dokumentum nyitva marad, az oldalakat kéréskor rajzoljuk
+pub struct PdfFile {
+    document: PdfDocument<'static>,
+    pub page_count: usize,
+    pub scale: f32,
+    pub render_scale: f32,
+}
+
+impl PdfFile {
+    pub fn open(path: &PathBuf) -> Result<Self, String> {
+        let pdfium = PDFIUM.as_ref().map_err(|e| e.clone())?;
+        let data = std::fs::read(path).map_err(|e| e.to_string())?;
+        let document = pdfium.load_pdf_from_byte_vec(data, None).map_err(|e| e.to_string())?;
+        let page_count = document.pages().len() as usize;
+        Ok(Self { document, page_count, scale: 1.0, render_scale: 1.0 })
+    }
+
+    // 1.0 nagyítás = 72 dpi
+    pub fn render(&self, page: usize, scale: f32, max_side: u32) -> Option<(image::DynamicImage, f32)> {
+        let page = self.document.pages().get(page as u16).ok()?;
+        let (w, h) = (page.width().value, page.height().value);
+        let limit = (max_side as f32 / w.max(h)).max(0.01);
+        let scale = scale.min(limit);
+        let bitmap = page.render_with_config(&PdfRenderConfig::new().scale_page_by_factor(scale)).ok()?;
+        Some((bitmap.as_image(), scale))
+    }
+}
+
+impl ImageViewer {
+
+    // Ha a nagyítás változott, a vektort újrarajzoljuk, hogy éles maradjon
+    pub fn refresh_vector(&mut self, ctx: &egui::Context) {
+        let scale = self.svg.as_ref().map(|svg| svg.scale)
+            .or(self.pdf.as_ref().map(|pdf| pdf.scale));
+        if scale.is_some_and(|scale| (scale - self.magnify).abs() > 0.001) {
+            self.review(ctx, true, false);
+        }
+    }
+
+    // A textúra és a logikai képméret aránya vektoros képnél
+    pub fn vector_render_scale(&self) -> Option<f32> {
+        self.svg.as_ref().map(|svg| svg.render_scale)
+            .or(self.pdf.as_ref().map(|pdf| pdf.render_scale))
+    }
+
+    // Az aktuális PDF oldal a kért felbontással (mentéshez)
+    pub fn render_pdf_page(&self, page: usize, dpi: f32) -> Option<image::DynamicImage> {
+        self.pdf.as_ref()?.render(page, dpi / 72.0, 16384).map(|(img, _)| img)
+    }
+}
@@ -0,0 +1,173 @@
+use crate::colors::ColorSettings;
+use crate::ImageViewer;
+
+// Fehéregyensúly: színhőmérséklet (Kelvin) és zöld-bíbor árnyalat, csatornánkénti szorzóként
+// a színkorrekció legelején. A fényforrás színhőmérsékletét adjuk meg: alacsonyabb érték hidegebb képet ad.
+
+pub const NEUTRAL_TEMPERATURE: f32 = 6500.0;
+pub const MIN_TEMPERATURE: f32 = 2000.0;
+pub const MAX_TEMPERATURE: f32 = 12000.0;
+const WHITE_PATCH: f64 = 0.995; // a fehér folt módszernél a legvilágosabb 0.5% átlaga helyett ez a percentilis
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum AutoWhiteBalance {
+    GrayWorld,  // az átlagszín legyen szürke
+    WhitePatch, // a legvilágosabb részek legyenek fehérek
+}
+
+// Fekete test színe sRGB-ben (Tanner Helland közelítése)
+fn kelvin_rgb(kelvin: f32) -> [f32; 3] {
+    let t = kelvin / 100.0;
+    let r = if t <= 66.0 { 255.0 } else { 329.69873 * (t - 60.0).powf(-0.13320476) };
+    let g = if t <= 66.0 { 99.4708 * t.ln() - 161.11957 } else { 288.12216 * (t - 60.0).powf(-0.07551485) };
+    let b = if t >= 66.0 { 255.0 } else if t <= 19.0 { 0.0 } else { 138.51773 * (t - 10.0).ln() - 305.0448 };
+    [r, g, b].map(|c| (c / 255.0).clamp(0.05, 1.0))
+}
+
+// A szorzók világosságra normálva, hogy a kép fényereje ne változzon
+pub fn wb_gains(temperature: f32, tint: f32) -> [f32; 3] {
+    let white = kelvin_rgb(NEUTRAL_TEMPERATURE);
+    let light = kelvin_rgb(temperature.clamp(MIN_TEMPERATURE, MAX_TEMPERATURE));
+    let mut gains = [white[0] / light[0], white[1] / light[1], white[2] / light[2]];
+    gains[1] *= 2.0f32.powf(-tint); // pozitív árnyalat: bíbor felé
+    let luma = 0.2126 * gains[0] + 0.7152 * gains[1] + 0.0722 * gains[2];
+    gains.map(|g| g / luma)
+}
+
+// Az a hőmérséklet és árnyalat, amellyel a megadott szín semleges szürke lesz
+pub fn neutral_settings(color: [f32; 3]) -> (f32, f32) {
+    let color = color.map(|c| c.max(0.001));
+    // a vörös/kék arányt csak a hőmérséklet határozza meg; a kék/vörös szorzó aránya a hőmérséklettel
+    // monoton csökken (3000 K: 2.27, 6500 K: 1.0, 10000 K: 0.78), ezért túl kicsi aránynál lejjebb megyünk
+    let target = color[0] / color[2];
+    let ratio = |t: f32| {
+        let g = wb_gains(t, 0.0);
+        g[2] / g[0]
+    };
+    let (mut lo, mut hi) = (MIN_TEMPERATURE, MAX_TEMPERATURE);
+    for _ in 0..30 {
+        let mid = (lo + hi) / 2.0;
+        if ratio(mid) < target {
+            hi = mid;
+        } else {
+            lo = mid;
+        }
+    }
+    let temperature = (lo + hi) / 2.0;
+    // a zöld szorzó a vörös csatorna eredményéhez igazítva
+    let g = wb_gains(temperature, 0.0);
+    let green_gain = g[0] * color[0] / color[1];
+    let tint = (-(green_gain / g[1]).log2()).clamp(-1.0, 1.0);
+    (temperature.round(), tint)
+}
+
+impl ColorSettings {
+    pub fn has_white_balance(&self) -> bool {
+        (self.temperature - NEUTRAL_TEMPERATURE).abs() >= 1.0 || self.tint.abs() >= 0.001
+    }
+
+    pub fn white_balance_gains(&self) -> [f32; 3] {
+        if self.has_white_balance() { wb_gains(self.temperature, self.tint) } else { [1.0, 1.0, 1.0] }
+    }
+}
+
+impl ImageViewer {
+    // A korrekciók előtti szín semlegesre állítva; a fehéregyensúly az első lépés, így nincs mit visszaszámolni
+    pub fn neutralize_color(&mut self, color: [f32; 3]) {
+        let (temperature, tint) = neutral_settings(color);
+        self.color_settings.temperature = temperature;
+        self.color_settings.tint = tint;
+    }
+
+    // A korrekciók előtti kép hisztogramjából becsüljük a fényforrás színét
+    pub fn auto_white_balance(&mut self, method: AutoWhiteBalance) {
+        let Some(hist) = self.source_histogram(|_| {}) else {
+            return;
+        };
+        let mut color = [0.0f32; 3];
+        for (c, value) in color.iter_mut().enumerate() {
+            let bins = &hist[c * 256..c * 256 + 256];
+            let total: u64 = bins.iter().map(|b| *b as u64).sum();
+            if total == 0 {
+                return;
+            }
+            *value = match method {
+                AutoWhiteBalance::GrayWorld => {
+                    let sum: u64 = bins.iter().enumerate().map(|(i, b)| i as u64 * *b as u64).sum();
+                    sum as f32 / total as f32 / 255.0
+                }
+                AutoWhiteBalance::WhitePatch => {
+                    let limit = (total as f64 * WHITE_PATCH) as u64;
+                    let mut sum = 0u64;
+                    let idx = bins.iter().position(|b| {
+                        sum += *b as u64;
+                        sum > limit
+                    }).unwrap_or(255);
+                    idx as f32 / 255.0
+                }
+            };
+        }
+        self.neutralize_color(color);
+    }
+
+    pub fn white_balance_panel(&mut self, ui: &mut egui::Ui, changed: &mut bool) {
+        let gpu = self.gpu_interface.is_some();
+        let slider_changed = |ui: &egui::Ui, res: &egui::Response| {
+            if gpu { res.changed() } else { res.drag_stopped() || (res.changed() && !ui.input(|i| i.pointer.any_down())) }
+        };
+        ui.horizontal(|ui| {
+            let res = ui.add(egui::Slider::new(&mut self.color_settings.temperature, MIN_TEMPERATURE..=MAX_TEMPERATURE)
+                .logarithmic(true)
+                .suffix(" K")
+                .text("Temperature"));
+            *changed |= slider_changed(ui, &res);
+            if ui.button("⟲").on_hover_text("Reset Temperature").clicked() {
+                self.color_settings.temperature = NEUTRAL_TEMPERATURE;
+                *changed = true;
+            }
+        });
+        ui.horizontal(|ui| {
+            let res = ui.add(egui::Slider::new(&mut self.color_settings.tint, -1.0..=1.0)
+                .text("Tint"))
+                .on_hover_text("Negative: green, positive: magenta");
+            *changed |= slider_changed(ui, &res);
+            if ui.button("⟲").on_hover_text("Reset Tint").clicked() {
+                self.color_settings.tint = 0.0;
+                *changed = true;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Auto:");
+            if ui.button("Gray World").on_hover_text("Make the average color neutral").clicked() {
+                self.auto_white_balance(AutoWhiteBalance::GrayWorld);
+                *changed = true;
+            }
+            if ui.button("White Patch").on_hover_text("Make the brightest areas white").clicked() {
+                self.auto_white_balance(AutoWhiteBalance::WhitePatch);
+                *changed = true;
+            }
+            ui.add_space(20.0);
+            let tool = crate::levels::Eyedropper::Neutral;
+            if ui.selectable_label(self.eyedropper == Some(tool), "💧 Neutral").on_hover_text("Click on something gray or white in the image").clicked() {
+                self.eyedropper = if self.eyedropper == Some(tool) { None } else { Some(tool) };
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn neutral_color_becomes_gray() {
+        let color = [0.8, 0.6, 0.4];
+        let (temperature, tint) = neutral_settings(color);
+        assert!(temperature < NEUTRAL_TEMPERATURE);
+        let gains = wb_gains(temperature, tint);
+        let out: Vec<f32> = (0..3).map(|c| color[c] * gains[c]).collect();
+        assert!((out[0] - out[1]).abs() < 0.005 * out[1] && (out[2] - out[1]).abs() < 0.005 * out[1], "{:?}", out);
+
+        assert_eq!(neutral_settings([0.5, 0.5, 0.5]), (NEUTRAL_TEMPERATURE, 0.0));
+    }
+}
@@ -0,0 +1,238 @@
+use std::path::PathBuf;
+
+use crate::file_handlers::SaveFormat;
+
+// XMP (JPEG APP1, PNG iTXt, WebP "XMP " chunk, TIFF 700-as tag) és IPTC-IIM (JPEG APP13) olvasása
+
+const XMP_JPEG_PREFIX: &[u8] = b"http://ns.adobe.com/xap/1.0/\0";
+const PHOTOSHOP_PREFIX: &[u8] = b"Photoshop 3.0\0";
+const NS_RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const NS_DC: &str = "http://purl.org/dc/elements/1.1/";
+const NS_XMP: &str = "http://ns.adobe.com/xap/1.0/";
+
+#[derive(Clone, Debug, Default)]
+pub struct XmpIptc {
+    pub title: Option<String>,
+    pub caption: Option<String>,
+    pub creator: Vec<String>,
+    pub rights: Option<String>,
+    pub keywords: Vec<String>,
+    pub rating: Option<i32>,
+    pub fields: Vec<(String, String)>, // minden kiolvasott mező a részletekhez és a kereséshez
+}
+
+impl XmpIptc {
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    // Az XMP az elsődleges, az IPTC csak a hiányzó mezőket tölti ki
+    fn merge(&mut self, other: XmpIptc) {
+        if self.title.is_none() {
+            self.title = other.title;
+        }
+        if self.caption.is_none() {
+            self.caption = other.caption;
+        }
+        if self.creator.is_empty() {
+            self.creator = other.creator;
+        }
+        if self.rights.is_none() {
+            self.rights = other.rights;
+        }
+        if self.rating.is_none() {
+            self.rating = other.rating;
+        }
+        for keyword in other.keywords {
+            if !self.keywords.contains(&keyword) {
+                self.keywords.push(keyword);
+            }
+        }
+        self.fields.extend(other.fields);
+    }
+}
+
+pub fn read_metadata(buffer: &[u8], format: SaveFormat, path: &PathBuf) -> Option<XmpIptc> {
+    let mut xmp_packet = None;
+    let mut iptc_data = None;
+    match format {
+        SaveFormat::Jpeg => {
+            let jpeg = img_parts::jpeg::Jpeg::from_bytes(buffer.to_vec().into()).ok()?;
+            for segment in jpeg.segments() {
+                let contents = segment.contents();
+                match segment.marker() {
+                    0xE1 if xmp_packet.is_none() => {
+                        xmp_packet = contents.strip_prefix(XMP_JPEG_PREFIX).map(|x| x.to_vec());
+                    }
+                    0xED if iptc_data.is_none() => {
+                        iptc_data = contents.strip_prefix(PHOTOSHOP_PREFIX).and_then(photoshop_iptc);
+                    }
+                    _ => {}
+                }
+            }
+        }
+        SaveFormat::Png => {
+            let png = img_parts::png::Png::from_bytes(buffer.to_vec().into()).ok()?;
+            xmp_packet = png.chunks().iter()
+                .filter(|c| c.kind() == *b"iTXt")
+                .find_map(|c| png_itxt_xmp(c.contents()));
+        }
+        SaveFormat::Webp => {
+            let webp = img_parts::webp::WebP::from_bytes(buffer.to_vec().into()).ok()?;
+            xmp_packet = webp.chunk_by_id(*b"XMP ")
+                .and_then(|c| c.content().data())
+                .map(|d| d.to_vec());
+        }
+        SaveFormat::Tif => {
+            let file = std::fs::File::open(path).ok()?;
+            let mut decoder = tiff::decoder::Decoder::new(std::io::BufReader::new(file)).ok()?;
+            xmp_packet = decoder.get_tag_u8_vec(tiff::tags::Tag::Unknown(700)).ok();
+        }
+        _ => {}
+    }
+
+    let mut meta = xmp_packet.and_then(|x| parse_xmp(&x)).unwrap_or_default();
+    if let Some(iptc) = iptc_data.and_then(|d| parse_iptc(&d)) {
+        meta.merge(iptc);
+    }
+    (!meta.is_empty()).then_some(meta)
+}
+
+// iTXt: kulcsszó\0, tömörítés jelző, módszer, nyelv\0, fordított kulcsszó\0, szöveg
+// Az XMP-t a szabvány tömörítetlenül ajánlja, a tömörített chunk-ot kihagyjuk
+fn png_itxt_xmp(data: &[u8]) -> Option<Vec<u8>> {
+    let rest = data.strip_prefix(b"XML:com.adobe.xmp\0")?;
+    let (&compressed, rest) = rest.split_first()?;
+    if compressed != 0 {
+        return None;
+    }
+    let rest = rest.get(1..)?;
+    let lang_end = rest.iter().position(|b| *b == 0)?;
+    let rest = &rest[lang_end + 1..];
+    let translated_end = rest.iter().position(|b| *b == 0)?;
+    Some(rest[translated_end + 1..].to_vec())
+}
+
+// Photoshop képforrás blokkok ("8BIM"); a 0x0404 az IPTC-IIM
+fn photoshop_iptc(data: &[u8]) -> Option<Vec<u8>> {
+    let mut pos = 0;
+    while pos + 12 <= data.len() {
+        if &data[pos..pos + 4] != b"8BIM" {
+            return None;
+        }
+        let id = u16::from_be_bytes([data[pos + 4], data[pos + 5]]);
+        let name_len = data[pos + 6] as usize;
+        pos += 6 + ((name_len + 2) & !1); // a Pascal sztring párosra kiegészítve
+        let size_bytes = data.get(pos..pos + 4)?;
+        let size = u32::from_be_bytes(size_bytes.try_into().ok()?) as usize;
+        pos += 4;
+        let block = data.get(pos..pos.checked_add(size)?)?;
+        if id == 0x0404 {
+            return Some(block.to_vec());
+        }
+        pos += (size + 1) & !1;
+    }
+    None
+}
+
+// IPTC-IIM: 0x1C, rekord, adatkészlet, 16 bites hossz, adat
+pub fn parse_iptc(data: &[u8]) -> Option<XmpIptc> {
+    let mut meta = XmpIptc::default();
+    let mut utf8 = false;
+    let mut pos = 0;
+    while pos + 5 <= data.len() && data[pos] == 0x1C {
+        let (record, dataset) = (data[pos + 1], data[pos + 2]);
+        let len = u16::from_be_bytes([data[pos + 3], data[pos + 4]]) as usize;
+        if len & 0x8000 != 0 {
+            break; // kiterjesztett hossz: szöveges mezőknél nem fordul elő
+        }
+        pos += 5;
+        let Some(value) = data.get(pos..pos + len) else {
+            break;
+        };
+        pos += len;
+        if (record, dataset) == (1, 90) {
+            utf8 = value == b"\x1b%G"; // CodedCharacterSet: UTF-8
+            continue;
+        }
+        if record != 2 {
+            continue;
+        }
+        // UTF-8 jelölés nélkül is sokszor UTF-8, különben Latin-1
+        let text = match std::str::from_utf8(value) {
+            Ok(s) => s.to_string(),
+            Err(_) if !utf8 => value.iter().map(|&b| b as char).collect(),
+            Err(_) => String::from_utf8_lossy(value).to_string(),
+        };
+        let text = text.trim().to_string();
+        let name = match dataset {
+            5 => { meta.title.get_or_insert(text.clone()); "ObjectName" }
+            25 => { meta.keywords.push(text.clone()); "Keywords" }
+            55 => "DateCreated",
+            80 => { meta.creator.push(text.clone()); "By-line" }
+            90 => "City",
+            95 => "Province-State",
+            101 => "Country",
+            105 => "Headline",
+            110 => "Credit",
+            115 => "Source",
+            116 => { meta.rights.get_or_insert(text.clone()); "CopyrightNotice" }
+            120 => { meta.caption.get_or_insert(text.clone()); "Caption-Abstract" }
+            _ => continue,
+        };
+        meta.fields.push((format!("IPTC:{}", name), text));
+    }
+    (!meta.is_empty()).then_some(meta)
+}
+
+// Egy tulajdonság értéke: egyszerű szöveg, vagy rdf:Bag/Seq/Alt elemei
+fn property_values(node: roxmltree::Node) -> Vec<String> {
+    let items: Vec<String> = node.descendants()
+        .filter(|n| n.tag_name().namespace() == Some(NS_RDF) && n.tag_name().name() == "li")
+        .filter_map(|n| n.text())
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .collect();
+    if !items.is_empty() {
+        return items;
+    }
+    if node.children().any(|c| c.is_element()) {
+        return Vec::new(); // összetett struktúra, nem jelenítjük meg
+    }
+    node.text().map(|t| t.trim().to_string()).filter(|t| !t.is_empty()).into_iter().collect()
+}
+
+pub fn parse_xmp(packet: &[u8]) -> Option<XmpIptc> {
+    let text = String::from_utf8_lossy(packet);
+    let text = text.trim_matches(char::from(0));
+    let doc = roxmltree::Document::parse(text).ok()?;
+    let mut meta = XmpIptc::default();
+    let descriptions = doc.descendants()
+        .filter(|n| n.tag_name().namespace() == Some(NS_RDF) && n.tag_name().name() == "Description");
+    for description in descriptions {
+        // Rövidített forma: a tulajdonságok attribútumként
+        let attributes = description.attributes()
+            .filter(|a| a.namespace().is_some_and(|ns| ns != NS_RDF))
+            .map(|a| (a.namespace().unwrap_or_default(), a.name(), vec![a.value().to_string()]));
+        let elements = description.children()
+            .filter(|c| c.is_element())
+            .map(|c| (c.tag_name().namespace().unwrap_or_default(), c.tag_name().name(), property_values(c)));
+        for (ns, name, values) in attributes.chain(elements) {
+            if values.is_empty() {
+                continue;
+            }
+            match (ns, name) {
+                (NS_DC, "title") => meta.title = values.first().cloned(),
+                (NS_DC, "description") => meta.caption = values.first().cloned(),
+                (NS_DC, "creator") => meta.creator = values.clone(),
+                (NS_DC, "rights") => meta.rights = values.first().cloned(),
+                (NS_DC, "subject") => meta.keywords = values.clone(),
+                (NS_XMP, "Rating") => meta.rating = values.first().and_then(|r| r.parse::<f32>().ok()).map(|r| r as i32),
+                _ => {}
+            }
+            let prefix = description.lookup_prefix(ns).unwrap_or("xmp");
+            meta.fields.push((format!("{}:{}", prefix, name), values.join("; ")));
+        }
+    }
+    (!meta.is_empty()).then_some(meta)
+}